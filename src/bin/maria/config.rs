@@ -0,0 +1,181 @@
+//! Configuration file of `maria`, given by `--config`.
+//!
+//! The keys are the long names of the arguments. The keys at the top level
+//! apply to all the subcommands, and the keys in the table of a subcommand
+//! apply to it. The arguments on the command line take precedence.
+//!
+//! ```toml
+//! model = "model.bin"
+//!
+//! [train]
+//! input = ["corpus/", "extra.txt"]
+//! pattern = "*.txt"
+//! tokenizer = "lindera"
+//! normalize = ["html", "sns", "nfkc"]
+//! order = 1
+//!
+//! [generate]
+//! count = 10
+//! seed = 42
+//!
+//! [haiku]
+//! season = "autumn"
+//! tolerance = 1
+//! format = "slash"
+//! ```
+
+use std::fs;
+use std::str::FromStr;
+
+use clap::ArgMatches;
+use toml::value::Table;
+use toml::Value;
+
+/// The names of the tables of the subcommands.
+const SUBCOMMANDS: [&str; 6] = ["train", "generate", "haiku", "tanka", "inspect", "serve"];
+
+/// Loads the configuration file in TOML.
+pub(crate) fn load_config(path: &str) -> Result<Table, String> {
+    let contents = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    let config = toml::from_str::<Table>(&contents).map_err(|e| format!("{}: {}", path, e))?;
+    for (key, value) in &config {
+        if SUBCOMMANDS.contains(&key.as_str()) && !value.is_table() {
+            return Err(format!("{}: `{}` must be a table.", path, key));
+        }
+    }
+    Ok(config)
+}
+
+/// Arguments of a subcommand, which fall back to the configuration file and
+/// then to the default values of the arguments.
+pub(crate) struct Settings<'a> {
+    matches: &'a ArgMatches<'a>,
+    section: Option<&'a Table>,
+    global: Option<&'a Table>,
+}
+
+impl<'a> Settings<'a> {
+    /// Creates a new instance of [`Settings`] of the subcommand.
+    pub(crate) fn new(
+        matches: &'a ArgMatches<'a>,
+        config: Option<&'a Table>,
+        subcommand: &str,
+    ) -> Settings<'a> {
+        Settings {
+            matches,
+            section: config
+                .and_then(|c| c.get(subcommand))
+                .and_then(|s| s.as_table()),
+            global: config,
+        }
+    }
+
+    /// Returns the values of the argument in the configuration file.
+    fn configured(&self, name: &str) -> Option<&'a Value> {
+        self.section
+            .and_then(|s| s.get(name))
+            .or_else(|| self.global.and_then(|g| g.get(name)))
+            .filter(|v| !v.is_table())
+    }
+
+    /// Returns the values of the argument, such as the paths of the corpus.
+    pub(crate) fn values(&self, name: &str) -> Result<Vec<String>, String> {
+        if self.matches.occurrences_of(name) == 0 {
+            match self.configured(name) {
+                Some(Value::Array(values)) => {
+                    return values.iter().map(|v| to_string(name, v)).collect()
+                }
+                Some(value) => return Ok(vec![to_string(name, value)?]),
+                None => {}
+            }
+        }
+        Ok(self
+            .matches
+            .values_of(name)
+            .map(|v| v.map(|v| v.to_string()).collect())
+            .unwrap_or_default())
+    }
+
+    /// Returns the value of the argument if it is given.
+    pub(crate) fn value(&self, name: &str) -> Result<Option<String>, String> {
+        let mut values = self.values(name)?;
+        match values.len() {
+            0 => Ok(None),
+            1 => Ok(values.pop()),
+            _ => Err(format!("`{}` must be a single value.", name)),
+        }
+    }
+
+    /// Returns the value of the required argument.
+    pub(crate) fn required(&self, name: &str) -> Result<String, String> {
+        self.value(name)?
+            .ok_or_else(|| format!("`--{}` is required.", name))
+    }
+
+    /// Parses the value of the argument if it is given.
+    pub(crate) fn parse<T: FromStr>(&self, name: &str) -> Result<Option<T>, String> {
+        match self.value(name)? {
+            Some(v) => v
+                .parse()
+                .map(Some)
+                .map_err(|_| format!("Invalid value `{}` for `--{}`.", v, name)),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Converts a value of the configuration file into the string as on the
+/// command line.
+fn to_string(name: &str, value: &Value) -> Result<String, String> {
+    match value {
+        Value::String(s) => Ok(s.clone()),
+        Value::Integer(i) => Ok(i.to_string()),
+        Value::Float(f) => Ok(f.to_string()),
+        Value::Boolean(b) => Ok(b.to_string()),
+        _ => Err(format!(
+            "Invalid value for `{}` in the configuration.",
+            name
+        )),
+    }
+}
+
+#[cfg(test)]
+mod config_test {
+    use crate::app;
+    use crate::config::Settings;
+    use toml::value::Table;
+
+    #[test]
+    fn fall_back_to_configuration() {
+        let config = toml::from_str::<Table>(
+            "model = \"global.bin\"\nseed = 1\n[generate]\ncount = 10\nseed = 42\n",
+        )
+        .unwrap();
+        let matches = app()
+            .get_matches_from_safe(vec!["maria", "generate", "--seed", "7"])
+            .unwrap();
+        let generate = matches.subcommand_matches("generate").unwrap();
+        let settings = Settings::new(generate, Some(&config), "generate");
+        assert_eq!(
+            (
+                settings.required("model"),
+                settings.parse::<usize>("count"),
+                settings.parse::<u64>("seed")
+            ),
+            (Ok("global.bin".to_string()), Ok(Some(10)), Ok(Some(7)))
+        )
+    }
+
+    #[test]
+    fn read_arrays_of_values() {
+        let config =
+            toml::from_str::<Table>("[train]\ninput = [\"a.txt\", \"corpus/\"]\n").unwrap();
+        let matches = app().get_matches_from_safe(vec!["maria", "train"]).unwrap();
+        let train = matches.subcommand_matches("train").unwrap();
+        let settings = Settings::new(train, Some(&config), "train");
+        assert_eq!(
+            (settings.values("input"), settings.value("input").is_err()),
+            (Ok(vec!["a.txt".to_string(), "corpus/".to_string()]), true)
+        )
+    }
+}
@@ -0,0 +1,599 @@
+//! Command line interface of lyrian.
+//!
+//! ```txt
+//! maria train --input corpus.txt --output model.bin
+//! maria generate --model model.bin --count 10 --seed 42
+//! maria haiku --model model.bin --season autumn --count 5
+//! maria tanka --model model.bin --format slash
+//! maria inspect --model model.bin --token 猫
+//! maria serve --model model.bin --port 8080
+//! ```
+//!
+//! The corpus and the model are read from the standard input if the files
+//! are omitted or `-`, and the model is written to the standard output, so
+//! that the command works in pipelines:
+//!
+//! ```txt
+//! cat corpus.txt | maria train -o model.bin
+//! maria generate -m model.bin -n 100 | sort | uniq -c
+//! ```
+//!
+//! The exit code is `0` on success, `1` on errors, and `2` on invalid
+//! arguments.
+//!
+//! The arguments can be set in a configuration file in TOML, given by
+//! `maria --config maria.toml train`, see [`config`] for the format.
+
+use std::fs;
+use std::io::{self, ErrorKind, Read, Write};
+use std::process;
+
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+
+use lyrian::clean::{strip_html, Cleaner, Pipeline, TextFilter};
+use lyrian::corpus::{load_dir, load_file};
+use lyrian::format::{LineSeparator, PoemFormatter};
+use lyrian::kigo::{Saijiki, Season};
+use lyrian::model::{LyrianModel, Smoothing};
+use lyrian::normalize::{fold_halfwidth_kana, nfkc, to_fullwidth_ascii, to_halfwidth_ascii};
+use lyrian::poem::{HaikuGenerator, HAIKU, TANKA};
+use lyrian::pronunciation::JapaneseCounter;
+#[cfg(feature = "mecab")]
+use lyrian::tokenize::MecabTokenizer;
+#[cfg(feature = "vibrato")]
+use lyrian::tokenize::VibratoTokenizer;
+use lyrian::tokenize::{LinderaTokenizer, LyrianToken, Tokenizer};
+
+use crate::config::{load_config, Settings};
+
+mod config;
+#[cfg(feature = "server")]
+mod server;
+
+/// The maximum number of tokens of a generated sentence.
+const MAX_TOKENS: usize = 64;
+
+fn main() {
+    let matches = app().get_matches_safe().unwrap_or_else(|e| {
+        if !e.use_stderr() {
+            e.exit();
+        }
+        eprintln!("{}", e.message);
+        process::exit(2);
+    });
+    let stdout = io::stdout();
+    if let Err(e) = run(&matches, &mut stdout.lock()) {
+        eprintln!("error: {}", e);
+        process::exit(1);
+    }
+}
+
+/// Builds the parser of the arguments.
+fn app() -> App<'static, 'static> {
+    let app = App::new("maria")
+        .version(env!("CARGO_PKG_VERSION"))
+        .about("Generates Japanese lyrics with Markov chain.")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .arg(
+            Arg::with_name("config")
+                .short("c")
+                .long("config")
+                .value_name("FILE")
+                .help("The configuration file in TOML, which sets the arguments")
+                .global(true),
+        )
+        .subcommand(
+            SubCommand::with_name("train")
+                .about("Builds a model from a corpus")
+                .arg(
+                    Arg::with_name("input")
+                        .short("i")
+                        .long("input")
+                        .value_name("FILE")
+                        .help("The corpus of text, or a directory of them [default: the standard input]")
+                        .multiple(true)
+                        .number_of_values(1),
+                )
+                .arg(
+                    Arg::with_name("pattern")
+                        .long("pattern")
+                        .value_name("WILDCARD")
+                        .help("The names of the files loaded from the directories")
+                        .default_value("*.txt"),
+                )
+                .arg(
+                    Arg::with_name("tokenizer")
+                        .long("tokenizer")
+                        .value_name("TOKENIZER")
+                        .help("`lindera`, `chars`, `morae`, `vibrato` or `mecab`")
+                        .default_value("lindera"),
+                )
+                .arg(
+                    Arg::with_name("dictionary")
+                        .long("dictionary")
+                        .value_name("PATH")
+                        .help("The dictionary of vibrato or MeCab"),
+                )
+                .arg(
+                    Arg::with_name("normalize")
+                        .long("normalize")
+                        .value_name("FILTER")
+                        .help(
+                            "Filters the corpus in order by `html`, `sns`, `nfkc`, \
+                             `halfwidth_kana`, `halfwidth_ascii` or `fullwidth_ascii`",
+                        )
+                        .multiple(true)
+                        .number_of_values(1),
+                )
+                .arg(
+                    Arg::with_name("order")
+                        .long("order")
+                        .value_name("N")
+                        .help("The number of the tokens which each token depends on")
+                        .default_value("1"),
+                )
+                .arg(
+                    Arg::with_name("smoothing")
+                        .long("smoothing")
+                        .value_name("SMOOTHING")
+                        .help("The smoothing of the transitions")
+                        .possible_values(&["none", "good-turing", "witten-bell"])
+                        .default_value("none"),
+                )
+                .arg(
+                    Arg::with_name("prior")
+                        .long("prior")
+                        .value_name("ALPHA")
+                        .help("The pseudo-count added to the transitions between all the tokens"),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .short("o")
+                        .long("output")
+                        .value_name("FILE")
+                        .help("The file to save the model in [default: the standard output]"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("generate")
+                .about("Generates sentences from a model")
+                .arg(
+                    Arg::with_name("model")
+                        .short("m")
+                        .long("model")
+                        .value_name("FILE")
+                        .help("The model saved by `maria train`, or `-` for the standard input"),
+                )
+                .arg(
+                    Arg::with_name("count")
+                        .short("n")
+                        .long("count")
+                        .value_name("N")
+                        .help("The number of sentences")
+                        .default_value("1"),
+                )
+                .arg(
+                    Arg::with_name("seed")
+                        .short("s")
+                        .long("seed")
+                        .value_name("SEED")
+                        .help("The seed of the random number generator"),
+                )
+                .arg(
+                    Arg::with_name("length")
+                        .short("l")
+                        .long("length")
+                        .value_name("MORAE")
+                        .help("Generates lyrics of the number of morae instead of sentences"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("inspect")
+                .about("Reports the size of a model and the transitions of tokens")
+                .arg(
+                    Arg::with_name("model")
+                        .short("m")
+                        .long("model")
+                        .value_name("FILE")
+                        .help("The model saved by `maria train`, or `-` for the standard input"),
+                )
+                .arg(
+                    Arg::with_name("token")
+                        .short("t")
+                        .long("token")
+                        .value_name("WORD")
+                        .help("Lists the most probable transitions from the tokens of the word"),
+                )
+                .arg(
+                    Arg::with_name("top")
+                        .long("top")
+                        .value_name("N")
+                        .help("The number of the transitions listed")
+                        .default_value("10"),
+                ),
+        )
+        .subcommand(poem_subcommand("haiku", "Generates haiku of 5, 7 and 5 morae"))
+        .subcommand(poem_subcommand(
+            "tanka",
+            "Generates tanka of 5, 7, 5, 7 and 7 morae",
+        ));
+    #[cfg(feature = "server")]
+    let app = app.subcommand(server::subcommand());
+    app
+}
+
+/// Builds the parser of the arguments of the subcommands of poems.
+fn poem_subcommand(name: &'static str, about: &'static str) -> App<'static, 'static> {
+    SubCommand::with_name(name)
+        .about(about)
+        .arg(
+            Arg::with_name("model")
+                .short("m")
+                .long("model")
+                .value_name("FILE")
+                .help("The model saved by `maria train`, or `-` for the standard input"),
+        )
+        .arg(
+            Arg::with_name("count")
+                .short("n")
+                .long("count")
+                .value_name("N")
+                .help("The number of poems")
+                .default_value("1"),
+        )
+        .arg(
+            Arg::with_name("seed")
+                .short("s")
+                .long("seed")
+                .value_name("SEED")
+                .help("The seed of the random number generator"),
+        )
+        .arg(
+            Arg::with_name("season")
+                .long("season")
+                .value_name("SEASON")
+                .help("Requires a kigo of the season, e.g. `autumn` or `秋`"),
+        )
+        .arg(
+            Arg::with_name("saijiki")
+                .long("saijiki")
+                .value_name("FILE")
+                .help("The saijiki in TSV or JSON to look up kigo"),
+        )
+        .arg(
+            Arg::with_name("tolerance")
+                .short("t")
+                .long("tolerance")
+                .value_name("MORAE")
+                .help("Allows jiamari and jitarazu by the number of morae"),
+        )
+        .arg(
+            Arg::with_name("format")
+                .short("f")
+                .long("format")
+                .value_name("FORMAT")
+                .help("The layout of the poems")
+                .possible_values(&["newline", "space", "slash", "vertical", "ruby"])
+                .default_value("newline"),
+        )
+}
+
+/// Runs the subcommand, which writes its results to `out`.
+fn run<W: Write>(matches: &ArgMatches, out: &mut W) -> Result<(), String> {
+    let (name, matches) = match matches.subcommand() {
+        (name, Some(m)) => (name, m),
+        _ => return Err("Unknown subcommand.".to_string()),
+    };
+    let config = match matches.value_of("config") {
+        Some(path) => Some(load_config(path)?),
+        None => None,
+    };
+    let settings = Settings::new(matches, config.as_ref(), name);
+    match name {
+        "train" => train(&settings, out),
+        "generate" => generate(&settings, out),
+        "haiku" => poem(&settings, &HAIKU, out),
+        "tanka" => poem(&settings, &TANKA, out),
+        "inspect" => inspect(&settings, out),
+        #[cfg(feature = "server")]
+        "serve" => server::serve(&settings),
+        _ => Err("Unknown subcommand.".to_string()),
+    }
+}
+
+/// Builds a model from the corpus, and saves it in json.
+///
+/// The corpus from the standard input is read line by line, so that it is
+/// not held in the memory, unless it is normalized or split into characters.
+fn train<W: Write>(settings: &Settings, out: &mut W) -> Result<(), String> {
+    if settings.parse::<usize>("order")? != Some(1) {
+        return Err("Only the order `1` is supported.".to_string());
+    }
+    let smoothing = settings
+        .parse::<Smoothing>("smoothing")?
+        .unwrap_or_default();
+    let prior = settings.parse::<f64>("prior")?;
+    let filter = normalizer(&settings.values("normalize")?)?;
+    let tokenizer = settings.required("tokenizer")?;
+    let inputs = settings
+        .values("input")?
+        .into_iter()
+        .filter(|p| p != "-")
+        .collect::<Vec<String>>();
+
+    let model = match (tokenizer.as_str(), inputs.is_empty()) {
+        ("chars", _) => LyrianModel::chars_from(&load_corpus(settings, &inputs, &filter)?),
+        ("morae", _) => LyrianModel::from_morae(&load_corpus(settings, &inputs, &filter)?),
+        (name, true) if settings.values("normalize")?.is_empty() => {
+            let stdin = io::stdin();
+            LyrianModel::from_reader(
+                stdin.lock(),
+                &*load_tokenizer(name, settings.value("dictionary")?)?,
+            )?
+        }
+        (name, _) => LyrianModel::from_text_with(
+            &load_corpus(settings, &inputs, &filter)?,
+            &*load_tokenizer(name, settings.value("dictionary")?)?,
+        )?,
+    };
+    let mut model = model.with_smoothing(smoothing);
+    if let Some(alpha) = prior {
+        model = model.with_prior(alpha);
+    }
+    match settings.value("output")? {
+        Some(path) if path != "-" => {
+            fs::write(path, model.to_json_str()?).map_err(|e| e.to_string())
+        }
+        _ => write_line(out, &model.to_json_str()?),
+    }
+}
+
+/// Loads the files and the files in the directories of the corpus, or the
+/// standard input without them, and filters them.
+///
+/// The texts are joined with newlines, which end the sentences, so that no
+/// transition is learned between them.
+fn load_corpus(
+    settings: &Settings,
+    inputs: &[String],
+    filter: &Pipeline,
+) -> Result<String, String> {
+    let mut texts = Vec::new();
+    if inputs.is_empty() {
+        let mut text = String::new();
+        io::stdin()
+            .read_to_string(&mut text)
+            .map_err(|e| e.to_string())?;
+        texts.push(text);
+    }
+    for path in inputs {
+        if fs::metadata(path).map(|m| m.is_dir()).unwrap_or(false) {
+            let pattern = settings.required("pattern")?;
+            texts.extend(load_dir(path, &pattern)?.into_iter().map(|(_, t)| t));
+        } else {
+            texts.push(load_file(path)?);
+        }
+    }
+    Ok(filter.filter_all(&texts).join("\n"))
+}
+
+/// Builds the pipeline of the filters by their names.
+fn normalizer(names: &[String]) -> Result<Pipeline, String> {
+    names.iter().try_fold(Pipeline::new(), |pipeline, name| {
+        Ok(match name.as_str() {
+            "html" => pipeline.then(strip_html),
+            "sns" => pipeline.then(Cleaner::sns()),
+            "nfkc" => pipeline.then(nfkc),
+            "halfwidth_kana" => pipeline.then(fold_halfwidth_kana),
+            "halfwidth_ascii" => pipeline.then(to_halfwidth_ascii),
+            "fullwidth_ascii" => pipeline.then(to_fullwidth_ascii),
+            _ => return Err(format!("Unknown filter `{}`.", name)),
+        })
+    })
+}
+
+/// Creates the tokenizer of the name, with the dictionary of vibrato or
+/// MeCab if any.
+#[cfg_attr(
+    not(any(feature = "vibrato", feature = "mecab")),
+    allow(unused_variables)
+)]
+fn load_tokenizer(name: &str, dictionary: Option<String>) -> Result<Box<dyn Tokenizer>, String> {
+    match name {
+        "lindera" => Ok(Box::new(LinderaTokenizer::new()?)),
+        #[cfg(feature = "vibrato")]
+        "vibrato" => match dictionary {
+            Some(path) => Ok(Box::new(VibratoTokenizer::load(path)?)),
+            None => Err("`--dictionary` is required by vibrato.".to_string()),
+        },
+        #[cfg(feature = "mecab")]
+        "mecab" => Ok(Box::new(match dictionary {
+            Some(path) => MecabTokenizer::new().with_dictionary(path),
+            None => MecabTokenizer::new(),
+        })),
+        _ => Err(format!("Unknown tokenizer `{}`.", name)),
+    }
+}
+
+/// Generates sentences or lyrics from the saved model, one per line.
+fn generate<W: Write>(settings: &Settings, out: &mut W) -> Result<(), String> {
+    let mut model = load_model(settings)?;
+
+    let count = settings.parse::<usize>("count")?.unwrap_or(1);
+    let length = settings.parse::<usize>("length")?;
+    for _ in 0..count {
+        let line = match length {
+            Some(length) => model.generate_lyric(length, false)?.join(),
+            None => model.generate_sentence(MAX_TOKENS),
+        };
+        write_line(out, &line)?;
+    }
+    Ok(())
+}
+
+/// Generates poems of the pattern from the saved model, separated by empty
+/// lines.
+fn poem<W: Write>(settings: &Settings, pattern: &[usize], out: &mut W) -> Result<(), String> {
+    let model = load_model(settings)?;
+    let mut generator = HaikuGenerator::new(model, JapaneseCounter::new(false));
+    if let Some(season) = settings.parse::<Season>("season")? {
+        generator = generator.with_season(season);
+    }
+    if let Some(path) = settings.value("saijiki")? {
+        generator = generator.with_saijiki(Saijiki::load(path)?);
+    }
+    if let Some(tolerance) = settings.parse::<usize>("tolerance")? {
+        generator = generator.with_tolerance(tolerance);
+    }
+
+    let format = settings.required("format")?;
+    let formatter = match format.as_str() {
+        "newline" | "vertical" | "ruby" => PoemFormatter::new(),
+        "space" => PoemFormatter::new().with_separator(LineSeparator::Space),
+        "slash" => PoemFormatter::new().with_separator(LineSeparator::Slash),
+        _ => return Err(format!("Unknown format `{}`.", format)),
+    };
+    let count = settings.parse::<usize>("count")?.unwrap_or(1);
+    for i in 0..count {
+        let poem = generator
+            .generate_pattern(pattern)
+            .map_err(|e| e.to_string())?;
+        let text = match format.as_str() {
+            "vertical" => formatter.format_vertical(&poem),
+            "ruby" => formatter.format_ruby(&poem),
+            _ => formatter.format(&poem),
+        };
+        if i > 0 && text.contains('\n') {
+            write_line(out, "")?;
+        }
+        write_line(out, &text)?;
+    }
+    Ok(())
+}
+
+/// Reports the summary of the saved model, and the transitions from the
+/// tokens of the word given by `--token`.
+///
+/// The tokens of the same word are listed separately by their parts of
+/// speech and readings.
+fn inspect<W: Write>(settings: &Settings, out: &mut W) -> Result<(), String> {
+    let model = load_model(settings)?;
+    let summary = model.summary();
+    write_line(out, &format!("vocabulary:  {}", summary.vocabulary))?;
+    write_line(out, &format!("order:       {}", summary.order))?;
+    write_line(out, &format!("transitions: {}", summary.transitions))?;
+    write_line(out, &format!("dead ends:   {}", summary.dead_ends))?;
+    write_line(out, &format!("memory:      {} bytes", summary.memory))?;
+
+    let word = match settings.value("token")? {
+        Some(word) => word,
+        None => return Ok(()),
+    };
+    let tokens = model
+        .tokens()
+        .iter()
+        .filter(|t| t.word == word)
+        .collect::<Vec<&LyrianToken>>();
+    if tokens.is_empty() {
+        return Err(format!("The word `{}` is not in the model.", word));
+    }
+    let top = settings.parse::<usize>("top")?.unwrap_or(10);
+    for token in tokens {
+        write_line(out, "")?;
+        write_line(
+            out,
+            &format!(
+                "{} ({}, {}), frequency {}:",
+                token.word,
+                token.part_of_speech,
+                token.mora,
+                model.frequency(token)
+            ),
+        )?;
+        let successors = model.successors(token);
+        if successors.is_empty() {
+            write_line(out, "  (dead end)")?;
+        }
+        for (next, probability) in successors.into_iter().take(top) {
+            write_line(
+                out,
+                &format!(
+                    "  {:.4}  {} ({})",
+                    probability, next.word, next.part_of_speech
+                ),
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Loads the model given by `--model`, seeded by `--seed` if any.
+fn load_model(settings: &Settings) -> Result<LyrianModel, String> {
+    let json = match settings.required("model")?.as_str() {
+        "-" => {
+            let mut json = String::new();
+            io::stdin()
+                .read_to_string(&mut json)
+                .map_err(|e| e.to_string())?;
+            json
+        }
+        path => fs::read_to_string(path).map_err(|e| e.to_string())?,
+    };
+    let mut model = LyrianModel::from_json(&json)?;
+    if let Some(seed) = settings.parse::<u64>("seed")? {
+        model.set_seed(seed);
+    }
+    Ok(model)
+}
+
+/// Writes the line, and flushes it so that the next command in the pipeline
+/// receives it at once.
+///
+/// The process exits successfully if the reader has closed the pipe, such as
+/// `head`, since no one needs the rest of the output.
+fn write_line<W: Write>(out: &mut W, line: &str) -> Result<(), String> {
+    match writeln!(out, "{}", line).and_then(|_| out.flush()) {
+        Err(e) if e.kind() == ErrorKind::BrokenPipe => process::exit(0),
+        result => result.map_err(|e| e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod maria_test {
+    use crate::config::Settings;
+    use crate::{app, write_line};
+
+    #[test]
+    fn parse_generate_arguments() {
+        let matches = app()
+            .get_matches_from_safe(vec!["maria", "generate", "-m", "model.bin", "--seed", "42"])
+            .unwrap();
+        let generate = Settings::new(matches.subcommand_matches("generate").unwrap(), None, "");
+        assert_eq!(
+            (
+                generate.parse::<u64>("seed"),
+                generate.parse::<usize>("count")
+            ),
+            (Ok(Some(42)), Ok(Some(1)))
+        )
+    }
+
+    #[test]
+    fn parse_season_of_haiku() {
+        use lyrian::kigo::Season;
+
+        let matches = app()
+            .get_matches_from_safe(vec!["maria", "haiku", "-m", "model.bin", "--season", "秋"])
+            .unwrap();
+        let haiku = Settings::new(matches.subcommand_matches("haiku").unwrap(), None, "");
+        assert_eq!(haiku.parse::<Season>("season"), Ok(Some(Season::Autumn)))
+    }
+
+    #[test]
+    fn write_newline_delimited_lines() {
+        let mut out = Vec::new();
+        write_line(&mut out, "古池や").unwrap();
+        write_line(&mut out, "蛙飛び込む").unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "古池や\n蛙飛び込む\n")
+    }
+}
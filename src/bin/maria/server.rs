@@ -0,0 +1,415 @@
+//! HTTP server of `maria serve`, which generates sentences and poems in
+//! json.
+//!
+//! ```txt
+//! curl -d '{"count": 3, "seed": 42}' localhost:8080/generate
+//! curl -d '{"season": "autumn", "form": "tanka"}' localhost:8080/haiku
+//! ```
+//!
+//! The requests are handled one by one, since the generation moves the
+//! state of the model. A client which sends nothing is dropped after the
+//! timeout, and the requests of too many sentences or too long ones are
+//! rejected.
+
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::time::Duration;
+
+use clap::{App, Arg, SubCommand};
+use serde::{Deserialize, Serialize};
+
+use lyrian::kigo::Season;
+use lyrian::model::LyrianModel;
+use lyrian::poem::{GenerationError, HaikuGenerator, HAIKU, TANKA};
+use lyrian::pronunciation::JapaneseCounter;
+
+use crate::config::Settings;
+use crate::{load_model, MAX_TOKENS};
+
+/// The maximum number of bytes of a request.
+const MAX_REQUEST: usize = 1 << 20;
+
+/// The maximum number of sentences or poems of a request.
+const MAX_COUNT: usize = 100;
+
+/// The maximum number of tokens of a sentence, or of morae of lyrics, of a
+/// request.
+const MAX_LENGTH: usize = 1000;
+
+/// The maximum tolerance of the numbers of morae of a request.
+const MAX_TOLERANCE: usize = 10;
+
+/// The time to wait for a client to send or receive a response.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Builds the parser of the arguments of `maria serve`.
+pub(crate) fn subcommand() -> App<'static, 'static> {
+    SubCommand::with_name("serve")
+        .about("Serves a model over HTTP with the json endpoints `/generate` and `/haiku`")
+        .arg(
+            Arg::with_name("model")
+                .short("m")
+                .long("model")
+                .value_name("FILE")
+                .help("The model saved by `maria train`, or `-` for the standard input"),
+        )
+        .arg(
+            Arg::with_name("host")
+                .long("host")
+                .value_name("HOST")
+                .help("The address to listen on")
+                .default_value("127.0.0.1"),
+        )
+        .arg(
+            Arg::with_name("port")
+                .short("p")
+                .long("port")
+                .value_name("PORT")
+                .help("The port to listen on")
+                .default_value("8080"),
+        )
+}
+
+/// Body of the requests to `/generate`, whose fields are all optional.
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+struct GenerateRequest {
+    /// The number of sentences.
+    count: usize,
+    /// The seed of the random number generator.
+    seed: Option<u64>,
+    /// The maximum number of tokens of a sentence.
+    max_tokens: usize,
+    /// Generates lyrics of the number of morae instead of sentences.
+    length: Option<usize>,
+    /// The word which the sentences start from.
+    start: Option<String>,
+}
+
+impl Default for GenerateRequest {
+    fn default() -> GenerateRequest {
+        GenerateRequest {
+            count: 1,
+            seed: None,
+            max_tokens: MAX_TOKENS,
+            length: None,
+            start: None,
+        }
+    }
+}
+
+/// Body of the requests to `/haiku`, whose fields are all optional.
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+struct HaikuRequest {
+    /// The number of poems.
+    count: usize,
+    /// The seed of the random number generator.
+    seed: Option<u64>,
+    /// The season of the kigo required, e.g. `autumn` or `秋`.
+    season: Option<String>,
+    /// The number of morae of jiamari and jitarazu allowed.
+    tolerance: usize,
+    /// `haiku` or `tanka`.
+    form: String,
+}
+
+impl Default for HaikuRequest {
+    fn default() -> HaikuRequest {
+        HaikuRequest {
+            count: 1,
+            seed: None,
+            season: None,
+            tolerance: 0,
+            form: "haiku".to_string(),
+        }
+    }
+}
+
+/// Body of the responses from `/generate`.
+#[derive(Serialize)]
+struct GenerateResponse {
+    sentences: Vec<String>,
+}
+
+/// Body of the responses from `/haiku`.
+#[derive(Serialize)]
+struct HaikuResponse {
+    poems: Vec<PoemResponse>,
+}
+
+/// A poem in [`HaikuResponse`].
+#[derive(Serialize)]
+struct PoemResponse {
+    text: String,
+    lines: Vec<String>,
+    morae: Vec<usize>,
+}
+
+/// Body of the responses of errors.
+#[derive(Serialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+/// Status and json body of a response.
+type Response = (&'static str, String);
+
+/// Serves the saved model until the process is killed.
+pub(crate) fn serve(settings: &Settings) -> Result<(), String> {
+    let mut model = Some(load_model(settings)?);
+    let port = settings.parse::<u16>("port")?.unwrap_or(8080);
+    let host = settings.required("host")?;
+    let listener = TcpListener::bind((host.as_str(), port)).map_err(|e| e.to_string())?;
+    eprintln!("Listening on http://{}", listener.local_addr().unwrap());
+
+    for stream in listener.incoming() {
+        let mut stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("error: {}", e);
+                continue;
+            }
+        };
+        let timeout = stream
+            .set_read_timeout(Some(TIMEOUT))
+            .and_then(|_| stream.set_write_timeout(Some(TIMEOUT)));
+        if let Err(e) = timeout {
+            eprintln!("error: {}", e);
+            continue;
+        }
+        let response = match read_request(&mut stream) {
+            Ok((method, path, body)) => route(&mut model, &method, &path, &body),
+            Err(e) => error("400 Bad Request", e),
+        };
+        if let Err(e) = write_response(&mut stream, response) {
+            eprintln!("error: {}", e);
+        }
+    }
+    Ok(())
+}
+
+/// Dispatches the request to the endpoint.
+///
+/// The model is taken out during the generation of poems, and is always
+/// put back.
+fn route(model: &mut Option<LyrianModel>, method: &str, path: &str, body: &[u8]) -> Response {
+    let path = path.split('?').next().unwrap_or_default();
+    if path != "/generate" && path != "/haiku" {
+        return error("404 Not Found", format!("No endpoint `{}`.", path));
+    }
+    if method != "POST" {
+        return error("405 Method Not Allowed", "Use POST.".to_string());
+    }
+
+    let body = if body.iter().all(|b| b.is_ascii_whitespace()) {
+        &b"{}"[..]
+    } else {
+        body
+    };
+    let result = match path {
+        "/generate" => serde_json::from_slice(body)
+            .map_err(|e| ("400 Bad Request", e.to_string()))
+            .and_then(|request| generate(model.as_mut().unwrap(), &request))
+            .map(|response| serde_json::to_string(&response)),
+        _ => serde_json::from_slice(body)
+            .map_err(|e| ("400 Bad Request", e.to_string()))
+            .and_then(|request| haiku(model, &request))
+            .map(|response| serde_json::to_string(&response)),
+    };
+    match result {
+        Ok(Ok(json)) => ("200 OK", json),
+        Ok(Err(e)) => error("500 Internal Server Error", e.to_string()),
+        Err((status, e)) => error(status, e),
+    }
+}
+
+/// Generates sentences or lyrics for `/generate`.
+fn generate(
+    model: &mut LyrianModel,
+    request: &GenerateRequest,
+) -> Result<GenerateResponse, (&'static str, String)> {
+    check_limit("count", request.count, MAX_COUNT)?;
+    check_limit("max_tokens", request.max_tokens, MAX_LENGTH)?;
+    check_limit("length", request.length.unwrap_or(0), MAX_LENGTH)?;
+    model.set_seed(request.seed.unwrap_or_else(rand::random));
+    let mut sentences = Vec::new();
+    for _ in 0..request.count {
+        let sentence = match (&request.start, request.length) {
+            (Some(word), _) => model.generate_from(word, request.max_tokens),
+            (None, Some(length)) => model.generate_lyric(length, false).map(|l| l.join()),
+            (None, None) => Ok(model.generate_sentence(request.max_tokens)),
+        };
+        sentences.push(sentence.map_err(|e| ("422 Unprocessable Entity", e))?);
+    }
+    Ok(GenerateResponse { sentences })
+}
+
+/// Generates poems for `/haiku`.
+fn haiku(
+    model: &mut Option<LyrianModel>,
+    request: &HaikuRequest,
+) -> Result<HaikuResponse, (&'static str, String)> {
+    check_limit("count", request.count, MAX_COUNT)?;
+    check_limit("tolerance", request.tolerance, MAX_TOLERANCE)?;
+    let pattern = match request.form.as_str() {
+        "haiku" => &HAIKU[..],
+        "tanka" => &TANKA[..],
+        form => return Err(("400 Bad Request", format!("Unknown form `{}`.", form))),
+    };
+    let season = match &request.season {
+        Some(season) => Some(
+            season
+                .parse::<Season>()
+                .map_err(|e| ("400 Bad Request", e))?,
+        ),
+        None => None,
+    };
+
+    let mut seeded = model.take().unwrap();
+    seeded.set_seed(request.seed.unwrap_or_else(rand::random));
+    let mut generator =
+        HaikuGenerator::new(seeded, JapaneseCounter::new(false)).with_tolerance(request.tolerance);
+    if let Some(season) = season {
+        generator = generator.with_season(season);
+    }
+    let poems = (0..request.count)
+        .map(|_| {
+            let poem = generator.generate_pattern(pattern).map_err(|e| match e {
+                GenerationError::Timeout(_) => ("422 Unprocessable Entity", e.to_string()),
+                _ => ("400 Bad Request", e.to_string()),
+            })?;
+            Ok(PoemResponse {
+                text: poem.join(),
+                lines: poem.lines.iter().map(|l| l.join()).collect(),
+                morae: poem.morae(),
+            })
+        })
+        .collect::<Result<Vec<PoemResponse>, (&'static str, String)>>();
+    *model = Some(generator.into_model());
+    Ok(HaikuResponse { poems: poems? })
+}
+
+/// Rejects the value of the field of a request above the limit.
+fn check_limit(name: &str, value: usize, max: usize) -> Result<(), (&'static str, String)> {
+    if value > max {
+        return Err((
+            "400 Bad Request",
+            format!("`{}` must be at most {}.", name, max),
+        ));
+    }
+    Ok(())
+}
+
+/// Returns the response of the error in json.
+fn error(status: &'static str, message: String) -> Response {
+    let body = ErrorResponse { error: message };
+    (status, serde_json::to_string(&body).unwrap())
+}
+
+/// Reads the method, the path and the body of a request.
+fn read_request(stream: &mut TcpStream) -> Result<(String, String, Vec<u8>), String> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0; 4096];
+    loop {
+        let n = stream.read(&mut chunk).map_err(|e| e.to_string())?;
+        if n == 0 {
+            return Err("The request ended before the headers.".to_string());
+        }
+        buffer.extend_from_slice(&chunk[..n]);
+
+        let mut headers = [httparse::EMPTY_HEADER; 32];
+        let mut request = httparse::Request::new(&mut headers);
+        let length = match request.parse(&buffer).map_err(|e| e.to_string())? {
+            httparse::Status::Complete(length) => length,
+            httparse::Status::Partial if buffer.len() < MAX_REQUEST => continue,
+            httparse::Status::Partial => return Err("The headers are too large.".to_string()),
+        };
+        let content_length = request
+            .headers
+            .iter()
+            .find(|h| h.name.eq_ignore_ascii_case("content-length"))
+            .map(|h| String::from_utf8_lossy(h.value).trim().parse::<usize>())
+            .transpose()
+            .map_err(|e| e.to_string())?
+            .unwrap_or(0);
+        if content_length > MAX_REQUEST {
+            return Err("The body is too large.".to_string());
+        }
+        let method = request.method.unwrap_or_default().to_string();
+        let path = request.path.unwrap_or_default().to_string();
+
+        let mut body = buffer.split_off(length);
+        while body.len() < content_length {
+            let n = stream.read(&mut chunk).map_err(|e| e.to_string())?;
+            if n == 0 {
+                return Err("The request ended before the body.".to_string());
+            }
+            body.extend_from_slice(&chunk[..n]);
+        }
+        body.truncate(content_length);
+        return Ok((method, path, body));
+    }
+}
+
+/// Writes the response, and closes the connection.
+fn write_response(stream: &mut TcpStream, (status, body): Response) -> Result<(), String> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: application/json; charset=utf-8\r\n\
+         Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )
+    .and_then(|_| stream.flush())
+    .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod server_test {
+    use crate::server::{route, GenerateRequest};
+    use lyrian::model::LyrianModel;
+
+    #[test]
+    fn fill_defaults_of_request() {
+        let request = serde_json::from_str::<GenerateRequest>(r#"{"seed": 42}"#).unwrap();
+        assert_eq!(
+            request,
+            GenerateRequest {
+                seed: Some(42),
+                ..GenerateRequest::default()
+            }
+        )
+    }
+
+    #[test]
+    fn route_requests_to_endpoints() {
+        let mut model = Some(LyrianModel::chars_from("あいう。"));
+        let statuses = [
+            route(&mut model, "POST", "/generate", br#"{"count": 2}"#).0,
+            route(&mut model, "GET", "/generate", b"").0,
+            route(&mut model, "POST", "/sonnet", b"").0,
+            route(&mut model, "POST", "/generate", br#"{"cuont": 2}"#).0,
+            route(
+                &mut model,
+                "POST",
+                "/generate",
+                br#"{"count": 18446744073709551615}"#,
+            )
+            .0,
+            route(&mut model, "POST", "/haiku", br#"{"tolerance": 1000}"#).0,
+        ];
+        assert_eq!(
+            statuses,
+            [
+                "200 OK",
+                "405 Method Not Allowed",
+                "404 Not Found",
+                "400 Bad Request",
+                "400 Bad Request",
+                "400 Bad Request"
+            ]
+        )
+    }
+}
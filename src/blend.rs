@@ -0,0 +1,241 @@
+//! Generation of sentences by blending two models, whose weights change
+//! over the positions of the tokens.
+
+use std::collections::BTreeMap;
+
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::corpus::TERMINATORS;
+use crate::model::LyrianModel;
+use crate::morphological_analysis::LyrianToken;
+
+/// The weight of the second model at the position of the token, given the
+/// position counted from `0` and `max_tokens`.
+type Schedule = Box<dyn Fn(usize, usize) -> f64>;
+
+/// Generator of sentences which follows two models at once, such as two
+/// styles, by interpolating their probabilities of the transitions.
+///
+/// The weight of the second model changes linearly from `0.0` at the first
+/// token to `1.0` at `max_tokens` by default, so the sentences morph from
+/// the style of the first model into the second one. The models without the
+/// last token do not take part in the next token.
+///
+/// The probabilities are those of
+/// [`LyrianModel::successors()`](crate::model::LyrianModel::successors), so
+/// the transitions given only by the smoothing or the prior are never
+/// followed. The options of the sampling of the models, such as
+/// [`with_no_repeat()`](crate::model::LyrianModel::with_no_repeat),
+/// [`with_unique_tokens()`](crate::model::LyrianModel::with_unique_tokens)
+/// and the cycle guard, are not applied, and the models must not be
+/// reversed.
+///
+/// ```rust
+/// use lyrian::blend::BlendGenerator;
+/// use lyrian::model::LyrianModel;
+///
+/// let first = LyrianModel::chars_from("ふるいけや。");
+/// let second = LyrianModel::chars_from("いけのみず。");
+/// let mut generator = BlendGenerator::new(first, second);
+/// println!("{}", generator.generate_from("ふ", 10).unwrap());
+/// ```
+pub struct BlendGenerator {
+    first: LyrianModel,
+    second: LyrianModel,
+    schedule: Schedule,
+    rng: Option<StdRng>,
+}
+
+impl BlendGenerator {
+    /// Creates a new instance of [`BlendGenerator`] of the two models.
+    pub fn new(first: LyrianModel, second: LyrianModel) -> BlendGenerator {
+        BlendGenerator {
+            first,
+            second,
+            schedule: Box::new(|position, max_tokens| {
+                position as f64 / max_tokens.saturating_sub(1).max(1) as f64
+            }),
+            rng: None,
+        }
+    }
+
+    /// Sets the schedule of the weight of the second model, given the
+    /// position of the token counted from `0` and `max_tokens`. The weight
+    /// is clamped between `0.0` and `1.0`, and NaN is regarded as `0.0`.
+    ///
+    /// ```rust
+    /// use lyrian::blend::BlendGenerator;
+    /// use lyrian::model::LyrianModel;
+    ///
+    /// let first = LyrianModel::chars_from("あいう。");
+    /// let second = LyrianModel::chars_from("あかき。");
+    /// // Follows the second model after the first three tokens.
+    /// let mut generator = BlendGenerator::new(first, second)
+    ///     .with_schedule(|position, _| if position < 3 { 0.0 } else { 1.0 });
+    /// ```
+    pub fn with_schedule<F: Fn(usize, usize) -> f64 + 'static>(
+        mut self,
+        schedule: F,
+    ) -> BlendGenerator {
+        self.schedule = Box::new(schedule);
+        self
+    }
+
+    /// Seeds the random number generators of the generator and the models,
+    /// so that the same sentences are generated from the same seed.
+    pub fn with_seed(mut self, seed: u64) -> BlendGenerator {
+        self.rng = Some(StdRng::seed_from_u64(seed));
+        self.first.set_seed(seed);
+        self.second.set_seed(seed);
+        self
+    }
+
+    /// Returns the models, to use them separately again.
+    pub fn into_models(self) -> (LyrianModel, LyrianModel) {
+        (self.first, self.second)
+    }
+
+    /// Generates a sentence like [`LyrianModel::generate_sentence()`].
+    ///
+    /// The first token is chosen by the first or the second model at random
+    /// by the weight at the position `0`. An empty string is returned if no
+    /// token can start a sentence.
+    pub fn generate_sentence(&mut self, max_tokens: usize) -> String {
+        let weight = self.weight(0, max_tokens);
+        let second = self.gen_bool(weight);
+        let model = if second {
+            &mut self.second
+        } else {
+            &mut self.first
+        };
+        let first = model.get_starting_token();
+        model.initialize();
+        match first {
+            Some(token) if max_tokens > 0 => self.continue_sentence(token, max_tokens),
+            _ => String::new(),
+        }
+    }
+
+    /// Generates a sentence which starts from the most frequent token of the
+    /// word in the first model, or in the second one if it is not in the
+    /// first one.
+    ///
+    /// An error is returned if the word is in neither of the models.
+    pub fn generate_from(&mut self, word: &str, max_tokens: usize) -> Result<String, String> {
+        match self
+            .first
+            .most_frequent_token(word)
+            .or_else(|| self.second.most_frequent_token(word))
+        {
+            Some(token) => Ok(self.continue_sentence(token, max_tokens.max(1))),
+            None => Err(format!("The word `{}` is in neither of the models.", word)),
+        }
+    }
+
+    /// Continues the sentence from the token, until a token which ends with
+    /// a terminator, a token which neither of the models can follow, or
+    /// `max_tokens` tokens.
+    fn continue_sentence(&mut self, first: LyrianToken, max_tokens: usize) -> String {
+        let mut sentence = first.word.clone();
+        let mut token = first;
+        for position in 1..max_tokens {
+            if token.word.ends_with(TERMINATORS) {
+                break;
+            }
+            let weight = self.weight(position, max_tokens);
+            let first = self.first.successors(&token);
+            let second = self.second.successors(&token);
+            let (a, b) = match (first.is_empty(), second.is_empty()) {
+                (true, true) => break,
+                (true, false) => (0.0, 1.0),
+                (false, true) => (1.0, 0.0),
+                (false, false) => (1.0 - weight, weight),
+            };
+            let mut candidates = BTreeMap::new();
+            for (t, p) in first {
+                *candidates.entry(t).or_insert(0.0) += a * p;
+            }
+            for (t, p) in second {
+                *candidates.entry(t).or_insert(0.0) += b * p;
+            }
+            let (tokens, weights): (Vec<&LyrianToken>, Vec<f64>) = candidates.into_iter().unzip();
+            let next = match WeightedIndex::new(&weights) {
+                Ok(distribution) => match &mut self.rng {
+                    Some(rng) => distribution.sample(rng),
+                    None => distribution.sample(&mut rand::thread_rng()),
+                },
+                Err(_) => break,
+            };
+            token = tokens[next].clone();
+            sentence.push_str(&token.word);
+        }
+        sentence
+    }
+
+    /// Returns the weight of the second model at the position.
+    fn weight(&self, position: usize, max_tokens: usize) -> f64 {
+        let weight = (self.schedule)(position, max_tokens);
+        if weight.is_nan() {
+            0.0
+        } else {
+            weight.clamp(0.0, 1.0)
+        }
+    }
+
+    /// Returns `true` with the probability.
+    fn gen_bool(&mut self, probability: f64) -> bool {
+        match &mut self.rng {
+            Some(rng) => rng.gen_bool(probability),
+            None => rand::thread_rng().gen_bool(probability),
+        }
+    }
+}
+
+#[cfg(test)]
+mod blend_test {
+    use crate::blend::BlendGenerator;
+    use crate::model::LyrianModel;
+
+    #[test]
+    fn follow_scheduled_models() {
+        let blend = || {
+            BlendGenerator::new(
+                LyrianModel::chars_from("あいう。"),
+                LyrianModel::chars_from("あかき。"),
+            )
+        };
+        let mut first = blend().with_schedule(|_, _| 0.0);
+        let mut second = blend().with_schedule(|_, _| 1.0);
+        let mut switched = blend().with_schedule(|position, _| position as f64 - 1.0);
+        let mut nan = blend().with_schedule(|_, _| f64::NAN);
+        assert_eq!(
+            (
+                first.generate_from("あ", 10),
+                second.generate_from("あ", 10),
+                switched.generate_from("あ", 10),
+                "あいう。".ends_with(&nan.generate_sentence(10))
+            ),
+            (
+                Ok("あいう。".to_string()),
+                Ok("あかき。".to_string()),
+                Ok("あいう。".to_string()),
+                true
+            )
+        )
+    }
+
+    #[test]
+    fn fall_back_to_model_with_token() {
+        let mut generator = BlendGenerator::new(
+            LyrianModel::chars_from("あい"),
+            LyrianModel::chars_from("いう。"),
+        )
+        .with_schedule(|_, _| 0.0);
+        assert_eq!(
+            generator.generate_from("あ", 10),
+            Ok("あいう。".to_string())
+        )
+    }
+}
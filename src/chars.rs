@@ -1,7 +1,7 @@
 //! Defines the characters associated with the number of pronunciations.
 
 /// Returns the counts of duplicated elements in two [`Vec<T>`].
-pub fn dup_num<T: std::cmp::PartialEq>(list1: &Vec<T>, list2: &Vec<T>) -> usize {
+pub(crate) fn dup_num<T: std::cmp::PartialEq>(list1: &Vec<T>, list2: &Vec<T>) -> usize {
     let mut num = 0;
     for i in list1 {
         for j in list2 {
@@ -16,8 +16,17 @@ pub fn dup_num<T: std::cmp::PartialEq>(list1: &Vec<T>, list2: &Vec<T>) -> usize
 /// Characters that are ignored when counting pronunciation by syllable unit.
 pub const SYLLABLE_CHARS: [char; 3] = ['ン', 'ッ', 'ー'];
 
+/// The long vowel mark.
+pub const LONG_VOWEL: char = 'ー';
+
+/// The geminate consonant marks.
+pub const SOKUON: [char; 2] = ['ッ', 'っ'];
+
 /// Characters that are pronounced as a set with the previous character.
-pub const LOWER_CASE: [char; 8] = ['ァ', 'ィ', 'ゥ', 'ェ', 'ォ', 'ャ', 'ュ', 'ョ'];
+pub const LOWER_CASE: [char; 22] = [
+    'ァ', 'ィ', 'ゥ', 'ェ', 'ォ', 'ャ', 'ュ', 'ョ', 'ヮ', 'ヵ', 'ヶ', 'ぁ', 'ぃ', 'ぅ', 'ぇ', 'ぉ',
+    'ゃ', 'ゅ', 'ょ', 'ゎ', 'ゕ', 'ゖ',
+];
 
 /// Symbols that cannot be counted as pronunciation numbers.
 pub const SYMBOLS: [char; 50] = [
@@ -26,10 +35,363 @@ pub const SYMBOLS: [char; 50] = [
     '}', '｛', '｝', ';', '；', ':', '：', '+', '＋', '*', '＊', '<', '＜', '>', '＞', '_',
 ];
 
-// pub const VOICELESS: [char; 10] = ['キ', 'ク', 'シ', 'ス', 'チ', 'ツ', 'ヒ', 'フ', 'ピ', 'プ'];
-// pub const A_ROW: [char; 5] = ['ア', 'イ', 'ウ', 'エ', 'オ'];
-// pub const K_ROW: [char; 5] = ['カ', 'キ', 'ク', 'ケ', 'コ'];
-// pub const S_ROW: [char; 5] = ['サ', 'シ', 'ス', 'セ', 'ソ'];
-// pub const T_ROW: [char; 5] = ['タ', 'チ', 'ツ', 'テ', 'ト'];
-// pub const H_ROW: [char; 5] = ['ハ', 'ヒ', 'フ', 'ヘ', 'ホ'];
-// pub const P_ROW: [char; 5] = ['パ', 'ピ', 'プ', 'ぺ', 'ポ'];
+/// Characters whose vowels can be devoiced.
+pub const VOICELESS: [char; 10] = ['キ', 'ク', 'シ', 'ス', 'チ', 'ツ', 'ヒ', 'フ', 'ピ', 'プ'];
+
+/// Vowels of Japanese.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Vowel {
+    A,
+    I,
+    U,
+    E,
+    O,
+}
+
+/// Rows of the kana table, where the voiced ones are distinguished.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Row {
+    A,
+    K,
+    G,
+    S,
+    Z,
+    T,
+    D,
+    N,
+    H,
+    B,
+    P,
+    M,
+    Y,
+    R,
+    W,
+    V,
+}
+
+/// Katakana and their rows and vowels.
+#[rustfmt::skip]
+const KANA_TABLE: &[(char, Row, Vowel)] = &[
+    ('ア', Row::A, Vowel::A), ('イ', Row::A, Vowel::I), ('ウ', Row::A, Vowel::U), ('エ', Row::A, Vowel::E), ('オ', Row::A, Vowel::O),
+    ('ァ', Row::A, Vowel::A), ('ィ', Row::A, Vowel::I), ('ゥ', Row::A, Vowel::U), ('ェ', Row::A, Vowel::E), ('ォ', Row::A, Vowel::O),
+    ('カ', Row::K, Vowel::A), ('キ', Row::K, Vowel::I), ('ク', Row::K, Vowel::U), ('ケ', Row::K, Vowel::E), ('コ', Row::K, Vowel::O),
+    ('ヵ', Row::K, Vowel::A), ('ヶ', Row::K, Vowel::E),
+    ('ガ', Row::G, Vowel::A), ('ギ', Row::G, Vowel::I), ('グ', Row::G, Vowel::U), ('ゲ', Row::G, Vowel::E), ('ゴ', Row::G, Vowel::O),
+    ('サ', Row::S, Vowel::A), ('シ', Row::S, Vowel::I), ('ス', Row::S, Vowel::U), ('セ', Row::S, Vowel::E), ('ソ', Row::S, Vowel::O),
+    ('ザ', Row::Z, Vowel::A), ('ジ', Row::Z, Vowel::I), ('ズ', Row::Z, Vowel::U), ('ゼ', Row::Z, Vowel::E), ('ゾ', Row::Z, Vowel::O),
+    ('タ', Row::T, Vowel::A), ('チ', Row::T, Vowel::I), ('ツ', Row::T, Vowel::U), ('テ', Row::T, Vowel::E), ('ト', Row::T, Vowel::O),
+    ('ダ', Row::D, Vowel::A), ('ヂ', Row::D, Vowel::I), ('ヅ', Row::D, Vowel::U), ('デ', Row::D, Vowel::E), ('ド', Row::D, Vowel::O),
+    ('ナ', Row::N, Vowel::A), ('ニ', Row::N, Vowel::I), ('ヌ', Row::N, Vowel::U), ('ネ', Row::N, Vowel::E), ('ノ', Row::N, Vowel::O),
+    ('ハ', Row::H, Vowel::A), ('ヒ', Row::H, Vowel::I), ('フ', Row::H, Vowel::U), ('ヘ', Row::H, Vowel::E), ('ホ', Row::H, Vowel::O),
+    ('バ', Row::B, Vowel::A), ('ビ', Row::B, Vowel::I), ('ブ', Row::B, Vowel::U), ('ベ', Row::B, Vowel::E), ('ボ', Row::B, Vowel::O),
+    ('パ', Row::P, Vowel::A), ('ピ', Row::P, Vowel::I), ('プ', Row::P, Vowel::U), ('ペ', Row::P, Vowel::E), ('ポ', Row::P, Vowel::O),
+    ('マ', Row::M, Vowel::A), ('ミ', Row::M, Vowel::I), ('ム', Row::M, Vowel::U), ('メ', Row::M, Vowel::E), ('モ', Row::M, Vowel::O),
+    ('ヤ', Row::Y, Vowel::A), ('ユ', Row::Y, Vowel::U), ('ヨ', Row::Y, Vowel::O),
+    ('ャ', Row::Y, Vowel::A), ('ュ', Row::Y, Vowel::U), ('ョ', Row::Y, Vowel::O),
+    ('ラ', Row::R, Vowel::A), ('リ', Row::R, Vowel::I), ('ル', Row::R, Vowel::U), ('レ', Row::R, Vowel::E), ('ロ', Row::R, Vowel::O),
+    ('ワ', Row::W, Vowel::A), ('ヰ', Row::W, Vowel::I), ('ヱ', Row::W, Vowel::E), ('ヲ', Row::W, Vowel::O),
+    ('ヮ', Row::W, Vowel::A),
+    ('ヴ', Row::V, Vowel::U), ('ヷ', Row::V, Vowel::A), ('ヸ', Row::V, Vowel::I), ('ヹ', Row::V, Vowel::E), ('ヺ', Row::V, Vowel::O),
+];
+
+/// Returns the vowel of a mora or a syllable, like `キャ`.
+///
+/// The vowel is taken from the last character which has a vowel, so `ン`,
+/// `ッ` and `ー` are skipped. Hiragana are also accepted.
+///
+/// ```rust
+/// use lyrian::chars::{vowel_of, Vowel};
+///
+/// assert_eq!(vowel_of("キャ"), Some(Vowel::A));
+/// assert_eq!(vowel_of("ン"), None);
+/// ```
+pub fn vowel_of(kana: &str) -> Option<Vowel> {
+    kana.chars()
+        .rev()
+        .find_map(|c| lookup(c).map(|(_, vowel)| vowel))
+}
+
+/// Returns the row of the first character of a mora or a syllable.
+///
+/// ```rust
+/// use lyrian::chars::{consonant_row_of, Row};
+///
+/// assert_eq!(consonant_row_of("ギョ"), Some(Row::G));
+/// ```
+pub fn consonant_row_of(kana: &str) -> Option<Row> {
+    kana.chars().next().and_then(lookup).map(|(row, _)| row)
+}
+
+/// Returns whether the character is a hiragana.
+pub fn is_hiragana(c: char) -> bool {
+    matches!(c, '\u{3041}'..='\u{3096}' | '\u{309D}'..='\u{309F}')
+}
+
+/// Returns whether the character is a katakana, including `ー`.
+pub fn is_katakana(c: char) -> bool {
+    matches!(c, '\u{30A1}'..='\u{30FA}' | '\u{30FC}'..='\u{30FF}')
+}
+
+/// Returns whether the character is a hiragana or a katakana.
+pub fn is_kana(c: char) -> bool {
+    is_hiragana(c) || is_katakana(c)
+}
+
+/// Returns whether the character is a kanji, including `々` and `〆`.
+pub fn is_kanji(c: char) -> bool {
+    matches!(
+        c,
+        '\u{3400}'..='\u{4DBF}'
+            | '\u{4E00}'..='\u{9FFF}'
+            | '\u{F900}'..='\u{FAFF}'
+            | '\u{20000}'..='\u{2FA1F}'
+            | '々'
+            | '〆'
+    )
+}
+
+/// Returns whether the character is a Japanese punctuation mark or bracket,
+/// such as `、`, `「` and `！`.
+pub fn is_japanese_punct(c: char) -> bool {
+    matches!(
+        c,
+        '\u{3001}'..='\u{3004}'
+            | '\u{3008}'..='\u{3020}'
+            | '\u{3030}'
+            | '\u{303D}'
+            | '\u{30A0}'
+            | '\u{30FB}'
+            | '\u{FF01}'..='\u{FF0F}'
+            | '\u{FF1A}'..='\u{FF20}'
+            | '\u{FF3B}'..='\u{FF40}'
+            | '\u{FF5B}'..='\u{FF65}'
+    )
+}
+
+/// Returns whether the text is not empty and consists of hiragana.
+pub fn is_hiragana_str(text: &str) -> bool {
+    !text.is_empty() && text.chars().all(is_hiragana)
+}
+
+/// Returns whether the text is not empty and consists of katakana.
+pub fn is_katakana_str(text: &str) -> bool {
+    !text.is_empty() && text.chars().all(is_katakana)
+}
+
+/// Returns whether the text is not empty and consists of kana.
+///
+/// ```rust
+/// use lyrian::chars::is_kana_str;
+///
+/// assert!(is_kana_str("ひらがなとカタカナ"));
+/// assert!(!is_kana_str("漢字"));
+/// ```
+pub fn is_kana_str(text: &str) -> bool {
+    !text.is_empty() && text.chars().all(is_kana)
+}
+
+/// Returns whether the text is not empty and consists of kanji.
+pub fn is_kanji_str(text: &str) -> bool {
+    !text.is_empty() && text.chars().all(is_kanji)
+}
+
+/// Returns whether the text is not empty and consists of Japanese
+/// punctuation marks.
+pub fn is_japanese_punct_str(text: &str) -> bool {
+    !text.is_empty() && text.chars().all(is_japanese_punct)
+}
+
+/// Characters whose voiced form is the next code point.
+const VOICEABLE: &str =
+    "カキクケコサシスセソタチツテトハヒフヘホかきくけこさしすせそたちつてとはひふへほ";
+
+/// Characters whose semi-voiced form is two code points ahead.
+const SEMI_VOICEABLE: &str = "ハヒフヘホはひふへほ";
+
+/// Pairs of the characters and their voiced forms which are not next to each
+/// other.
+const VOICED_PAIRS: [(char, char); 6] = [
+    ('ウ', 'ヴ'),
+    ('う', 'ゔ'),
+    ('ワ', 'ヷ'),
+    ('ヲ', 'ヺ'),
+    ('ゝ', 'ゞ'),
+    ('ヽ', 'ヾ'),
+];
+
+/// Returns the voiced form of a kana, like `カ` to `ガ`.
+pub fn voice(c: char) -> Option<char> {
+    if let Some((_, v)) = VOICED_PAIRS.iter().find(|(k, _)| *k == c) {
+        Some(*v)
+    } else if VOICEABLE.contains(c) {
+        std::char::from_u32(c as u32 + 1)
+    } else {
+        None
+    }
+}
+
+/// Returns the semi-voiced form of a kana, like `ハ` to `パ`.
+pub fn semi_voice(c: char) -> Option<char> {
+    if SEMI_VOICEABLE.contains(c) {
+        std::char::from_u32(c as u32 + 2)
+    } else {
+        None
+    }
+}
+
+/// Removes the voiced or semi-voiced sound mark from a kana, like `ガ` to
+/// `カ` and `パ` to `ハ`.
+///
+/// The other characters are returned as they are.
+pub fn unvoice(c: char) -> char {
+    if let Some((k, _)) = VOICED_PAIRS.iter().find(|(_, v)| *v == c) {
+        return *k;
+    }
+
+    let before = |n: u32| (c as u32).checked_sub(n).and_then(std::char::from_u32);
+    match (before(1), before(2)) {
+        (Some(base), _) if voice(base) == Some(c) => base,
+        (_, Some(base)) if semi_voice(base) == Some(c) => base,
+        _ => c,
+    }
+}
+
+/// Composes the combining voiced sound marks (`U+3099`, `U+309A`) into the
+/// previous kana.
+///
+/// The marks which can not be composed are left as they are.
+///
+/// ```rust
+/// use lyrian::chars::compose_voicing;
+///
+/// assert_eq!(compose_voicing("カ\u{3099}ハ\u{309A}"), "ガパ");
+/// ```
+pub fn compose_voicing(text: &str) -> String {
+    let mut composed = String::with_capacity(text.len());
+    for c in text.chars() {
+        let voiced = match c {
+            '\u{3099}' => composed.chars().last().and_then(voice),
+            '\u{309A}' => composed.chars().last().and_then(semi_voice),
+            _ => None,
+        };
+
+        match voiced {
+            Some(v) => {
+                composed.pop();
+                composed.push(v);
+            }
+            None => composed.push(c),
+        }
+    }
+    composed
+}
+
+/// Returns the row and vowel of a kana.
+fn lookup(c: char) -> Option<(Row, Vowel)> {
+    let c = hiragana_to_katakana(c);
+    KANA_TABLE
+        .iter()
+        .find(|(k, _, _)| *k == c)
+        .map(|(_, row, vowel)| (*row, *vowel))
+}
+
+/// Converts a hiragana into katakana.
+pub(crate) fn hiragana_to_katakana(c: char) -> char {
+    match c {
+        '\u{3041}'..='\u{3096}' | '\u{309D}'..='\u{309E}' => {
+            std::char::from_u32(c as u32 + 0x60).unwrap_or(c)
+        }
+        _ => c,
+    }
+}
+
+/// Converts a katakana into hiragana.
+pub(crate) fn katakana_to_hiragana(c: char) -> char {
+    match c {
+        '\u{30A1}'..='\u{30F6}' | '\u{30FD}'..='\u{30FE}' => {
+            std::char::from_u32(c as u32 - 0x60).unwrap_or(c)
+        }
+        _ => c,
+    }
+}
+
+#[cfg(test)]
+mod chars_test {
+    use crate::chars::{
+        compose_voicing, consonant_row_of, is_hiragana, is_japanese_punct, is_kana_str, is_kanji,
+        is_katakana, semi_voice, unvoice, voice, vowel_of, Row, Vowel,
+    };
+
+    #[test]
+    fn get_vowel_of_mora() {
+        assert_eq!(
+            (vowel_of("ティ"), vowel_of("ろ"), vowel_of("ラー")),
+            (Some(Vowel::I), Some(Vowel::O), Some(Vowel::A))
+        )
+    }
+
+    #[test]
+    fn get_consonant_row_of_mora() {
+        assert_eq!(
+            (
+                consonant_row_of("パ"),
+                consonant_row_of("しゃ"),
+                consonant_row_of("ッ")
+            ),
+            (Some(Row::P), Some(Row::S), None)
+        )
+    }
+
+    #[test]
+    fn classify_japanese_characters() {
+        assert_eq!(
+            (
+                is_hiragana('ゑ'),
+                is_katakana('ー'),
+                is_kanji('々'),
+                is_japanese_punct('」'),
+                is_japanese_punct('ー')
+            ),
+            (true, true, true, true, false)
+        )
+    }
+
+    #[test]
+    fn classify_japanese_text() {
+        assert_eq!(
+            (
+                is_kana_str("ラーメン"),
+                is_kana_str("ラーメン屋"),
+                is_kana_str("")
+            ),
+            (true, false, false)
+        )
+    }
+
+    #[test]
+    fn add_voiced_sound_marks() {
+        assert_eq!(
+            (voice('カ'), voice('ウ'), semi_voice('ほ'), voice('ア')),
+            (Some('ガ'), Some('ヴ'), Some('ぽ'), None)
+        )
+    }
+
+    #[test]
+    fn remove_voiced_sound_marks() {
+        assert_eq!(
+            (
+                unvoice('ガ'),
+                unvoice('ビ'),
+                unvoice('ピ'),
+                unvoice('ヴ'),
+                unvoice('キ')
+            ),
+            ('カ', 'ヒ', 'ヒ', 'ウ', 'キ')
+        )
+    }
+
+    #[test]
+    fn leave_marks_which_can_not_be_composed() {
+        assert_eq!(compose_voicing("ア\u{3099}"), "ア\u{3099}")
+    }
+}
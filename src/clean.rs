@@ -0,0 +1,611 @@
+//! Cleaning and filtering of the corpora before tokenizing.
+
+use crate::morphological_analysis::LyrianToken;
+use crate::tokenize::Tokenizer;
+
+/// The elements which separate the sentences in [`strip_html()`].
+const BLOCK_ELEMENTS: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "br",
+    "dd",
+    "div",
+    "dl",
+    "dt",
+    "figcaption",
+    "figure",
+    "footer",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hr",
+    "li",
+    "main",
+    "nav",
+    "ol",
+    "p",
+    "pre",
+    "section",
+    "table",
+    "td",
+    "th",
+    "title",
+    "tr",
+    "ul",
+];
+
+/// The elements whose contents are removed in [`strip_html()`], where `rt`
+/// and `rp` are the readings of ruby.
+const SKIPPED_ELEMENTS: &[&str] = &[
+    "head", "noscript", "rp", "rt", "script", "style", "template",
+];
+
+/// The named character references decoded in [`strip_html()`].
+#[rustfmt::skip]
+const ENTITIES: &[(&str, &str)] = &[
+    ("amp", "&"), ("lt", "<"), ("gt", ">"), ("quot", "\""), ("apos", "'"), ("nbsp", " "),
+    ("ensp", " "), ("emsp", " "), ("thinsp", " "), ("copy", "©"), ("reg", "®"), ("yen", "¥"),
+    ("hellip", "…"), ("mdash", "—"), ("ndash", "–"), ("middot", "·"), ("times", "×"),
+    ("laquo", "«"), ("raquo", "»"), ("lsquo", "‘"), ("rsquo", "’"), ("ldquo", "“"), ("rdquo", "”"),
+];
+
+/// What to do with the emoji in [`Cleaner`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum EmojiPolicy {
+    /// Keeps the emoji as they are.
+    #[default]
+    Keep,
+    /// Removes the emoji.
+    Drop,
+    /// Replaces each emoji, including its modifiers and joined emoji, with
+    /// the string.
+    Replace(String),
+}
+
+/// Cleaner of the text from social media, such as tweets.
+///
+/// Each pass is enabled by its builder method, so they can be composed as
+/// needed. [`Cleaner::new()`] leaves the text as it is, and
+/// [`Cleaner::sns()`] enables all of them. The spaces left around the
+/// removed parts are squeezed.
+///
+/// ```rust
+/// use lyrian::clean::{Cleaner, EmojiPolicy};
+///
+/// let cleaner = Cleaner::new()
+///     .with_urls_stripped(true)
+///     .with_mentions_stripped(true)
+///     .with_hashtag_bodies_kept(true)
+///     .with_repeats_limited(2)
+///     .with_emoji(EmojiPolicy::Drop);
+/// assert_eq!(
+///     cleaner.clean("@lyrian すごーーーーい😂 #俳句 https://example.com"),
+///     "すごーーい 俳句"
+/// );
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Cleaner {
+    strip_urls: bool,
+    strip_mentions: bool,
+    strip_retweets: bool,
+    strip_hashtags: bool,
+    keep_hashtag_bodies: bool,
+    max_repeats: Option<usize>,
+    emoji: EmojiPolicy,
+}
+
+impl Cleaner {
+    /// Creates a new instance of [`Cleaner`], which leaves the text as it
+    /// is.
+    pub fn new() -> Cleaner {
+        Cleaner::default()
+    }
+
+    /// Creates a new instance of [`Cleaner`] for social media, which strips
+    /// the URLs, the mentions, the RT prefixes and the hashtags, limits the
+    /// repeated characters to 3, and drops the emoji.
+    pub fn sns() -> Cleaner {
+        Cleaner::new()
+            .with_urls_stripped(true)
+            .with_mentions_stripped(true)
+            .with_retweets_stripped(true)
+            .with_hashtags_stripped(true)
+            .with_repeats_limited(3)
+            .with_emoji(EmojiPolicy::Drop)
+    }
+
+    /// Strips the URLs starting with `http://` or `https://`.
+    pub fn with_urls_stripped(mut self, strip: bool) -> Cleaner {
+        self.strip_urls = strip;
+        self
+    }
+
+    /// Strips the mentions like `@lyrian`.
+    pub fn with_mentions_stripped(mut self, strip: bool) -> Cleaner {
+        self.strip_mentions = strip;
+        self
+    }
+
+    /// Strips the prefixes of retweets like `RT @lyrian:` at the start of
+    /// the lines.
+    pub fn with_retweets_stripped(mut self, strip: bool) -> Cleaner {
+        self.strip_retweets = strip;
+        self
+    }
+
+    /// Strips the hashtags like `#俳句`.
+    pub fn with_hashtags_stripped(mut self, strip: bool) -> Cleaner {
+        self.strip_hashtags = strip;
+        self
+    }
+
+    /// Strips only the `#` of the hashtags, so `#俳句` becomes `俳句`.
+    pub fn with_hashtag_bodies_kept(mut self, keep: bool) -> Cleaner {
+        self.keep_hashtag_bodies = keep;
+        self
+    }
+
+    /// Limits the runs of the same character, so `すごーーーーい` becomes
+    /// `すごーーい` by 2.
+    pub fn with_repeats_limited(mut self, max: usize) -> Cleaner {
+        self.max_repeats = Some(max.max(1));
+        self
+    }
+
+    /// Sets what to do with the emoji.
+    pub fn with_emoji(mut self, policy: EmojiPolicy) -> Cleaner {
+        self.emoji = policy;
+        self
+    }
+
+    /// Cleans the text line by line.
+    pub fn clean(&self, text: &str) -> String {
+        text.lines()
+            .map(|l| self.clean_line(l))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    /// Cleans a line of the text.
+    fn clean_line(&self, line: &str) -> String {
+        let mut rest = line;
+        let mut removed = false;
+        if self.strip_retweets {
+            rest = strip_retweet(rest);
+            removed = rest.len() != line.len();
+        }
+
+        let mut cleaned = String::with_capacity(rest.len());
+        let mut prev = None;
+        while let Some(c) = rest.chars().next() {
+            let after_word = matches!(prev, Some(p) if is_word_char(p));
+            let body = &rest[c.len_utf8()..];
+            let skipped = if self.strip_urls
+                && (rest.starts_with("http://") || rest.starts_with("https://"))
+            {
+                span(rest, |c| c.is_ascii_graphic())
+            } else if self.strip_mentions && (c == '@' || c == '＠') && !after_word {
+                match span(body, |c| c.is_ascii_alphanumeric() || c == '_') {
+                    0 => 0,
+                    n => c.len_utf8() + n,
+                }
+            } else if (self.strip_hashtags || self.keep_hashtag_bodies)
+                && (c == '#' || c == '＃')
+                && !after_word
+            {
+                match span(body, is_word_char) {
+                    0 => 0,
+                    _ if self.keep_hashtag_bodies => c.len_utf8(),
+                    n => c.len_utf8() + n,
+                }
+            } else if self.emoji != EmojiPolicy::Keep && is_emoji(c) {
+                if let EmojiPolicy::Replace(r) = &self.emoji {
+                    cleaned.push_str(r);
+                }
+                span(rest, |c| is_emoji(c) || c == '\u{200D}' || c == '\u{FE0F}')
+            } else {
+                0
+            };
+
+            if skipped == 0 {
+                cleaned.push(c);
+                prev = Some(c);
+                rest = body;
+            } else {
+                removed = true;
+                prev = rest[..skipped].chars().last();
+                rest = &rest[skipped..];
+            }
+        }
+
+        if let Some(max) = self.max_repeats {
+            cleaned = limit_repeats(&cleaned, max);
+        }
+        if removed {
+            cleaned = cleaned
+                .split(' ')
+                .filter(|s| !s.is_empty())
+                .collect::<Vec<&str>>()
+                .join(" ");
+        }
+        cleaned
+    }
+}
+
+impl TextFilter for Cleaner {
+    fn filter(&self, text: &str) -> String {
+        self.clean(text)
+    }
+}
+
+/// Filter which transforms the text of a corpus before tokenizing.
+///
+/// [`Cleaner`], [`Pipeline`] and the functions of text such as
+/// [`strip_html()`] and [`normalize::nfkc()`](crate::normalize::nfkc) are
+/// filters. The texts from the loaders in [`corpus`](crate::corpus) are
+/// filtered by [`filter_all()`](#method.filter_all), and the sentences given
+/// to the training entry points are filtered by [`Filtered`].
+pub trait TextFilter {
+    /// Filters the text.
+    fn filter(&self, text: &str) -> String;
+
+    /// Filters each of the texts.
+    fn filter_all<S: AsRef<str>>(&self, texts: &[S]) -> Vec<String>
+    where
+        Self: Sized,
+    {
+        texts.iter().map(|t| self.filter(t.as_ref())).collect()
+    }
+}
+
+impl<F: Fn(&str) -> String> TextFilter for F {
+    fn filter(&self, text: &str) -> String {
+        self(text)
+    }
+}
+
+/// Chain of [`TextFilter`], which are applied in order.
+///
+/// ```rust
+/// use lyrian::clean::{strip_html, Cleaner, Pipeline, TextFilter};
+/// use lyrian::normalize::{fold_halfwidth_kana, to_halfwidth_ascii};
+///
+/// let pipeline = Pipeline::new()
+///     .then(strip_html)
+///     .then(Cleaner::new().with_urls_stripped(true))
+///     .then(fold_halfwidth_kana)
+///     .then(to_halfwidth_ascii);
+/// assert_eq!(
+///     pipeline.filter("<p>ｶﾞｯｺｳ ＡＢＣ https://example.com</p>"),
+///     "ガッコウ ABC"
+/// );
+/// ```
+#[derive(Default)]
+pub struct Pipeline {
+    filters: Vec<Box<dyn TextFilter>>,
+}
+
+impl Pipeline {
+    /// Creates a new instance of [`Pipeline`] without filters, which leaves
+    /// the text as it is.
+    pub fn new() -> Pipeline {
+        Pipeline::default()
+    }
+
+    /// Appends a filter to the pipeline.
+    pub fn then<F: TextFilter + 'static>(mut self, filter: F) -> Pipeline {
+        self.filters.push(Box::new(filter));
+        self
+    }
+}
+
+impl TextFilter for Pipeline {
+    fn filter(&self, text: &str) -> String {
+        self.filters
+            .iter()
+            .fold(text.to_string(), |text, f| f.filter(&text))
+    }
+}
+
+/// Tokenizer which filters the text before tokenizing it by another
+/// tokenizer.
+///
+/// The training entry points such as
+/// [`LyrianModel::from_text_with()`](crate::model::LyrianModel::from_text_with)
+/// split the text into sentences before tokenizing, so the filters which
+/// need the whole text like [`strip_html()`] should be applied to the text
+/// in advance.
+///
+/// ```rust
+/// use lyrian::clean::{Cleaner, Filtered};
+/// use lyrian::model::LyrianModel;
+/// use lyrian::tokenize::LinderaTokenizer;
+///
+/// let tokenizer = Filtered::new(Cleaner::sns(), LinderaTokenizer::new().unwrap());
+/// let model = LyrianModel::from_text_with("古池や #芭蕉\n蛙飛び込む水の音", &tokenizer).unwrap();
+/// assert!(model.tokens().iter().all(|t| t.word != "芭蕉"));
+/// ```
+pub struct Filtered<F, T> {
+    filter: F,
+    tokenizer: T,
+}
+
+impl<F: TextFilter, T: Tokenizer> Filtered<F, T> {
+    /// Creates a new instance of [`Filtered`].
+    pub fn new(filter: F, tokenizer: T) -> Filtered<F, T> {
+        Filtered { filter, tokenizer }
+    }
+}
+
+impl<F: TextFilter, T: Tokenizer> Tokenizer for Filtered<F, T> {
+    fn tokenize(&self, text: &str) -> Result<Vec<LyrianToken>, String> {
+        self.tokenizer.tokenize(&self.filter.filter(text))
+    }
+}
+
+/// Converts HTML into plain text.
+///
+/// The tags and the comments are removed, and the character references like
+/// `&amp;` or `&#12354;` are decoded. The block elements such as `<p>` and
+/// `<br>` become newlines, which end the sentences in
+/// [`split_sentences()`](crate::corpus::split_sentences), and the other
+/// whitespace is squeezed into a space. The contents of `<script>`,
+/// `<style>` and the readings of ruby in `<rt>` are removed.
+///
+/// ```rust
+/// use lyrian::clean::strip_html;
+///
+/// assert_eq!(
+///     strip_html("<h1>古池</h1><p><ruby>蛙<rt>かわず</rt></ruby>飛び込む<br>水の音&hellip;</p>"),
+///     "古池\n蛙飛び込む\n水の音…"
+/// );
+/// ```
+pub fn strip_html(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut skipping: Option<String> = None;
+    let mut rest = html;
+    while let Some(i) = rest.find('<') {
+        if skipping.is_none() {
+            push_html_text(&mut text, &rest[..i]);
+        }
+        rest = &rest[i..];
+
+        if rest.starts_with("<!--") {
+            rest = rest.find("-->").map_or("", |j| &rest[j + 3..]);
+            continue;
+        }
+        let end = match rest.find('>') {
+            Some(end) if starts_tag(&rest[1..]) => end,
+            _ => {
+                if skipping.is_none() {
+                    push_html_text(&mut text, "<");
+                }
+                rest = &rest[1..];
+                continue;
+            }
+        };
+        let tag = &rest[1..end];
+        rest = &rest[end + 1..];
+
+        let closing = tag.starts_with('/');
+        let name = tag
+            .trim_start_matches('/')
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or("")
+            .to_ascii_lowercase();
+        if let Some(skipped) = &skipping {
+            if closing && *skipped == name {
+                skipping = None;
+            }
+        } else if !closing && !tag.ends_with('/') && SKIPPED_ELEMENTS.contains(&name.as_str()) {
+            skipping = Some(name);
+        } else if BLOCK_ELEMENTS.contains(&name.as_str()) {
+            text.push('\n');
+        }
+    }
+    if skipping.is_none() {
+        push_html_text(&mut text, rest);
+    }
+
+    text.lines()
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty())
+        .collect::<Vec<&str>>()
+        .join("\n")
+}
+
+/// Returns whether the text after `<` starts a tag, a comment or a
+/// declaration.
+fn starts_tag(text: &str) -> bool {
+    matches!(text.chars().next(), Some(c) if c.is_ascii_alphabetic() || c == '/' || c == '!')
+}
+
+/// Pushes the text between the tags, decoding the character references and
+/// squeezing the whitespace.
+fn push_html_text(text: &mut String, html: &str) {
+    for c in decode_entities(html).chars() {
+        if c.is_whitespace() && c != '\u{3000}' {
+            if !text.ends_with([' ', '\n']) {
+                text.push(' ');
+            }
+        } else {
+            text.push(c);
+        }
+    }
+}
+
+/// Decodes the character references, leaving the unknown ones as they are.
+fn decode_entities(html: &str) -> String {
+    let mut decoded = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(i) = rest.find('&') {
+        decoded.push_str(&rest[..i]);
+        rest = &rest[i..];
+
+        let reference = rest[1..].find(';').map(|j| &rest[1..j + 1]);
+        let c = reference.and_then(|r| match r.strip_prefix('#') {
+            Some(n) => match n.strip_prefix(['x', 'X']) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                None => n.parse().ok(),
+            }
+            .and_then(std::char::from_u32)
+            .map(|c| c.to_string()),
+            None => ENTITIES
+                .iter()
+                .find(|(name, _)| *name == r)
+                .map(|(_, c)| c.to_string()),
+        });
+        match (reference, c) {
+            (Some(r), Some(c)) => {
+                decoded.push_str(&c);
+                rest = &rest[r.len() + 2..];
+            }
+            _ => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+/// Strips the prefix of a retweet, with the mention and the colon after it.
+fn strip_retweet(line: &str) -> &str {
+    let rest = line.trim_start();
+    let rest = match rest.strip_prefix("RT") {
+        Some(r) if r.is_empty() || r.starts_with([' ', ':', '\u{3000}']) => r,
+        _ => return line,
+    };
+    let rest = rest.trim_start_matches([' ', '\u{3000}']);
+    let rest = match rest.strip_prefix(['@', '＠']) {
+        Some(r) => &r[span(r, |c| c.is_ascii_alphanumeric() || c == '_')..],
+        None => rest,
+    };
+    rest.trim_start_matches([' ', ':', '：', '\u{3000}'])
+}
+
+/// Returns the length in bytes of the prefix whose characters satisfy the
+/// predicate.
+fn span<F: Fn(char) -> bool>(text: &str, predicate: F) -> usize {
+    text.char_indices()
+        .find(|(_, c)| !predicate(*c))
+        .map_or(text.len(), |(i, _)| i)
+}
+
+/// Limits the runs of the same character to `max`.
+fn limit_repeats(text: &str, max: usize) -> String {
+    let mut limited = String::with_capacity(text.len());
+    let mut prev = None;
+    let mut run = 0;
+    for c in text.chars() {
+        run = if prev == Some(c) { run + 1 } else { 1 };
+        prev = Some(c);
+        if run <= max {
+            limited.push(c);
+        }
+    }
+    limited
+}
+
+/// Returns whether the character can be a part of a hashtag.
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Returns whether the character is an emoji or its modifier.
+fn is_emoji(c: char) -> bool {
+    matches!(
+        c,
+        '\u{1F000}'..='\u{1FAFF}'
+            | '\u{2600}'..='\u{27BF}'
+            | '\u{2B00}'..='\u{2BFF}'
+            | '\u{2300}'..='\u{23FF}'
+            | '\u{E0020}'..='\u{E007F}'
+    )
+}
+
+#[cfg(test)]
+mod clean_test {
+    use crate::clean::{strip_html, Cleaner, EmojiPolicy, Filtered, Pipeline, TextFilter};
+    use crate::morphological_analysis::LyrianToken;
+    use crate::normalize::nfkc;
+    use crate::tokenize::Tokenizer;
+
+    #[test]
+    fn clean_tweet() {
+        assert_eq!(
+            Cleaner::sns().clean("RT @lyrian: 古池や #芭蕉 https://t.co/abc\n蛙飛び込むーーーー🐸"),
+            "古池や\n蛙飛び込むーーー".to_string()
+        )
+    }
+
+    #[test]
+    fn keep_email_and_text_without_passes() {
+        let text = "mail@example.com RT  #1";
+        assert_eq!(
+            (
+                Cleaner::new().clean(text),
+                Cleaner::sns().clean("mail@example.com")
+            ),
+            (text.to_string(), "mail@example.com".to_string())
+        )
+    }
+
+    #[test]
+    fn replace_joined_emoji() {
+        let cleaner = Cleaner::new().with_emoji(EmojiPolicy::Replace("。".to_string()));
+        assert_eq!(
+            cleaner.clean("家族👨\u{200D}👩\u{200D}👧楽しい"),
+            "家族。楽しい"
+        )
+    }
+
+    #[test]
+    fn strip_tags_and_scripts_of_html() {
+        let html = "<html><head><title>題</title></head>\n<body><!-- 広告 --><script>let a = 1 < 2;</script>\n<div class=\"main\">古池や  蛙飛び込む</div>\n<p>A &amp; B &#x3042;&#12356; &unknown;</p></body></html>";
+        assert_eq!(
+            strip_html(html),
+            "古池や 蛙飛び込む\nA & B あい &unknown;".to_string()
+        )
+    }
+
+    #[test]
+    fn filter_texts_through_pipeline() {
+        let pipeline = Pipeline::new()
+            .then(Cleaner::new().with_mentions_stripped(true))
+            .then(nfkc)
+            .then(|text: &str| text.replace("ーー", "ー"));
+        assert_eq!(
+            pipeline.filter_all(&["@lyrian ＡＢＣ", "すごーーい"]),
+            vec!["ABC".to_string(), "すごーい".to_string()]
+        )
+    }
+
+    #[test]
+    fn tokenize_filtered_text() {
+        let split = |text: &str| -> Result<Vec<LyrianToken>, String> {
+            Ok(text
+                .split_whitespace()
+                .map(|w| LyrianToken::new(w.into(), w.into(), w.into(), "名詞".into()))
+                .collect())
+        };
+        let tokens = Filtered::new(Cleaner::sns(), split)
+            .tokenize("RT @lyrian: フルイケ ヤ #芭蕉")
+            .unwrap();
+        assert_eq!(
+            tokens
+                .iter()
+                .map(|t| t.word.as_str())
+                .collect::<Vec<&str>>(),
+            vec!["フルイケ", "ヤ"]
+        )
+    }
+}
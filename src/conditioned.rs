@@ -0,0 +1,160 @@
+//! Models conditioned on the labels of the texts, such as genres and
+//! authors.
+
+use std::collections::BTreeMap;
+
+use crate::corpus::split_sentences;
+use crate::markov::ChainBuilder;
+use crate::model::LyrianModel;
+use crate::morphological_analysis::LyrianToken;
+use crate::tokenize::Tokenizer;
+
+/// Models of the labels of the texts, which share the vocabulary of the
+/// global model of all the texts.
+///
+/// The model of each label counts only the transitions in the texts of the
+/// label, and generates the sentences like them by the methods of
+/// [`LyrianModel`]. The tokens of the other labels are kept in its
+/// vocabulary without transitions, and never chosen at random.
+///
+/// ```rust
+/// use lyrian::conditioned::ConditionedModel;
+/// use lyrian::tokenize::LyrianToken;
+///
+/// let chars = |text: &str| -> Result<Vec<LyrianToken>, String> {
+///     Ok(text
+///         .chars()
+///         .map(|c| LyrianToken::new(c.into(), c.into(), c.into(), "名詞".into()))
+///         .collect())
+/// };
+/// let texts = [("fruit", "すもももももももものうち。"), ("sea", "うみはひろいな。")];
+/// let mut model = ConditionedModel::from_texts_with(&texts, &chars).unwrap();
+/// let sentence = model.generate_sentence("sea", 20).unwrap();
+/// assert!(!sentence.contains('も'));
+/// ```
+pub struct ConditionedModel {
+    global: LyrianModel,
+    labels: BTreeMap<String, LyrianModel>,
+}
+
+impl ConditionedModel {
+    /// Builds the models from the pairs of the labels and the sequences of
+    /// tokens, such as sentences.
+    pub fn from_sequences<L: AsRef<str>>(sequences: &[(L, Vec<LyrianToken>)]) -> ConditionedModel {
+        let mut global = ChainBuilder::new();
+        for (_, sequence) in sequences {
+            global.add(sequence);
+        }
+        let global = global.build();
+
+        let mut builders = BTreeMap::new();
+        for (label, sequence) in sequences {
+            builders
+                .entry(label.as_ref().to_string())
+                .or_insert_with(|| ChainBuilder::with_states(global.states()))
+                .add(sequence);
+        }
+        ConditionedModel {
+            global: LyrianModel::new(global),
+            labels: builders
+                .into_iter()
+                .map(|(label, builder)| (label, LyrianModel::new(builder.build())))
+                .collect(),
+        }
+    }
+
+    /// Builds the models from the pairs of the labels and the texts
+    /// tokenized by the tokenizer, split into sentences like
+    /// [`LyrianModel::from_text()`].
+    pub fn from_texts_with<L: AsRef<str>, S: AsRef<str>, T: Tokenizer + ?Sized>(
+        texts: &[(L, S)],
+        tokenizer: &T,
+    ) -> Result<ConditionedModel, String> {
+        let mut sequences = Vec::new();
+        for (label, text) in texts {
+            for sentence in split_sentences(text.as_ref()) {
+                sequences.push((label.as_ref(), tokenizer.tokenize(sentence)?));
+            }
+        }
+        Ok(ConditionedModel::from_sequences(&sequences))
+    }
+
+    /// Backs off the models of the labels to the global model, so that the
+    /// tokens never seen in a label follow the transitions of all the
+    /// texts, e.g. in [`LyrianModel::generate_from()`].
+    ///
+    /// ```rust
+    /// use lyrian::conditioned::ConditionedModel;
+    /// use lyrian::tokenize::LyrianToken;
+    ///
+    /// let chars = |text: &str| -> Result<Vec<LyrianToken>, String> {
+    ///     Ok(text
+    ///         .chars()
+    ///         .map(|c| LyrianToken::new(c.into(), c.into(), c.into(), "名詞".into()))
+    ///         .collect())
+    /// };
+    /// let texts = [("fruit", "すもももももももものうち。"), ("sea", "うみはひろいな。")];
+    /// let mut model = ConditionedModel::from_texts_with(&texts, &chars)
+    ///     .unwrap()
+    ///     .with_back_off();
+    /// let sea = model.model_mut("sea").unwrap();
+    /// assert_eq!(sea.generate_from("の", 10), Ok("のうみはひろいな。".to_string()));
+    /// ```
+    pub fn with_back_off(mut self) -> ConditionedModel {
+        for model in self.labels.values_mut() {
+            model.back_off_to(&self.global);
+        }
+        self
+    }
+
+    /// Returns the labels of the models in order.
+    pub fn labels(&self) -> Vec<&str> {
+        self.labels.keys().map(|l| l.as_str()).collect()
+    }
+
+    /// Returns the model of all the texts.
+    pub fn global_mut(&mut self) -> &mut LyrianModel {
+        &mut self.global
+    }
+
+    /// Returns the model of the label, or [`None`] for an unknown label.
+    pub fn model_mut(&mut self, label: &str) -> Option<&mut LyrianModel> {
+        self.labels.get_mut(label)
+    }
+
+    /// Generates a sentence conditioned on the label, like
+    /// [`LyrianModel::generate_sentence()`].
+    pub fn generate_sentence(&mut self, label: &str, max_tokens: usize) -> Result<String, String> {
+        match self.labels.get_mut(label) {
+            Some(model) => Ok(model.generate_sentence(max_tokens)),
+            None => Err(format!("The label `{}` is not in the model.", label)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod conditioned_test {
+    use crate::conditioned::ConditionedModel;
+    use crate::morphological_analysis::noun;
+
+    #[test]
+    fn share_vocabulary_between_labels() {
+        let mut model = ConditionedModel::from_sequences(&[
+            ("a", vec![noun("ア"), noun("イ")]),
+            ("b", vec![noun("ウ"), noun("エ")]),
+            ("a", vec![noun("イ"), noun("オ")]),
+        ]);
+        let a = model.model_mut("a").unwrap();
+        let vocabulary = a.tokens().len();
+        let sentences = (0..10)
+            .map(|_| a.generate_sentence(10))
+            .collect::<Vec<String>>();
+        assert!(
+            vocabulary == 5
+                && sentences
+                    .iter()
+                    .all(|s| s.ends_with("オ") && !s.contains("ウ"))
+                && model.generate_sentence("c", 10).is_err()
+        )
+    }
+}
@@ -0,0 +1,616 @@
+//! Loading and preprocessing of corpora.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+
+use crate::chars::is_kanji;
+use crate::reading::ReadingOverrides;
+
+/// The characters which end sentences.
+pub(crate) const TERMINATORS: [char; 8] = ['。', '！', '？', '!', '?', '．', '…', '‥'];
+
+/// The closing brackets which belong to the sentences ended before them.
+const CLOSING_BRACKETS: [char; 6] = ['」', '』', '）', ')', '】', '”'];
+
+/// Splits Japanese text into sentences.
+///
+/// A sentence ends with `。`, `！`, `？`, ellipses such as `……`, or a
+/// newline. The successive terminators and the closing brackets after them,
+/// such as `」`, belong to the sentence. The whitespace around the
+/// sentences is trimmed, and the empty ones are skipped.
+///
+/// ```rust
+/// use lyrian::corpus::split_sentences;
+///
+/// assert_eq!(
+///     split_sentences("「おはよう！」と言った。そして……\n帰った"),
+///     vec!["「おはよう！」", "と言った。", "そして……", "帰った"]
+/// );
+/// ```
+pub fn split_sentences<'a>(text: &'a str) -> Vec<&'a str> {
+    let mut sentences = Vec::new();
+    let mut push = |sentence: &'a str| {
+        let sentence = sentence.trim();
+        if !sentence.is_empty() {
+            sentences.push(sentence);
+        }
+    };
+
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        if c == '\n' || c == '\r' {
+            push(&text[start..i]);
+            start = i + c.len_utf8();
+        } else if TERMINATORS.contains(&c) {
+            let mut end = i + c.len_utf8();
+            while let Some(&(j, d)) = chars.peek() {
+                if !(TERMINATORS.contains(&d) || CLOSING_BRACKETS.contains(&d)) {
+                    break;
+                }
+                end = j + d.len_utf8();
+                chars.next();
+            }
+            push(&text[start..end]);
+            start = end;
+        }
+    }
+    push(&text[start..]);
+    sentences
+}
+
+/// Loads the text of a corpus from a file.
+///
+/// The encoding is detected from the byte order mark, and the text without
+/// it is assumed to be UTF-8. The text which is not UTF-8 is decoded from
+/// Shift_JIS if the `shift_jis` feature is enabled, or an error is returned
+/// otherwise.
+pub fn load_file<P: AsRef<Path>>(path: P) -> Result<String, String> {
+    let bytes = fs::read(path).map_err(|e| e.to_string())?;
+    decode(&bytes)
+}
+
+/// Loads the texts of the files in the directory and its subdirectories,
+/// whose names match the pattern.
+///
+/// The pattern is a wildcard, where `*` matches any characters and `?`
+/// matches a character, e.g. `*.txt`. The files are loaded by
+/// [`load_file()`] in the order of their paths. The symbolic links to
+/// directories are not followed.
+pub fn load_dir<P: AsRef<Path>>(path: P, pattern: &str) -> Result<Vec<(PathBuf, String)>, String> {
+    find_files(path.as_ref(), pattern)?
+        .into_iter()
+        .map(|p| load_file(&p).map(|text| (p, text)))
+        .collect()
+}
+
+/// Loads the texts of the files like [`load_dir()`], reading them with the
+/// number of threads.
+pub fn load_dir_parallel<P: AsRef<Path>>(
+    path: P,
+    pattern: &str,
+    threads: usize,
+) -> Result<Vec<(PathBuf, String)>, String> {
+    let paths = find_files(path.as_ref(), pattern)?;
+    let threads = threads.max(1);
+    let size = (paths.len() + threads - 1) / threads;
+    let handles = paths
+        .chunks(size.max(1))
+        .map(|chunk| {
+            let chunk = chunk.to_vec();
+            thread::spawn(move || {
+                chunk
+                    .into_iter()
+                    .map(|p| load_file(&p).map(|text| (p, text)))
+                    .collect::<Result<Vec<(PathBuf, String)>, String>>()
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let mut files = Vec::with_capacity(paths.len());
+    for handle in handles {
+        match handle.join() {
+            Ok(loaded) => files.extend(loaded?),
+            Err(_) => return Err("Could not load the files.".to_string()),
+        }
+    }
+    Ok(files)
+}
+
+/// Finds the files in the directory and its subdirectories whose names
+/// match the pattern, sorted by their paths.
+fn find_files(dir: &Path, pattern: &str) -> Result<Vec<PathBuf>, String> {
+    let mut files = Vec::new();
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(&dir).map_err(|e| e.to_string())? {
+            let entry = entry.map_err(|e| e.to_string())?;
+            let path = entry.path();
+            // The links to directories are not followed, which may loop.
+            if entry.file_type().map_err(|e| e.to_string())?.is_dir() {
+                dirs.push(path);
+            } else if path.is_dir() {
+                continue;
+            } else if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
+                if matches_wildcard(name, pattern) {
+                    files.push(path);
+                }
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Returns whether the name matches the wildcard pattern of `*` and `?`.
+fn matches_wildcard(name: &str, pattern: &str) -> bool {
+    let name = name.chars().collect::<Vec<char>>();
+    let pattern = pattern.chars().collect::<Vec<char>>();
+    // matched[j] is whether the name so far matches the first j characters
+    // of the pattern.
+    let mut matched = vec![false; pattern.len() + 1];
+    matched[0] = true;
+    for j in 0..pattern.len() {
+        matched[j + 1] = matched[j] && pattern[j] == '*';
+    }
+    for c in name {
+        let mut next = vec![false; pattern.len() + 1];
+        for (j, p) in pattern.iter().enumerate() {
+            next[j + 1] = match p {
+                '*' => next[j] || matched[j + 1],
+                '?' => matched[j],
+                p => matched[j] && *p == c,
+            };
+        }
+        matched = next;
+    }
+    matched[pattern.len()]
+}
+
+/// Reads the texts in JSON Lines, where each line is an object which has
+/// the text in the field.
+///
+/// The field of a nested object is given by the names joined with `.`,
+/// such as `user.description`. The empty lines, and the objects whose field
+/// is not a string, are skipped.
+///
+/// ```rust
+/// use lyrian::corpus::read_jsonl;
+///
+/// let jsonl = "{\"id\": 1, \"text\": \"古池や\"}\n{\"id\": 2}\n";
+/// assert_eq!(read_jsonl(jsonl, "text"), Ok(vec!["古池や".to_string()]));
+/// ```
+pub fn read_jsonl(contents: &str, field: &str) -> Result<Vec<String>, String> {
+    let mut texts = Vec::new();
+    for (i, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let value = serde_json::from_str::<serde_json::Value>(line)
+            .map_err(|e| format!("{} at line {}", e, i + 1))?;
+        let text = field
+            .split('.')
+            .try_fold(&value, |v, name| v.get(name))
+            .and_then(|v| v.as_str());
+        if let Some(text) = text {
+            texts.push(text.to_string());
+        }
+    }
+    Ok(texts)
+}
+
+/// Loads the texts in a file of JSON Lines, see [`read_jsonl()`] for the
+/// details.
+pub fn load_jsonl<P: AsRef<Path>>(path: P, field: &str) -> Result<Vec<String>, String> {
+    read_jsonl(&load_file(path)?, field)
+}
+
+/// Column of CSV and TSV which has the texts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Column {
+    /// The column of the index from `0`, where all the rows are the records.
+    Index(usize),
+    /// The column of the name in the first row, which is the header.
+    Name(String),
+}
+
+/// Reads the texts in the column of CSV.
+///
+/// The fields may be quoted by `"`, and the quoted ones may have commas,
+/// newlines and the quotes escaped as `""`. The records which lack the
+/// column and the empty fields are skipped.
+///
+/// ```rust
+/// use lyrian::corpus::{read_csv, Column};
+///
+/// let csv = "id,answer\n1,\"古池や、\n蛙飛び込む\"\n2,\n";
+/// assert_eq!(
+///     read_csv(csv, &Column::Name("answer".to_string())),
+///     Ok(vec!["古池や、\n蛙飛び込む".to_string()])
+/// );
+/// ```
+pub fn read_csv(contents: &str, column: &Column) -> Result<Vec<String>, String> {
+    read_delimited(contents, ',', column)
+}
+
+/// Reads the texts in the column of TSV, quoted like [`read_csv()`].
+pub fn read_tsv(contents: &str, column: &Column) -> Result<Vec<String>, String> {
+    read_delimited(contents, '\t', column)
+}
+
+/// Loads the texts in the column of a file of CSV, or TSV if its extension
+/// is `tsv`.
+pub fn load_csv<P: AsRef<Path>>(path: P, column: &Column) -> Result<Vec<String>, String> {
+    let contents = load_file(&path)?;
+    match path.as_ref().extension() {
+        Some(ext) if ext == "tsv" => read_tsv(&contents, column),
+        _ => read_csv(&contents, column),
+    }
+}
+
+/// Reads the texts in the column of the records separated by the delimiter.
+fn read_delimited(contents: &str, delimiter: char, column: &Column) -> Result<Vec<String>, String> {
+    let mut records = parse_records(contents, delimiter)?.into_iter();
+    let index = match column {
+        Column::Index(i) => *i,
+        Column::Name(name) => records
+            .next()
+            .and_then(|header| header.iter().position(|h| h.trim() == name))
+            .ok_or(format!("There is no column `{}`.", name))?,
+    };
+    Ok(records
+        .filter_map(|mut r| match r.get(index) {
+            Some(field) if !field.is_empty() => Some(r.swap_remove(index)),
+            _ => None,
+        })
+        .collect())
+}
+
+/// Parses the records of the fields separated by the delimiter, which may be
+/// quoted by `"`.
+fn parse_records(contents: &str, delimiter: char) -> Result<Vec<Vec<String>>, String> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = contents.chars().peekable();
+    while let Some(c) = chars.next() {
+        if quoted {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    field.push('"');
+                    chars.next();
+                }
+                '"' => quoted = false,
+                c => field.push(c),
+            }
+        } else if c == '"' && field.is_empty() {
+            quoted = true;
+        } else if c == delimiter {
+            record.push(std::mem::take(&mut field));
+        } else if c == '\n' || c == '\r' {
+            if c == '\r' && chars.peek() == Some(&'\n') {
+                chars.next();
+            }
+            record.push(std::mem::take(&mut field));
+            records.push(std::mem::take(&mut record));
+        } else {
+            field.push(c);
+        }
+    }
+    if quoted {
+        return Err("A quoted field is not closed.".to_string());
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    Ok(records)
+}
+
+/// Text of Aozora Bunko without its markup, by [`read_aozora()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AozoraText {
+    /// The body of the text without the header, the footer and the markup.
+    pub text: String,
+
+    /// The readings given by the ruby, which can be used to train a model by
+    /// [`LyrianModel::from_str_with_overrides()`](crate::model::LyrianModel::from_str_with_overrides).
+    pub readings: ReadingOverrides,
+}
+
+/// Preprocesses a text of [Aozora Bunko](https://www.aozora.gr.jp/) to use
+/// it as a corpus.
+///
+/// The header before the explanation of the symbols, which is enclosed by
+/// the lines of `-`, and the footer from `底本：` are removed. The
+/// annotations like `［＃「」は縦中横］` are removed, and the ruby like
+/// `｜青空《あおぞら》` or `文庫《ぶんこ》` is removed from the text and
+/// kept in the readings, where the ruby without `｜` is given to the kanji
+/// just before it.
+///
+/// ```rust
+/// use lyrian::corpus::read_aozora;
+///
+/// let aozora = read_aozora("吾輩《わがはい》は猫である［＃「猫」に傍点］。\n\n底本：「夏目漱石全集」");
+/// assert_eq!(aozora.text, "吾輩は猫である。");
+/// assert_eq!(aozora.readings.get("吾輩"), Some("わがはい"));
+/// ```
+pub fn read_aozora(contents: &str) -> AozoraText {
+    let lines = contents.lines().collect::<Vec<&str>>();
+    let is_rule = |l: &&str| l.len() >= 8 && l.chars().all(|c| c == '-');
+    let rules = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, l)| is_rule(l))
+        .map(|(i, _)| i)
+        .take(2)
+        .collect::<Vec<usize>>();
+    let start = match rules.as_slice() {
+        [_, end] => end + 1,
+        _ => 0,
+    };
+    let end = lines
+        .iter()
+        .rposition(|l| l.starts_with("底本："))
+        .filter(|i| *i >= start)
+        .unwrap_or(lines.len());
+    let body = lines[start..end].join("\n");
+
+    let mut text = String::with_capacity(body.len());
+    let mut readings = ReadingOverrides::new();
+    let mut ruby_start = None;
+    let mut chars = body.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '｜' => ruby_start = Some(text.len()),
+            '《' => {
+                let reading = chars
+                    .by_ref()
+                    .take_while(|c| *c != '》')
+                    .collect::<String>();
+                let start = match ruby_start.take() {
+                    Some(start) => start,
+                    None => text
+                        .char_indices()
+                        .rev()
+                        .take_while(|(_, c)| is_kanji(*c) || *c == 'ヶ')
+                        .last()
+                        .map_or(text.len(), |(i, _)| i),
+                };
+                if start < text.len() && !reading.is_empty() {
+                    readings.insert(&text[start..], &reading);
+                }
+            }
+            '［' if chars.peek() == Some(&'＃') => {
+                chars.by_ref().find(|c| *c == '］');
+            }
+            c => text.push(c),
+        }
+    }
+
+    AozoraText {
+        text: text.trim().to_string(),
+        readings,
+    }
+}
+
+/// The number of the most frequent tokens in [`CorpusStats`].
+const FREQUENCY_HEAD: usize = 10;
+
+/// Statistics of a corpus of token sequences, by [`stats()`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct CorpusStats<T> {
+    /// The number of the sequences.
+    pub sequences: usize,
+    /// The number of the tokens.
+    pub tokens: usize,
+    /// The number of the distinct tokens.
+    pub vocabulary: usize,
+    /// The vocabulary size divided by the number of the tokens.
+    pub type_token_ratio: f64,
+    /// The average number of the tokens in a sequence.
+    pub average_length: f64,
+    /// The 10 most frequent tokens and their counts, in descending order of
+    /// the counts.
+    pub most_frequent: Vec<(T, usize)>,
+}
+
+/// Computes the statistics of a corpus of token sequences, such as the
+/// tokenized sentences, to check the data before training.
+///
+/// ```rust
+/// use lyrian::corpus::stats;
+///
+/// let stats = stats(&[vec!["古池", "や"], vec!["蛙", "飛び込む", "水", "の", "音", "や"]]);
+/// assert_eq!((stats.tokens, stats.vocabulary, stats.average_length), (8, 7, 4.0));
+/// assert_eq!(stats.most_frequent[0], ("や", 2));
+/// ```
+pub fn stats<T: Clone + Ord>(sequences: &[Vec<T>]) -> CorpusStats<T> {
+    let mut counts = BTreeMap::new();
+    for token in sequences.iter().flatten() {
+        *counts.entry(token).or_insert(0) += 1;
+    }
+    let tokens = sequences.iter().map(|s| s.len()).sum::<usize>();
+    let ratio = |n: usize, total: usize| match total {
+        0 => 0.0,
+        _ => n as f64 / total as f64,
+    };
+
+    let mut frequencies = counts.into_iter().collect::<Vec<(&T, usize)>>();
+    frequencies.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+    CorpusStats {
+        sequences: sequences.len(),
+        tokens,
+        vocabulary: frequencies.len(),
+        type_token_ratio: ratio(frequencies.len(), tokens),
+        average_length: ratio(tokens, sequences.len()),
+        most_frequent: frequencies
+            .into_iter()
+            .take(FREQUENCY_HEAD)
+            .map(|(t, n)| (t.clone(), n))
+            .collect(),
+    }
+}
+
+/// Decodes the bytes of a corpus, see [`load_file()`] for the details.
+pub fn decode(bytes: &[u8]) -> Result<String, String> {
+    match bytes {
+        [0xEF, 0xBB, 0xBF, rest @ ..] => decode_utf8(rest),
+        [0xFF, 0xFE, rest @ ..] => decode_utf16(rest, u16::from_le_bytes),
+        [0xFE, 0xFF, rest @ ..] => decode_utf16(rest, u16::from_be_bytes),
+        _ => match decode_utf8(bytes) {
+            Ok(text) => Ok(text),
+            Err(e) => decode_fallback(bytes).ok_or(e),
+        },
+    }
+}
+
+/// Decodes the bytes in UTF-8.
+fn decode_utf8(bytes: &[u8]) -> Result<String, String> {
+    match std::str::from_utf8(bytes) {
+        Ok(text) => Ok(text.to_string()),
+        Err(e) => Err(format!("The text is not encoded in UTF-8: {}", e)),
+    }
+}
+
+/// Decodes the bytes in UTF-16 of the byte order.
+fn decode_utf16(bytes: &[u8], from_bytes: fn([u8; 2]) -> u16) -> Result<String, String> {
+    let units = bytes
+        .chunks(2)
+        .map(|c| match c {
+            [a, b] => from_bytes([*a, *b]),
+            _ => 0xFFFD,
+        })
+        .collect::<Vec<u16>>();
+    String::from_utf16(&units).map_err(|e| e.to_string())
+}
+
+/// Decodes the bytes in Shift_JIS, or [`None`] if they are not.
+#[cfg(feature = "shift_jis")]
+fn decode_fallback(bytes: &[u8]) -> Option<String> {
+    let (text, _, had_errors) = encoding_rs::SHIFT_JIS.decode(bytes);
+    if had_errors {
+        None
+    } else {
+        Some(text.into_owned())
+    }
+}
+
+/// Gives no other encoding than UTF-8 without the `shift_jis` feature.
+#[cfg(not(feature = "shift_jis"))]
+fn decode_fallback(_: &[u8]) -> Option<String> {
+    None
+}
+
+#[cfg(test)]
+mod corpus_test {
+    use crate::corpus::{
+        decode, load_dir, load_dir_parallel, matches_wildcard, read_aozora, read_jsonl, read_tsv,
+        split_sentences, stats, Column,
+    };
+
+    #[test]
+    fn split_text_into_sentences() {
+        assert_eq!(
+            split_sentences("古池や。蛙飛び込む！？\r\n\n　水の音"),
+            vec!["古池や。", "蛙飛び込む！？", "水の音"]
+        )
+    }
+
+    #[test]
+    fn keep_closing_brackets_in_sentence() {
+        assert_eq!(
+            split_sentences("『雪だ。』と彼は言った"),
+            vec!["『雪だ。』", "と彼は言った"]
+        )
+    }
+
+    #[test]
+    fn decode_text_with_byte_order_mark() {
+        let utf8 = [&[0xEF, 0xBB, 0xBF][..], "古池".as_bytes()].concat();
+        let utf16 = [0xFF, 0xFE, 0xE4, 0x53, 0x60, 0x6C];
+        assert_eq!(
+            (decode(&utf8), decode(&utf16)),
+            (Ok("古池".to_string()), Ok("古池".to_string()))
+        )
+    }
+
+    #[test]
+    fn match_file_names_by_wildcard() {
+        assert_eq!(
+            (
+                matches_wildcard("basho.txt", "*.txt"),
+                matches_wildcard("basho.txt.bak", "*.txt"),
+                matches_wildcard("a1.txt", "a?.*")
+            ),
+            (true, false, true)
+        )
+    }
+
+    #[test]
+    fn load_files_in_directory() {
+        let dir = std::env::temp_dir().join(format!("lyrian-corpus-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        std::fs::write(dir.join("a.txt"), "古池や").unwrap();
+        std::fs::write(dir.join("sub").join("b.txt"), "蛙飛び込む").unwrap();
+        std::fs::write(dir.join("c.md"), "水の音").unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(&dir, dir.join("sub").join("loop.txt")).unwrap();
+
+        let files = load_dir(&dir, "*.txt");
+        let parallel = load_dir_parallel(&dir, "*.txt", 4);
+        std::fs::remove_dir_all(&dir).unwrap();
+        let files = files.unwrap();
+        assert_eq!(
+            (
+                files.iter().map(|(_, t)| t.as_str()).collect::<Vec<&str>>(),
+                parallel.unwrap()
+            ),
+            (vec!["古池や", "蛙飛び込む"], files.clone())
+        )
+    }
+
+    #[test]
+    fn read_nested_field_of_json_lines() {
+        let jsonl = "{\"tweet\": {\"full_text\": \"古池や\"}}\n\n{\"tweet\": {}}\n";
+        assert_eq!(
+            (
+                read_jsonl(jsonl, "tweet.full_text"),
+                read_jsonl("{", "text").is_err()
+            ),
+            (Ok(vec!["古池や".to_string()]), true)
+        )
+    }
+
+    #[test]
+    fn read_quoted_fields_of_tsv() {
+        let tsv = "1\t\"say \"\"hi\"\"\t\"\r\n2\tplain\n3";
+        assert_eq!(
+            read_tsv(tsv, &Column::Index(1)),
+            Ok(vec!["say \"hi\"\t".to_string(), "plain".to_string()])
+        )
+    }
+
+    #[test]
+    fn strip_header_and_ruby_of_aozora() {
+        let aozora = read_aozora(
+            "銀河鉄道の夜\n宮沢賢治\n\n-------------------------------------------------------\n【テキスト中に現れる記号について】\n-------------------------------------------------------\n\n｜天気輪《てんきりん》の柱\n",
+        );
+        assert_eq!(
+            (aozora.text, aozora.readings.get("天気輪")),
+            ("天気輪の柱".to_string(), Some("てんきりん"))
+        )
+    }
+
+    #[test]
+    fn compute_stats_of_empty_corpus() {
+        let stats = stats::<char>(&[vec![], vec![]]);
+        assert_eq!(
+            (stats.tokens, stats.type_token_ratio, stats.average_length),
+            (0, 0.0, 0.0)
+        )
+    }
+}
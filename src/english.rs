@@ -0,0 +1,170 @@
+//! Counting the number of syllables of English.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::morphological_analysis::LyrianToken;
+use crate::pronunciation::MoraCounter;
+
+/// Counter of English by syllable unit.
+///
+/// The syllables of a word are looked up in the pronouncing dictionary, such
+/// as [CMUdict](https://github.com/cmusphinx/cmudict), and guessed by
+/// [`guess_syllables()`] if the word is not in it.
+///
+/// ```rust
+/// use lyrian::english::EnglishCounter;
+/// use lyrian::pronunciation::MoraCounter;
+///
+/// let counter = EnglishCounter::from_cmudict("AUTUMN  AO1 T AH0 M\n");
+/// assert_eq!(counter.count("an autumn moon"), 4);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EnglishCounter {
+    dict: HashMap<String, usize>,
+}
+
+impl EnglishCounter {
+    /// Creates a new instance of [`EnglishCounter`] without a dictionary.
+    ///
+    /// All the syllables are guessed by [`guess_syllables()`].
+    pub fn new() -> EnglishCounter {
+        EnglishCounter::default()
+    }
+
+    /// Creates a new instance of [`EnglishCounter`] from the contents of
+    /// CMUdict.
+    ///
+    /// Each line consists of a word and its phonemes, whose vowels have the
+    /// stress digits. The comment lines starting with `;;;` and the
+    /// alternative pronunciations like `WORD(2)` are skipped.
+    pub fn from_cmudict(contents: &str) -> EnglishCounter {
+        let mut counter = EnglishCounter::new();
+        for line in contents.lines() {
+            if line.starts_with(";;;") {
+                continue;
+            }
+
+            let mut fields = line.split_whitespace();
+            let word = match fields.next() {
+                Some(w) if !w.ends_with(')') => w,
+                _ => continue,
+            };
+            let syllables = fields
+                .filter(|p| p.ends_with(|c: char| c.is_ascii_digit()))
+                .count();
+            if syllables > 0 {
+                counter.insert(word, syllables);
+            }
+        }
+        counter
+    }
+
+    /// Loads CMUdict from a file.
+    ///
+    /// See [`from_cmudict()`](#method.from_cmudict) for the format.
+    pub fn load_cmudict<P: AsRef<Path>>(path: P) -> Result<EnglishCounter, String> {
+        match fs::read_to_string(path) {
+            Ok(contents) => Ok(EnglishCounter::from_cmudict(&contents)),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    /// Adds a word and its number of syllables to the dictionary.
+    pub fn insert(&mut self, word: &str, syllables: usize) {
+        self.dict.insert(word.to_ascii_lowercase(), syllables);
+    }
+
+    /// Calculates the number of syllables of a word.
+    pub fn count_word(&self, word: &str) -> usize {
+        match self.dict.get(&word.to_ascii_lowercase()) {
+            Some(syllables) => *syllables,
+            None => guess_syllables(word),
+        }
+    }
+}
+
+impl MoraCounter for EnglishCounter {
+    fn count(&self, text: &str) -> usize {
+        text.split(|c: char| !(c.is_ascii_alphabetic() || c == '\''))
+            .filter(|w| w.chars().any(|c| c.is_ascii_alphabetic()))
+            .map(|w| self.count_word(w))
+            .sum()
+    }
+
+    fn count_token(&self, token: &LyrianToken) -> usize {
+        self.count(&token.word)
+    }
+}
+
+/// Guesses the number of syllables of an English word by its spelling.
+///
+/// The groups of vowels are counted, except a silent `e` at the end of the
+/// word and the endings `-es` and `-ed` which are not pronounced separately.
+///
+/// ```rust
+/// use lyrian::english::guess_syllables;
+///
+/// assert_eq!(guess_syllables("haiku"), 2);
+/// assert_eq!(guess_syllables("stone"), 1);
+/// ```
+pub fn guess_syllables(word: &str) -> usize {
+    let word = word
+        .chars()
+        .filter(|c| c.is_ascii_alphabetic())
+        .map(|c| c.to_ascii_lowercase())
+        .collect::<String>();
+    if word.is_empty() {
+        return 0;
+    } else if word.len() <= 3 {
+        return 1;
+    }
+
+    let is_vowel = |c: char| "aeiouy".contains(c);
+    let chars = word.chars().collect::<Vec<char>>();
+    let mut syllables = chars
+        .iter()
+        .enumerate()
+        .filter(|(i, c)| is_vowel(**c) && (*i == 0 || !is_vowel(chars[i - 1])))
+        .count();
+
+    let n = chars.len();
+    let consonant_le = chars[n - 2] == 'l' && !is_vowel(chars[n - 3]);
+    let silent_e = chars[n - 1] == 'e' && !is_vowel(chars[n - 2]) && !consonant_le;
+    let silent_ending = (word.ends_with("es") || word.ends_with("ed"))
+        && !is_vowel(chars[n - 3])
+        && !matches!(chars[n - 3], 't' | 'd' | 's' | 'z' | 'x' | 'c' | 'g' | 'h');
+    if silent_e || silent_ending {
+        syllables -= 1;
+    }
+
+    syllables.max(1)
+}
+
+#[cfg(test)]
+mod english_test {
+    use crate::english::{guess_syllables, EnglishCounter};
+    use crate::pronunciation::MoraCounter;
+
+    #[test]
+    fn guess_syllables_of_words() {
+        assert_eq!(
+            (
+                guess_syllables("table"),
+                guess_syllables("jumped"),
+                guess_syllables("wanted"),
+                guess_syllables("beautiful")
+            ),
+            (2, 1, 2, 3)
+        )
+    }
+
+    #[test]
+    fn count_syllables_by_cmudict() {
+        let counter = EnglishCounter::from_cmudict(
+            ";;; comment\nFIRE  F AY1 ER0\nFIRE(2)  F AY1 R\nFLY  F L AY1\n",
+        );
+        assert_eq!(counter.count("Fire-fly!"), 3)
+    }
+}
@@ -0,0 +1,596 @@
+//! Expansion of numerals and Latin letters into katakana readings.
+
+use std::collections::BTreeMap;
+
+/// Readings of the digits.
+const DIGITS: [&str; 10] = [
+    "ゼロ",
+    "イチ",
+    "ニ",
+    "サン",
+    "ヨン",
+    "ゴ",
+    "ロク",
+    "ナナ",
+    "ハチ",
+    "キュウ",
+];
+
+/// Readings of the Latin letters from `A` to `Z`.
+const LETTERS: [&str; 26] = [
+    "エー",
+    "ビー",
+    "シー",
+    "ディー",
+    "イー",
+    "エフ",
+    "ジー",
+    "エイチ",
+    "アイ",
+    "ジェー",
+    "ケー",
+    "エル",
+    "エム",
+    "エヌ",
+    "オー",
+    "ピー",
+    "キュー",
+    "アール",
+    "エス",
+    "ティー",
+    "ユー",
+    "ブイ",
+    "ダブリュー",
+    "エックス",
+    "ワイ",
+    "ゼット",
+];
+
+/// Readings of the units of every four digits.
+const UNITS: [&str; 5] = ["", "マン", "オク", "チョウ", "ケイ"];
+
+/// The endings of numbers which are geminated in most counters.
+const GEMINATE: &[u64] = &[1, 6, 8, 10, 100];
+
+/// Sound change of a number followed by a counter.
+#[derive(Debug, Clone, Copy)]
+enum Change {
+    /// No sound change.
+    Plain,
+
+    /// `ヨン` becomes `ヨ`, as in `ヨネン`.
+    Yo,
+
+    /// `ヨン`, `ナナ` and `キュウ` become `ヨ`, `シチ` and `ク`, as in `クジ`.
+    Hour,
+
+    /// The number is geminated when it ends with the given digits, as in
+    /// `イッコ`. `10` and `100` stand for the numbers ending with `ジュウ` and
+    /// `ヒャク`.
+    Geminate(&'static [u64]),
+
+    /// The counter starting with the `ハ` row is voiced after `ン` and
+    /// semi-voiced after a geminated number, as in `サンビキ` and `イッピキ`.
+    HaRow(&'static str, &'static str),
+
+    /// `フン` is semi-voiced after `ン` and a geminated number, as in
+    /// `サンプン` and `イップン`.
+    Minute,
+}
+
+/// Counters and their readings.
+///
+/// The longer counters come first, so that `時間` is matched before `時`.
+#[rustfmt::skip]
+const COUNTERS: &[(&str, &str, Change)] = &[
+    ("ヶ月", "カゲツ", Change::Geminate(GEMINATE)), ("か月", "カゲツ", Change::Geminate(GEMINATE)),
+    ("カ月", "カゲツ", Change::Geminate(GEMINATE)), ("ヵ月", "カゲツ", Change::Geminate(GEMINATE)),
+    ("時間", "ジカン", Change::Hour), ("時", "ジ", Change::Hour),
+    ("分", "フン", Change::Minute), ("秒", "ビョウ", Change::Plain),
+    ("年", "ネン", Change::Yo), ("円", "エン", Change::Yo),
+    ("匹", "ヒキ", Change::HaRow("ビキ", "ピキ")), ("本", "ホン", Change::HaRow("ボン", "ポン")),
+    ("杯", "ハイ", Change::HaRow("バイ", "パイ")),
+    ("個", "コ", Change::Geminate(GEMINATE)), ("回", "カイ", Change::Geminate(GEMINATE)),
+    ("件", "ケン", Change::Geminate(GEMINATE)),
+    ("歳", "サイ", Change::Geminate(&[1, 8, 10])), ("才", "サイ", Change::Geminate(&[1, 8, 10])),
+    ("冊", "サツ", Change::Geminate(&[1, 8, 10])),
+    ("枚", "マイ", Change::Plain), ("台", "ダイ", Change::Plain), ("度", "ド", Change::Plain),
+    ("番", "バン", Change::Plain), ("倍", "バイ", Change::Plain),
+    ("%", "パーセント", Change::Geminate(GEMINATE)), ("％", "パーセント", Change::Geminate(GEMINATE)),
+];
+
+/// Readings of the days of a month which are not read with `ニチ`.
+const DAYS: [(u64, &str); 13] = [
+    (1, "ツイタチ"),
+    (2, "フツカ"),
+    (3, "ミッカ"),
+    (4, "ヨッカ"),
+    (5, "イツカ"),
+    (6, "ムイカ"),
+    (7, "ナノカ"),
+    (8, "ヨウカ"),
+    (9, "ココノカ"),
+    (10, "トオカ"),
+    (14, "ジュウヨッカ"),
+    (20, "ハツカ"),
+    (24, "ニジュウヨッカ"),
+];
+
+/// Converts a number into its katakana reading.
+///
+/// ```rust
+/// use lyrian::expand::read_number;
+///
+/// assert_eq!(read_number(2024), "ニセンニジュウヨン");
+/// assert_eq!(read_number(3_0000_0800), "サンオクハッピャク");
+/// ```
+pub fn read_number(n: u64) -> String {
+    if n == 0 {
+        return DIGITS[0].to_string();
+    }
+
+    let mut groups = Vec::new();
+    let mut rest = n;
+    while rest > 0 {
+        groups.push(rest % 10000);
+        rest /= 10000;
+    }
+
+    let mut reading = String::new();
+    for (i, group) in groups.into_iter().enumerate().rev() {
+        if group == 0 {
+            continue;
+        }
+
+        let mut part = read_below_10000(group);
+        if i == 0 {
+            reading.push_str(&part);
+            continue;
+        }
+
+        if group == 1 {
+            part = DIGITS[1].to_string();
+        }
+        match i {
+            3 => {
+                geminate(&mut part, group, &[1, 8, 10]);
+            }
+            4 => {
+                geminate(&mut part, group, GEMINATE);
+            }
+            _ => {}
+        }
+        reading.push_str(&part);
+        reading.push_str(UNITS[i]);
+    }
+    reading
+}
+
+/// Expands the numerals in the text into their katakana readings.
+///
+/// Numerals are read with the common counters which follow them, such as
+/// `年`, `月`, `日`, `時`, `分`, `匹` and `本`, including their sound changes.
+/// Times like `12:30`, digit groups like `1,000` and decimals like `3.14` are
+/// also read. Full-width digits are accepted. The other characters are left
+/// as they are.
+///
+/// ```rust
+/// use lyrian::expand::expand_numbers;
+/// use lyrian::pronunciation::count_morae;
+///
+/// assert_eq!(expand_numbers("3匹のこぶた"), "サンビキのこぶた");
+/// assert_eq!(count_morae(&expand_numbers("2024年")), 9);
+/// ```
+pub fn expand_numbers(text: &str) -> String {
+    let mut expanded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        if to_digit(c).is_some() {
+            let (reading, len) = read_numeral(rest);
+            expanded.push_str(&reading);
+            rest = &rest[len..];
+        } else {
+            expanded.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    expanded
+}
+
+/// Readings of acronyms written in Latin letters.
+///
+/// An acronym is a run of upper case Latin letters, which is spelled out
+/// letter by letter unless it is registered as an exception. Full-width
+/// letters are also accepted. The runs which contain lower case letters are
+/// left as they are unless they are registered.
+///
+/// ```rust
+/// use lyrian::expand::AcronymReadings;
+///
+/// let mut acronyms = AcronymReadings::new();
+/// acronyms.insert("NASA", "ナサ");
+/// assert_eq!(acronyms.expand("AIとNASA"), "エーアイとナサ");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AcronymReadings {
+    exceptions: BTreeMap<String, String>,
+}
+
+impl AcronymReadings {
+    /// Creates a new instance of [`AcronymReadings`] without exceptions.
+    pub fn new() -> AcronymReadings {
+        AcronymReadings::default()
+    }
+
+    /// Adds an exception which is not spelled out.
+    pub fn insert(&mut self, acronym: &str, reading: &str) {
+        self.exceptions
+            .insert(acronym.to_string(), reading.to_string());
+    }
+
+    /// Expands the acronyms in the text into their katakana readings.
+    pub fn expand(&self, text: &str) -> String {
+        let mut expanded = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(c) = rest.chars().next() {
+            if to_latin(c).is_none() {
+                expanded.push(c);
+                rest = &rest[c.len_utf8()..];
+                continue;
+            }
+
+            let len = rest
+                .char_indices()
+                .find(|(_, c)| to_latin(*c).is_none())
+                .map_or(rest.len(), |(i, _)| i);
+            let word = rest[..len].chars().filter_map(to_latin).collect::<String>();
+            match self.exceptions.get(&word) {
+                Some(reading) => expanded.push_str(reading),
+                None if word.chars().all(|c| c.is_ascii_uppercase()) => {
+                    for c in word.chars() {
+                        expanded.push_str(LETTERS[c as usize - 'A' as usize]);
+                    }
+                }
+                None => expanded.push_str(&rest[..len]),
+            }
+            rest = &rest[len..];
+        }
+        expanded
+    }
+}
+
+/// Expands the acronyms in the text into their katakana readings without
+/// exceptions.
+///
+/// See [`AcronymReadings`] for the details.
+///
+/// ```rust
+/// use lyrian::expand::expand_acronyms;
+///
+/// assert_eq!(expand_acronyms("URLをひらく"), "ユーアールエルをひらく");
+/// ```
+pub fn expand_acronyms(text: &str) -> String {
+    AcronymReadings::new().expand(text)
+}
+
+/// Reads the numeral at the start of the text with the counter which follows
+/// it, and returns the reading and the byte length of the read part.
+fn read_numeral(text: &str) -> (String, usize) {
+    let (mut integer, mut len) = take_digits(text);
+
+    if integer.len() <= 3 {
+        loop {
+            let after = &text[len..];
+            let comma = match after.chars().next() {
+                Some(c) if c == ',' || c == '，' => c.len_utf8(),
+                _ => break,
+            };
+            let (group, group_len) = take_digits(&after[comma..]);
+            if group.len() != 3 {
+                break;
+            }
+            integer.push_str(&group);
+            len += comma + group_len;
+        }
+    }
+
+    if let Some((reading, time_len)) = read_time(&integer, &text[len..]) {
+        return (reading, len + time_len);
+    }
+
+    let mut decimals = String::new();
+    let after = &text[len..];
+    if let Some(point) = after.chars().next().filter(|c| *c == '.' || *c == '．') {
+        let (digits, digits_len) = take_digits(&after[point.len_utf8()..]);
+        if !digits.is_empty() {
+            decimals = digits;
+            len += point.len_utf8() + digits_len;
+        }
+    }
+
+    let n = match integer.parse::<u64>() {
+        Ok(n) if !(integer.len() > 1 && integer.starts_with('0')) => n,
+        _ => return (read_digits(&integer), len),
+    };
+
+    let after = &text[len..];
+    if !decimals.is_empty() {
+        let mut reading = read_number(n) + "テン" + &read_digits(&decimals);
+        if let Some((counter, counter_reading, _)) =
+            COUNTERS.iter().find(|(c, _, _)| after.starts_with(c))
+        {
+            reading.push_str(counter_reading);
+            len += counter.len();
+        }
+        return (reading, len);
+    }
+
+    if let Some(reading) = after.strip_prefix('月').and_then(|_| read_month(n)) {
+        return (reading, len + '月'.len_utf8());
+    }
+    if after.starts_with('日') {
+        return (read_day(n), len + '日'.len_utf8());
+    }
+    if after.starts_with('人') {
+        return (read_people(n), len + '人'.len_utf8());
+    }
+    if let Some((counter, counter_reading, change)) =
+        COUNTERS.iter().find(|(c, _, _)| after.starts_with(c))
+    {
+        return (
+            read_with_counter(n, counter_reading, *change),
+            len + counter.len(),
+        );
+    }
+
+    (read_number(n), len)
+}
+
+/// Reads a time like `12:30` from the hour and the text after it.
+fn read_time(hour: &str, text: &str) -> Option<(String, usize)> {
+    let colon = text.chars().next().filter(|c| *c == ':' || *c == '：')?;
+    let (minute, minute_len) = take_digits(&text[colon.len_utf8()..]);
+    let (h, m) = (hour.parse::<u64>().ok()?, minute.parse::<u64>().ok()?);
+    if hour.len() > 2 || minute.len() != 2 || h > 24 || m > 59 {
+        return None;
+    }
+
+    let mut reading = read_with_counter(h, "ジ", Change::Hour);
+    if m > 0 {
+        reading.push_str(&read_with_counter(m, "フン", Change::Minute));
+    }
+    Some((reading, colon.len_utf8() + minute_len))
+}
+
+/// Reads a number followed by a counter.
+fn read_with_counter(n: u64, counter: &str, change: Change) -> String {
+    let mut number = read_number(n);
+    match change {
+        Change::Plain => {}
+        Change::Yo => replace_last_digit(&mut number, n, &[(4, "ヨ")]),
+        Change::Hour => replace_last_digit(&mut number, n, &[(4, "ヨ"), (7, "シチ"), (9, "ク")]),
+        Change::Geminate(endings) => {
+            geminate(&mut number, n, endings);
+        }
+        Change::HaRow(voiced, semi_voiced) => {
+            if geminate(&mut number, n, GEMINATE) {
+                return number + semi_voiced;
+            }
+            if number.ends_with('ン') && n % 10 != 4 {
+                return number + voiced;
+            }
+        }
+        Change::Minute => {
+            if geminate(&mut number, n, GEMINATE) || number.ends_with('ン') {
+                return number + "プン";
+            }
+        }
+    }
+    number + counter
+}
+
+/// Reads a month, which is between 1 and 12.
+fn read_month(n: u64) -> Option<String> {
+    if !(1..=12).contains(&n) {
+        return None;
+    }
+    let mut number = read_number(n);
+    replace_last_digit(&mut number, n, &[(4, "シ"), (7, "シチ"), (9, "ク")]);
+    Some(number + "ガツ")
+}
+
+/// Reads a day of a month or a number of days.
+fn read_day(n: u64) -> String {
+    if let Some((_, reading)) = DAYS.iter().find(|(d, _)| *d == n) {
+        return reading.to_string();
+    }
+    if n % 10 == 4 {
+        let mut number = read_number(n);
+        replace_last_digit(&mut number, n, &[(4, "ヨッカ")]);
+        return number;
+    }
+    read_with_counter(n, "ニチ", Change::Hour)
+}
+
+/// Reads a number of people.
+fn read_people(n: u64) -> String {
+    match n {
+        1 => "ヒトリ".to_string(),
+        2 => "フタリ".to_string(),
+        _ => read_with_counter(n, "ニン", Change::Yo),
+    }
+}
+
+/// Reads the number below 10000.
+fn read_below_10000(n: u64) -> String {
+    let (thousands, hundreds, tens, ones) = (n / 1000, n / 100 % 10, n / 10 % 10, n % 10);
+    let mut reading = String::new();
+    match thousands {
+        0 => {}
+        1 => reading.push_str("セン"),
+        3 => reading.push_str("サンゼン"),
+        8 => reading.push_str("ハッセン"),
+        d => reading.push_str(&(DIGITS[d as usize].to_string() + "セン")),
+    }
+    match hundreds {
+        0 => {}
+        1 => reading.push_str("ヒャク"),
+        3 => reading.push_str("サンビャク"),
+        6 => reading.push_str("ロッピャク"),
+        8 => reading.push_str("ハッピャク"),
+        d => reading.push_str(&(DIGITS[d as usize].to_string() + "ヒャク")),
+    }
+    match tens {
+        0 => {}
+        1 => reading.push_str("ジュウ"),
+        d => reading.push_str(&(DIGITS[d as usize].to_string() + "ジュウ")),
+    }
+    if ones > 0 {
+        reading.push_str(DIGITS[ones as usize]);
+    }
+    reading
+}
+
+/// Reads the digits one by one.
+fn read_digits(digits: &str) -> String {
+    digits
+        .chars()
+        .filter_map(to_digit)
+        .map(|d| DIGITS[d as usize])
+        .collect()
+}
+
+/// Replaces the reading of the last digit of the number.
+fn replace_last_digit(reading: &mut String, n: u64, replacements: &[(u64, &str)]) {
+    let ones = n % 10;
+    if let Some((_, replacement)) = replacements.iter().find(|(d, _)| *d == ones) {
+        if reading.ends_with(DIGITS[ones as usize]) {
+            reading.truncate(reading.len() - DIGITS[ones as usize].len());
+            reading.push_str(replacement);
+        }
+    }
+}
+
+/// Geminates the end of the reading, like `イチ` to `イッ`, and returns
+/// whether it is geminated.
+fn geminate(reading: &mut String, n: u64, endings: &[u64]) -> bool {
+    let ending = match (n % 10, n % 100, n % 1000) {
+        (0, 0, 0) => 0,
+        (0, 0, _) => 100,
+        (0, _, _) => 10,
+        (ones, _, _) => ones,
+    };
+
+    let geminated = n > 0 && endings.contains(&ending);
+    if geminated {
+        reading.pop();
+        reading.push('ッ');
+    }
+    geminated
+}
+
+/// Takes the digits at the start of the text, and returns them in ASCII and
+/// their byte length.
+fn take_digits(text: &str) -> (String, usize) {
+    let mut digits = String::new();
+    let mut len = 0;
+    for c in text.chars() {
+        match to_digit(c) {
+            Some(d) => {
+                digits.push(std::char::from_digit(d, 10).unwrap_or('0'));
+                len += c.len_utf8();
+            }
+            None => break,
+        }
+    }
+    (digits, len)
+}
+
+/// Returns the value of an ASCII or full-width digit.
+fn to_digit(c: char) -> Option<u32> {
+    match c {
+        '0'..='9' => c.to_digit(10),
+        '０'..='９' => Some(c as u32 - '０' as u32),
+        _ => None,
+    }
+}
+
+/// Returns the ASCII form of an ASCII or full-width Latin letter.
+fn to_latin(c: char) -> Option<char> {
+    match c {
+        'A'..='Z' | 'a'..='z' => Some(c),
+        'Ａ'..='Ｚ' | 'ａ'..='ｚ' => std::char::from_u32(c as u32 - 0xFEE0),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod expand_test {
+    use crate::expand::{expand_acronyms, expand_numbers, read_number, AcronymReadings};
+
+    #[test]
+    fn read_large_numbers() {
+        assert_eq!(
+            (
+                read_number(10),
+                read_number(1_0000),
+                read_number(3600),
+                read_number(1_0000_0000_0000)
+            ),
+            (
+                "ジュウ".to_string(),
+                "イチマン".to_string(),
+                "サンゼンロッピャク".to_string(),
+                "イッチョウ".to_string()
+            )
+        )
+    }
+
+    #[test]
+    fn expand_numbers_with_counters() {
+        assert_eq!(
+            expand_numbers("1本と3本と6本"),
+            "イッポンとサンボンとロッポン".to_string()
+        )
+    }
+
+    #[test]
+    fn expand_dates() {
+        assert_eq!(
+            expand_numbers("2024年4月1日"),
+            "ニセンニジュウヨネンシガツツイタチ".to_string()
+        )
+    }
+
+    #[test]
+    fn expand_times() {
+        assert_eq!(
+            expand_numbers("9:10から12:00まで"),
+            "クジジュップンからジュウニジまで".to_string()
+        )
+    }
+
+    #[test]
+    fn expand_digit_groups_and_decimals() {
+        assert_eq!(
+            expand_numbers("１，０００円と3.14"),
+            "センエンとサンテンイチヨン".to_string()
+        )
+    }
+
+    #[test]
+    fn spell_out_acronyms() {
+        assert_eq!(
+            expand_acronyms("ＤＶＤとCDとWi-Fi"),
+            "ディーブイディーとシーディーとWi-Fi".to_string()
+        )
+    }
+
+    #[test]
+    fn read_acronyms_by_exceptions() {
+        let mut acronyms = AcronymReadings::new();
+        acronyms.insert("Wi", "ワイ");
+        acronyms.insert("Fi", "ファイ");
+        assert_eq!(acronyms.expand("Wi-Fi"), "ワイ-ファイ".to_string())
+    }
+}
@@ -0,0 +1,309 @@
+//! Formatting of generated poems.
+
+use crate::chars::is_kanji;
+use crate::lyric::is_symbol;
+use crate::morphological_analysis::LyrianToken;
+use crate::normalize::to_fullwidth_ascii;
+use crate::poem::Poem;
+use crate::reading::align_furigana;
+
+/// Characters and their forms in vertical writing.
+#[rustfmt::skip]
+const VERTICAL_FORMS: &[(char, char)] = &[
+    ('ー', '丨'), ('～', '≀'), ('…', '︙'), ('「', '﹁'), ('」', '﹂'), ('『', '﹃'), ('』', '﹄'),
+    ('（', '︵'), ('）', '︶'), ('【', '︻'), ('】', '︼'), ('〈', '︿'), ('〉', '﹀'),
+];
+
+/// Separators between the lines of a poem.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineSeparator {
+    /// A newline.
+    Newline,
+    /// A full-width space, as haiku are often written in one line.
+    Space,
+    /// A full-width slash (`／`).
+    Slash,
+}
+
+impl LineSeparator {
+    /// Returns the separator as a string.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LineSeparator::Newline => "\n",
+            LineSeparator::Space => "\u{3000}",
+            LineSeparator::Slash => "／",
+        }
+    }
+}
+
+/// Formatter of poems into text.
+///
+/// ```rust
+/// use lyrian::format::{LineSeparator, PoemFormatter};
+/// use lyrian::model::LyrianModel;
+/// use lyrian::poem::HaikuGenerator;
+/// use lyrian::pronunciation::JapaneseCounter;
+///
+/// let json = std::fs::read_to_string("examples/sample_model.json").unwrap();
+/// let model = LyrianModel::from_json(&json).unwrap();
+/// let mut generator = HaikuGenerator::new(model, JapaneseCounter::new(false));
+/// let formatter = PoemFormatter::new()
+///     .with_separator(LineSeparator::Slash)
+///     .with_particles_trimmed(true);
+/// if let Ok(haiku) = generator.generate() {
+///     println!("{}", formatter.format(&haiku));
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PoemFormatter {
+    separator: LineSeparator,
+    token_spaces: bool,
+    trim_particles: bool,
+}
+
+impl Default for PoemFormatter {
+    fn default() -> PoemFormatter {
+        PoemFormatter {
+            separator: LineSeparator::Newline,
+            token_spaces: false,
+            trim_particles: false,
+        }
+    }
+}
+
+impl PoemFormatter {
+    /// Creates a new instance of [`PoemFormatter`], which joins the lines
+    /// with newlines like [`Poem::join()`].
+    pub fn new() -> PoemFormatter {
+        PoemFormatter::default()
+    }
+
+    /// Uses the separator between the lines.
+    pub fn with_separator(mut self, separator: LineSeparator) -> PoemFormatter {
+        self.separator = separator;
+        self
+    }
+
+    /// Puts spaces between the words of the tokens.
+    pub fn with_token_spaces(mut self, token_spaces: bool) -> PoemFormatter {
+        self.token_spaces = token_spaces;
+        self
+    }
+
+    /// Removes the particles at the beginnings of the lines.
+    pub fn with_particles_trimmed(mut self, trim_particles: bool) -> PoemFormatter {
+        self.trim_particles = trim_particles;
+        self
+    }
+
+    /// Formats the poem into text.
+    ///
+    /// The symbols are removed as in [`Poem::join()`].
+    pub fn format(&self, poem: &Poem) -> String {
+        self.format_lines(poem).join(self.separator.as_str())
+    }
+
+    /// Formats the poem into vertical columns (tategaki), from right to left.
+    ///
+    /// Each line of the poem is a column, and the columns are separated and
+    /// padded by full-width spaces, so that the text is aligned in monospace
+    /// fonts. ASCII is converted into full-width, and the long vowel mark and
+    /// the brackets are rotated. The separator of the lines is not used.
+    ///
+    /// ```rust
+    /// use lyrian::format::PoemFormatter;
+    /// use lyrian::model::LyrianModel;
+    /// use lyrian::poem::HaikuGenerator;
+    /// use lyrian::pronunciation::JapaneseCounter;
+    ///
+    /// let json = std::fs::read_to_string("examples/sample_model.json").unwrap();
+    /// let model = LyrianModel::from_json(&json).unwrap();
+    /// let mut generator = HaikuGenerator::new(model, JapaneseCounter::new(false));
+    /// if let Ok(haiku) = generator.generate() {
+    ///     println!("{}", PoemFormatter::new().format_vertical(&haiku));
+    /// }
+    /// ```
+    pub fn format_vertical(&self, poem: &Poem) -> String {
+        let columns = self
+            .format_lines(poem)
+            .iter()
+            .rev()
+            .map(|l| to_fullwidth_ascii(l).chars().map(to_vertical).collect())
+            .collect::<Vec<Vec<char>>>();
+        let height = columns.iter().map(|c| c.len()).max().unwrap_or(0);
+
+        (0..height)
+            .map(|row| {
+                columns
+                    .iter()
+                    .map(|c| c.get(row).copied().unwrap_or('\u{3000}').to_string())
+                    .collect::<Vec<String>>()
+                    .join("\u{3000}")
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    /// Formats the poem into HTML, where the words which contain kanji are
+    /// given furigana by `<ruby>` elements.
+    ///
+    /// The furigana are given by the readings of the tokens, which are
+    /// aligned with the okurigana by
+    /// [`align_furigana()`](crate::reading::align_furigana). The lines are
+    /// separated by `<br>` instead of newlines.
+    ///
+    /// ```rust
+    /// use lyrian::format::PoemFormatter;
+    /// use lyrian::model::LyrianModel;
+    /// use lyrian::poem::HaikuGenerator;
+    /// use lyrian::pronunciation::JapaneseCounter;
+    ///
+    /// let json = std::fs::read_to_string("examples/sample_model.json").unwrap();
+    /// let model = LyrianModel::from_json(&json).unwrap();
+    /// let mut generator = HaikuGenerator::new(model, JapaneseCounter::new(false));
+    /// if let Ok(haiku) = generator.generate() {
+    ///     println!("{}", PoemFormatter::new().format_ruby(&haiku));
+    /// }
+    /// ```
+    pub fn format_ruby(&self, poem: &Poem) -> String {
+        let separator = match self.separator {
+            LineSeparator::Newline => "<br>\n",
+            s => s.as_str(),
+        };
+        self.format_lines_by(poem, ruby).join(separator)
+    }
+
+    /// Formats the lines of the poem.
+    fn format_lines(&self, poem: &Poem) -> Vec<String> {
+        self.format_lines_by(poem, |t| t.word.clone())
+    }
+
+    /// Formats the lines of the poem, where the tokens are formatted by the
+    /// function.
+    fn format_lines_by<F: Fn(&LyrianToken) -> String>(
+        &self,
+        poem: &Poem,
+        format: F,
+    ) -> Vec<String> {
+        poem.lines
+            .iter()
+            .map(|line| {
+                let mut tokens = line.tokens();
+                while self.trim_particles
+                    && matches!(tokens.first(), Some(t) if t.part_of_speech == "助詞")
+                {
+                    tokens = &tokens[1..];
+                }
+                tokens
+                    .iter()
+                    .filter(|t| !is_symbol(t))
+                    .map(&format)
+                    .collect::<Vec<String>>()
+                    .join(if self.token_spaces { " " } else { "" })
+            })
+            .collect()
+    }
+}
+
+/// Formats the token into HTML with `<ruby>` elements.
+fn ruby(token: &LyrianToken) -> String {
+    if token.mora == "unknown" || !token.word.chars().any(is_kanji) {
+        return escape_html(&token.word);
+    }
+
+    align_furigana(&token.word, &token.mora)
+        .segments
+        .iter()
+        .map(|s| match &s.reading {
+            Some(reading) => format!(
+                "<ruby>{}<rt>{}</rt></ruby>",
+                escape_html(&s.surface),
+                escape_html(reading)
+            ),
+            None => escape_html(&s.surface),
+        })
+        .collect()
+}
+
+/// Escapes the special characters of HTML.
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Converts the character into its form in vertical writing.
+fn to_vertical(c: char) -> char {
+    match VERTICAL_FORMS.iter().find(|(h, _)| *h == c) {
+        Some((_, v)) => *v,
+        None => c,
+    }
+}
+
+#[cfg(test)]
+mod format_test {
+    use crate::format::{LineSeparator, PoemFormatter};
+    use crate::morphological_analysis::{token, LyrianToken};
+    use crate::poem::{Poem, PoemLine};
+
+    fn poem() -> Poem {
+        Poem {
+            lines: vec![
+                PoemLine::new(vec![token("古池", "名詞"), token("や", "助詞")], 5, 5),
+                PoemLine::new(
+                    vec![
+                        token("の", "助詞"),
+                        token("蛙", "名詞"),
+                        token("、", "記号"),
+                    ],
+                    7,
+                    7,
+                ),
+            ],
+        }
+    }
+
+    #[test]
+    fn format_poem_with_separator_and_spaces() {
+        let formatter = PoemFormatter::new()
+            .with_separator(LineSeparator::Slash)
+            .with_token_spaces(true);
+        assert_eq!(formatter.format(&poem()), "古池 や／の 蛙".to_string())
+    }
+
+    #[test]
+    fn trim_leading_particles() {
+        let formatter = PoemFormatter::new()
+            .with_separator(LineSeparator::Space)
+            .with_particles_trimmed(true);
+        assert_eq!(formatter.format(&poem()), "古池や\u{3000}蛙".to_string())
+    }
+
+    #[test]
+    fn format_poem_vertically() {
+        assert_eq!(
+            PoemFormatter::new().format_vertical(&poem()),
+            "の\u{3000}古\n蛙\u{3000}池\n\u{3000}\u{3000}や".to_string()
+        )
+    }
+
+    #[test]
+    fn format_poem_with_ruby() {
+        let line = PoemLine::new(
+            vec![LyrianToken::new(
+                "飛び込む".to_string(),
+                "トビコム".to_string(),
+                "トビコム".to_string(),
+                "動詞".to_string(),
+            )],
+            4,
+            4,
+        );
+        let poem = Poem { lines: vec![line] };
+        assert_eq!(
+            PoemFormatter::new().format_ruby(&poem),
+            "<ruby>飛<rt>と</rt></ruby>び<ruby>込<rt>こ</rt></ruby>む".to_string()
+        )
+    }
+}
@@ -0,0 +1,189 @@
+//! Transcription of kana into IPA.
+
+use crate::chars::{hiragana_to_katakana, is_kana, LONG_VOWEL, SOKUON};
+use crate::pronunciation::split_morae;
+
+/// Katakana and their broad transcriptions in IPA of Tokyo Japanese.
+#[rustfmt::skip]
+const IPA_TABLE: &[(&str, &str)] = &[
+    ("キャ", "kʲa"), ("キュ", "kʲɯ"), ("キョ", "kʲo"), ("ギャ", "ɡʲa"), ("ギュ", "ɡʲɯ"), ("ギョ", "ɡʲo"),
+    ("シャ", "ɕa"), ("シュ", "ɕɯ"), ("ショ", "ɕo"), ("シェ", "ɕe"), ("スィ", "si"),
+    ("ジャ", "dʑa"), ("ジュ", "dʑɯ"), ("ジョ", "dʑo"), ("ジェ", "dʑe"), ("ズィ", "zi"),
+    ("チャ", "tɕa"), ("チュ", "tɕɯ"), ("チョ", "tɕo"), ("チェ", "tɕe"),
+    ("ヂャ", "dʑa"), ("ヂュ", "dʑɯ"), ("ヂョ", "dʑo"),
+    ("ツァ", "tsa"), ("ツィ", "tsi"), ("ツェ", "tse"), ("ツォ", "tso"),
+    ("ティ", "ti"), ("トゥ", "tɯ"), ("テュ", "tʲɯ"), ("ディ", "di"), ("ドゥ", "dɯ"), ("デュ", "dʲɯ"),
+    ("ニャ", "ɲa"), ("ニュ", "ɲɯ"), ("ニョ", "ɲo"),
+    ("ヒャ", "ça"), ("ヒュ", "çɯ"), ("ヒョ", "ço"),
+    ("ファ", "ɸa"), ("フィ", "ɸi"), ("フェ", "ɸe"), ("フォ", "ɸo"), ("フュ", "ɸʲɯ"),
+    ("ビャ", "bʲa"), ("ビュ", "bʲɯ"), ("ビョ", "bʲo"), ("ピャ", "pʲa"), ("ピュ", "pʲɯ"), ("ピョ", "pʲo"),
+    ("ミャ", "mʲa"), ("ミュ", "mʲɯ"), ("ミョ", "mʲo"), ("リャ", "ɾʲa"), ("リュ", "ɾʲɯ"), ("リョ", "ɾʲo"),
+    ("ウィ", "wi"), ("ウェ", "we"), ("ウォ", "wo"), ("イェ", "je"), ("クァ", "kwa"), ("グァ", "ɡwa"),
+    ("ヴァ", "va"), ("ヴィ", "vi"), ("ヴェ", "ve"), ("ヴォ", "vo"),
+    ("ア", "a"), ("イ", "i"), ("ウ", "ɯ"), ("エ", "e"), ("オ", "o"),
+    ("カ", "ka"), ("キ", "ki"), ("ク", "kɯ"), ("ケ", "ke"), ("コ", "ko"),
+    ("ガ", "ɡa"), ("ギ", "ɡi"), ("グ", "ɡɯ"), ("ゲ", "ɡe"), ("ゴ", "ɡo"),
+    ("サ", "sa"), ("シ", "ɕi"), ("ス", "sɯ"), ("セ", "se"), ("ソ", "so"),
+    ("ザ", "za"), ("ジ", "dʑi"), ("ズ", "zɯ"), ("ゼ", "ze"), ("ゾ", "zo"),
+    ("タ", "ta"), ("チ", "tɕi"), ("ツ", "tsɯ"), ("テ", "te"), ("ト", "to"),
+    ("ダ", "da"), ("ヂ", "dʑi"), ("ヅ", "zɯ"), ("デ", "de"), ("ド", "do"),
+    ("ナ", "na"), ("ニ", "ɲi"), ("ヌ", "nɯ"), ("ネ", "ne"), ("ノ", "no"),
+    ("ハ", "ha"), ("ヒ", "çi"), ("フ", "ɸɯ"), ("ヘ", "he"), ("ホ", "ho"),
+    ("バ", "ba"), ("ビ", "bi"), ("ブ", "bɯ"), ("ベ", "be"), ("ボ", "bo"),
+    ("パ", "pa"), ("ピ", "pi"), ("プ", "pɯ"), ("ペ", "pe"), ("ポ", "po"),
+    ("マ", "ma"), ("ミ", "mi"), ("ム", "mɯ"), ("メ", "me"), ("モ", "mo"),
+    ("ヤ", "ja"), ("ユ", "jɯ"), ("ヨ", "jo"),
+    ("ラ", "ɾa"), ("リ", "ɾi"), ("ル", "ɾɯ"), ("レ", "ɾe"), ("ロ", "ɾo"),
+    ("ワ", "wa"), ("ヰ", "i"), ("ヱ", "e"), ("ヲ", "o"), ("ヴ", "vɯ"),
+    ("ァ", "a"), ("ィ", "i"), ("ゥ", "ɯ"), ("ェ", "e"), ("ォ", "o"),
+    ("ャ", "ja"), ("ュ", "jɯ"), ("ョ", "jo"), ("ヮ", "wa"), ("ヵ", "ka"), ("ヶ", "ke"),
+];
+
+/// Transcribes kana into IPA.
+///
+/// The transcription is broad, based on Tokyo Japanese. `ン` is assimilated
+/// to the next consonant, `ッ` doubles the next consonant, and `ー` becomes
+/// the length mark (`ː`). The characters which are not kana, such as kanji,
+/// Latin letters and punctuation, are removed.
+///
+/// ```rust
+/// use lyrian::ipa::to_ipa;
+///
+/// assert_eq!(to_ipa("しんぶん"), "ɕimbɯɴ");
+/// assert_eq!(to_ipa("キッチン"), "kittɕiɴ");
+/// assert_eq!(to_ipa("カa漢キ。"), "kaki");
+/// ```
+pub fn to_ipa(kana: &str) -> String {
+    to_ipa_morae(kana).concat()
+}
+
+/// Transcribes kana into IPA mora by mora.
+///
+/// See [`to_ipa()`] for the details.
+///
+/// ```rust
+/// use lyrian::ipa::to_ipa_morae;
+///
+/// assert_eq!(to_ipa_morae("コーヒー"), vec!["ko", "ː", "çi", "ː"]);
+/// ```
+pub fn to_ipa_morae(kana: &str) -> Vec<String> {
+    let katakana = kana
+        .chars()
+        .filter(|c| is_kana(*c))
+        .map(hiragana_to_katakana)
+        .collect::<String>();
+    let plain = split_morae(&katakana)
+        .into_iter()
+        .map(transcribe)
+        .collect::<Vec<String>>();
+
+    let mut morae = Vec::with_capacity(plain.len());
+    for (i, mora) in plain.iter().enumerate() {
+        let next = plain.get(i + 1).map_or("", |m| m.as_str());
+        let ipa = match mora.as_str() {
+            "ン" => moraic_nasal(next).to_string(),
+            m if m.chars().all(|c| SOKUON.contains(&c)) => geminate(next),
+            m if m.chars().all(|c| c == LONG_VOWEL) => "ː".to_string(),
+            m => m.to_string(),
+        };
+        morae.push(ipa);
+    }
+    morae
+}
+
+/// Transcribes a mora, leaving `ン`, `ッ` and `ー` for the context.
+fn transcribe(mora: &str) -> String {
+    if let Some((_, ipa)) = IPA_TABLE.iter().find(|(k, _)| *k == mora) {
+        return ipa.to_string();
+    }
+
+    let mut ipa = String::new();
+    let mut rest = mora;
+    while let Some(c) = rest.chars().next() {
+        match IPA_TABLE.iter().find(|(k, _)| rest.starts_with(k)) {
+            Some((k, v)) => {
+                ipa.push_str(v);
+                rest = &rest[k.len()..];
+            }
+            None => {
+                ipa.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+    ipa
+}
+
+/// Returns the allophone of `ン` before the transcribed mora.
+fn moraic_nasal(next: &str) -> &'static str {
+    if next.starts_with("tɕ") || next.starts_with("dʑ") || next.starts_with('ɲ') {
+        "ɲ"
+    } else if next.starts_with(['p', 'b', 'm']) {
+        "m"
+    } else if next.starts_with(['t', 'd', 'n', 'ɾ', 'z']) {
+        "n"
+    } else if next.starts_with(['k', 'ɡ']) {
+        "ŋ"
+    } else {
+        "ɴ"
+    }
+}
+
+/// Returns the consonant which `ッ` doubles before the transcribed mora.
+fn geminate(next: &str) -> String {
+    match next.chars().next() {
+        Some(c) if !"aiɯeoːjwɴ".contains(c) && !SOKUON.contains(&c) && c != 'ン' => {
+            c.to_string()
+        }
+        _ => "ʔ".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod ipa_test {
+    use crate::ipa::to_ipa;
+
+    #[test]
+    fn transcribe_kana_into_ipa() {
+        assert_eq!(to_ipa("ひらがな"), "çiɾaɡana".to_string())
+    }
+
+    #[test]
+    fn transcribe_contracted_sounds() {
+        assert_eq!(to_ipa("ちょうちょ"), "tɕoɯtɕo".to_string())
+    }
+
+    #[test]
+    fn assimilate_moraic_nasal() {
+        assert_eq!(
+            (
+                to_ipa("サンバ"),
+                to_ipa("サンカ"),
+                to_ipa("サンチ"),
+                to_ipa("サン")
+            ),
+            (
+                "samba".to_string(),
+                "saŋka".to_string(),
+                "saɲtɕi".to_string(),
+                "saɴ".to_string()
+            )
+        )
+    }
+
+    #[test]
+    fn transcribe_geminate_consonant() {
+        assert_eq!(
+            (to_ipa("カップ"), to_ipa("アッ")),
+            ("kappɯ".to_string(), "aʔ".to_string())
+        )
+    }
+
+    #[test]
+    fn remove_characters_other_than_kana() {
+        assert_eq!(
+            (to_ipa("カa漢。"), to_ipa("サン漢バ"), to_ipa("ア!ッ")),
+            ("ka".to_string(), "samba".to_string(), "aʔ".to_string())
+        )
+    }
+}
@@ -0,0 +1,300 @@
+//! Seasonal words (kigo) of haiku.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use serde::Deserialize;
+
+use crate::chars::hiragana_to_katakana;
+use crate::morphological_analysis::LyrianToken;
+use crate::poem::Poem;
+
+/// Seasons of kigo.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Season {
+    Spring,
+    Summer,
+    Autumn,
+    Winter,
+    NewYear,
+}
+
+impl FromStr for Season {
+    type Err = String;
+
+    /// Parses the name of a season in English or Japanese, such as `autumn`,
+    /// `fall` or `秋`.
+    fn from_str(name: &str) -> Result<Season, String> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "spring" | "春" => Ok(Season::Spring),
+            "summer" | "夏" => Ok(Season::Summer),
+            "autumn" | "fall" | "秋" => Ok(Season::Autumn),
+            "winter" | "冬" => Ok(Season::Winter),
+            "new year" | "newyear" | "new_year" | "新年" => Ok(Season::NewYear),
+            _ => Err(format!("Unknown season `{}`.", name)),
+        }
+    }
+}
+
+/// Kigo in a saijiki file.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct KigoEntry {
+    /// The word of the kigo.
+    pub word: String,
+    /// The reading of the kigo in kana, which is matched like the word.
+    #[serde(default)]
+    pub reading: Option<String>,
+    /// The season of the kigo.
+    #[serde(deserialize_with = "deserialize_season")]
+    pub season: Season,
+    /// The category of the kigo, such as `天文` or `動物`.
+    #[serde(default)]
+    pub category: Option<String>,
+}
+
+/// Kigo bundled with [`Saijiki::default()`].
+#[rustfmt::skip]
+const KIGO: &[(&str, Season)] = &[
+    ("春", Season::Spring), ("桜", Season::Spring), ("さくら", Season::Spring), ("梅", Season::Spring),
+    ("花見", Season::Spring), ("鶯", Season::Spring), ("蛙", Season::Spring), ("かわず", Season::Spring),
+    ("蝶", Season::Spring), ("霞", Season::Spring), ("朧月", Season::Spring), ("菜の花", Season::Spring),
+    ("燕", Season::Spring), ("雛祭", Season::Spring),
+    ("夏", Season::Summer), ("蝉", Season::Summer), ("蛍", Season::Summer), ("向日葵", Season::Summer),
+    ("夕立", Season::Summer), ("風鈴", Season::Summer), ("金魚", Season::Summer), ("五月雨", Season::Summer),
+    ("若葉", Season::Summer), ("紫陽花", Season::Summer), ("雷", Season::Summer), ("夏草", Season::Summer),
+    ("秋", Season::Autumn), ("月", Season::Autumn), ("名月", Season::Autumn), ("紅葉", Season::Autumn),
+    ("もみじ", Season::Autumn), ("菊", Season::Autumn), ("柿", Season::Autumn), ("鈴虫", Season::Autumn),
+    ("赤とんぼ", Season::Autumn), ("すすき", Season::Autumn), ("朝顔", Season::Autumn), ("天の川", Season::Autumn),
+    ("七夕", Season::Autumn), ("稲妻", Season::Autumn),
+    ("冬", Season::Winter), ("雪", Season::Winter), ("霜", Season::Winter), ("木枯らし", Season::Winter),
+    ("氷", Season::Winter), ("炬燵", Season::Winter), ("枯野", Season::Winter), ("時雨", Season::Winter),
+    ("大根", Season::Winter), ("雪だるま", Season::Winter), ("水仙", Season::Winter),
+    ("正月", Season::NewYear), ("元旦", Season::NewYear), ("初日", Season::NewYear), ("門松", Season::NewYear),
+    ("雑煮", Season::NewYear), ("初詣", Season::NewYear), ("年賀", Season::NewYear),
+];
+
+/// Dictionary of kigo and their seasons.
+///
+/// A word is matched by its surface form, or by its reading if the kigo is
+/// written in kana, so `さくら` matches `桜` read as `サクラ`.
+///
+/// ```rust
+/// use lyrian::kigo::{Saijiki, Season};
+///
+/// let mut saijiki = Saijiki::default();
+/// assert_eq!(saijiki.season_of("名月"), Some(Season::Autumn));
+///
+/// saijiki.insert("かき氷", Season::Summer);
+/// assert_eq!(saijiki.season_of("かき氷"), Some(Season::Summer));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Saijiki {
+    words: BTreeMap<String, Season>,
+    categories: BTreeMap<String, String>,
+}
+
+impl Default for Saijiki {
+    fn default() -> Saijiki {
+        let mut saijiki = Saijiki::empty();
+        for (word, season) in KIGO {
+            saijiki.insert(word, *season);
+        }
+        saijiki
+    }
+}
+
+impl Saijiki {
+    /// Creates a new instance of [`Saijiki`] without kigo.
+    ///
+    /// Use [`Saijiki::default()`] for the bundled kigo.
+    pub fn empty() -> Saijiki {
+        Saijiki {
+            words: BTreeMap::new(),
+            categories: BTreeMap::new(),
+        }
+    }
+
+    /// Builds a new saijiki from TSV data, without the bundled kigo.
+    ///
+    /// Each line has the word, the reading, the season and the category
+    /// separated by tabs, where the reading and the category may be empty
+    /// or omitted. The season is written like `spring` or `春`. The empty
+    /// lines and the lines starting with `#` are skipped.
+    ///
+    /// ```rust
+    /// use lyrian::kigo::{Saijiki, Season};
+    ///
+    /// let saijiki = Saijiki::from_tsv("# word\treading\tseason\tcategory\n蜩\tひぐらし\t秋\t動物\n").unwrap();
+    /// assert_eq!(saijiki.season_of("ひぐらし"), Some(Season::Autumn));
+    /// assert_eq!(saijiki.category_of("蜩"), Some("動物"));
+    /// ```
+    pub fn from_tsv(contents: &str) -> Result<Saijiki, String> {
+        let mut saijiki = Saijiki::empty();
+        for (i, line) in contents.lines().enumerate() {
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let fields = line.split('\t').map(|f| f.trim()).collect::<Vec<&str>>();
+            let optional = |i: usize| match fields.get(i) {
+                Some(f) if !f.is_empty() => Some(f.to_string()),
+                _ => None,
+            };
+            let season = match fields.get(2) {
+                Some(s) => s
+                    .parse()
+                    .map_err(|_| format!("Unknown season `{}` at line {}.", s, i + 1))?,
+                None => return Err(format!("There is no season at line {}.", i + 1)),
+            };
+            saijiki.insert_entry(&KigoEntry {
+                word: fields[0].to_string(),
+                reading: optional(1),
+                season,
+                category: optional(3),
+            });
+        }
+        Ok(saijiki)
+    }
+
+    /// Builds a new saijiki from json data, without the bundled kigo.
+    ///
+    /// The json data is an array of [`KigoEntry`] like
+    /// `{"word": "蜩", "reading": "ひぐらし", "season": "autumn", "category": "動物"}`,
+    /// where the reading and the category may be omitted.
+    pub fn from_json(json: &str) -> Result<Saijiki, String> {
+        let entries = serde_json::from_str::<Vec<KigoEntry>>(json).map_err(|e| e.to_string())?;
+        let mut saijiki = Saijiki::empty();
+        for entry in &entries {
+            saijiki.insert_entry(entry);
+        }
+        Ok(saijiki)
+    }
+
+    /// Loads a saijiki from a file, which is read as json if its extension
+    /// is `json`, or as TSV otherwise.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Saijiki, String> {
+        let contents = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+        match path.as_ref().extension() {
+            Some(ext) if ext == "json" => Saijiki::from_json(&contents),
+            _ => Saijiki::from_tsv(&contents),
+        }
+    }
+
+    /// Adds a kigo and its season.
+    pub fn insert(&mut self, word: &str, season: Season) {
+        self.words.insert(to_key(word), season);
+    }
+
+    /// Adds a kigo with its reading and category.
+    pub fn insert_entry(&mut self, entry: &KigoEntry) {
+        let keys = std::iter::once(&entry.word).chain(entry.reading.as_ref());
+        for key in keys {
+            self.insert(key, entry.season);
+            if let Some(category) = &entry.category {
+                self.categories.insert(to_key(key), category.clone());
+            }
+        }
+    }
+
+    /// Adds all the kigo of another saijiki, such as the bundled one.
+    pub fn merge(&mut self, other: &Saijiki) {
+        self.words
+            .extend(other.words.iter().map(|(k, v)| (k.clone(), *v)));
+        self.categories
+            .extend(other.categories.iter().map(|(k, v)| (k.clone(), v.clone())));
+    }
+
+    /// Returns the season of the word if it is a kigo.
+    pub fn season_of(&self, word: &str) -> Option<Season> {
+        self.words.get(&to_key(word)).copied()
+    }
+
+    /// Returns the category of the word if it is a kigo with a category.
+    pub fn category_of(&self, word: &str) -> Option<&str> {
+        self.categories.get(&to_key(word)).map(|c| c.as_str())
+    }
+
+    /// Returns the season of the token if its word or reading is a kigo.
+    pub(crate) fn season_of_token(&self, token: &LyrianToken) -> Option<Season> {
+        self.season_of(&token.word)
+            .or_else(|| self.season_of(&token.mora))
+    }
+
+    /// Detects the kigo in the poem, and returns their words and seasons.
+    pub fn detect(&self, poem: &Poem) -> Vec<(String, Season)> {
+        poem.lines
+            .iter()
+            .flat_map(|l| l.tokens())
+            .filter_map(|t| self.season_of_token(t).map(|s| (t.word.clone(), s)))
+            .collect()
+    }
+}
+
+/// Deserializes the season of [`KigoEntry`] from its name.
+fn deserialize_season<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Season, D::Error> {
+    let name = String::deserialize(deserializer)?;
+    name.parse().map_err(serde::de::Error::custom)
+}
+
+/// Converts a word into the key of [`Saijiki`], where hiragana are unified
+/// into katakana.
+fn to_key(word: &str) -> String {
+    word.chars().map(hiragana_to_katakana).collect()
+}
+
+#[cfg(test)]
+mod kigo_test {
+    use crate::kigo::{Saijiki, Season};
+    use crate::morphological_analysis::LyrianToken;
+
+    #[test]
+    fn find_season_of_kigo() {
+        let saijiki = Saijiki::default();
+        assert_eq!(
+            (saijiki.season_of("雪"), saijiki.season_of("空")),
+            (Some(Season::Winter), None)
+        )
+    }
+
+    #[test]
+    fn find_season_of_token_by_reading() {
+        let token = LyrianToken::new(
+            "サクラ".to_string(),
+            "サクラ".to_string(),
+            "サクラ".to_string(),
+            "名詞".to_string(),
+        );
+        assert_eq!(
+            Saijiki::default().season_of_token(&token),
+            Some(Season::Spring)
+        )
+    }
+
+    #[test]
+    fn load_saijiki_from_tsv_and_json() {
+        let tsv =
+            Saijiki::from_tsv("蜩\tひぐらし\tautumn\t動物\n\n# comment\n雪解\t\t春\n").unwrap();
+        let json = Saijiki::from_json(
+            r#"[{"word": "蜩", "reading": "ひぐらし", "season": "秋", "category": "動物"},
+                {"word": "雪解", "season": "spring"}]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            (
+                tsv.season_of("ヒグラシ"),
+                tsv.category_of("雪解"),
+                tsv == json
+            ),
+            (Some(Season::Autumn), None, true)
+        )
+    }
+
+    #[test]
+    fn reject_unknown_season() {
+        assert!(Saijiki::from_tsv("蜩\tひぐらし\t雨季\n").is_err())
+    }
+}
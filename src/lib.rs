@@ -65,9 +65,56 @@
 //! mora    : 読んだ僕は早くも
 //! ```
 //!
+//! ## Features
+//!
+//! - `tokenizer` (default)
+//!     - Enables [`tokenize`] module of morphological analysis by lindera,
+//!       which is required to build a model from text data.
+//! - `vibrato`
+//!     - Enables [`tokenize::VibratoTokenizer`] of morphological analysis by
+//!       vibrato, which is faster than lindera.
+//! - `huggingface`
+//!     - Enables [`tokenize::HfTokenizer`] of the tokenizers of Hugging
+//!       Face, which reuses their vocabularies.
+//! - `mecab`
+//!     - Enables [`tokenize::MecabTokenizer`] running the external MeCab
+//!       command, with the dictionaries such as NEologd.
+//! - `shift_jis`
+//!     - Enables [`corpus::load_file()`] to decode the files in Shift_JIS.
+//! - `cli`
+//!     - Enables `maria` command to train models and generate lyrics, haiku
+//!       and tanka.
+//! - `server`
+//!     - Enables `maria serve` to generate sentences and poems over HTTP.
+//! - `english`
+//!     - Enables [`english`] module to count the syllables of English.
+//! - `ipa`
+//!     - Enables [`ipa`] module to transcribe kana into IPA.
+//!
 
+pub mod blend;
+pub mod chars;
+pub mod clean;
+pub mod conditioned;
+pub mod corpus;
+#[cfg(feature = "english")]
+pub mod english;
+pub mod expand;
+pub mod format;
+#[cfg(feature = "ipa")]
+pub mod ipa;
+pub mod kigo;
 pub mod lyric;
 pub mod model;
+pub mod normalize;
+pub mod phrase;
+pub mod poem;
+pub mod pronunciation;
+pub mod reading;
+pub mod rhyme;
+pub mod romaji;
+pub mod template;
+pub mod tokenize;
 
-mod chars;
+mod markov;
 mod morphological_analysis;
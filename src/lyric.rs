@@ -2,6 +2,7 @@
 
 use crate::chars::{dup_num, SYMBOLS};
 use crate::morphological_analysis::LyrianToken;
+use crate::pronunciation::{CountingRules, MoraCounter};
 
 /// Lyric structure
 pub struct Lyric {
@@ -21,24 +22,52 @@ impl Lyric {
             .fold(0, |acc, cur| acc + cur.length(syllable))
     }
 
+    /// Returns the length of the generated lyric with the given rules.
+    pub fn length_with(&self, rules: &CountingRules, syllable: bool) -> usize {
+        self.tokens
+            .iter()
+            .fold(0, |acc, cur| acc + cur.length_with(rules, syllable))
+    }
+
+    /// Returns the length of the generated lyric counted by the counter.
+    pub fn length_by<C: MoraCounter + ?Sized>(&self, counter: &C) -> usize {
+        self.tokens
+            .iter()
+            .fold(0, |acc, cur| acc + counter.count_token(cur))
+    }
+
     /// Adds a new token to [`Lyric`].
     pub fn add_token(&mut self, token: LyrianToken) {
         self.tokens.push(token);
     }
 
+    /// Returns the tokens of the lyric.
+    pub(crate) fn tokens(&self) -> &[LyrianToken] {
+        &self.tokens
+    }
+
     /// Join the words of `tokens`.
     pub fn join(&self) -> String {
-        self.tokens.iter().fold(String::from(""), |acc, cur| {
-            let count = dup_num(&cur.mora.chars().collect(), &SYMBOLS.to_vec());
-            if count == 0 {
-                format!("{}{}", acc, cur.word)
-            } else {
-                acc
-            }
-        })
+        join_words(&self.tokens)
     }
 }
 
+/// Joins the words of the tokens except symbols.
+pub(crate) fn join_words(tokens: &[LyrianToken]) -> String {
+    tokens.iter().fold(String::from(""), |acc, cur| {
+        if is_symbol(cur) {
+            acc
+        } else {
+            format!("{}{}", acc, cur.word)
+        }
+    })
+}
+
+/// Returns whether the reading of the token contains symbols.
+pub(crate) fn is_symbol(token: &LyrianToken) -> bool {
+    dup_num(&token.mora.chars().collect(), &SYMBOLS.to_vec()) != 0
+}
+
 #[cfg(test)]
 mod lyric_test {
     use crate::lyric::Lyric;
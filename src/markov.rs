@@ -0,0 +1,1546 @@
+//! Markov chain of states with weighted transitions.
+//!
+//! This replaces `markov_rs`, whose transitions could not be biased in
+//! sampling. The chain is serialized as the weights of the transitions, and
+//! the Walker tables of `markov_rs` are still read.
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::mem::size_of;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+/// The maximum number of the sweeps of the iterations in
+/// [`MarkovChain::hitting_times()`] and [`MarkovChain::pagerank()`].
+const MAX_SWEEPS: usize = 100_000;
+
+/// Smoothing of the transitions, which gives some probability to the
+/// transitions never seen in training.
+///
+/// The counts of the transitions are kept as they are, and smoothed when
+/// they are converted into probabilities. The probability taken from the
+/// seen transitions is shared uniformly by the unseen ones. The states
+/// which no state followed in training still move to any state uniformly.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Smoothing {
+    /// No smoothing, where only the seen transitions are chosen.
+    #[default]
+    None,
+    /// Simple Good-Turing by Gale and Sampson, which discounts the counts by
+    /// the numbers of the transitions of each count.
+    ///
+    /// It handles the heavy tail of the transitions seen once better than
+    /// adding a constant for medium corpora. The counts are not discounted if
+    /// the transitions have fewer than two distinct counts.
+    GoodTuring,
+    /// Witten-Bell, which gives the unseen transitions from a state the
+    /// weight of the number of the distinct states following it.
+    ///
+    /// It is cheap and free of parameters, and works nicely for the models
+    /// of characters, whose states have many distinct next states.
+    WittenBell,
+}
+
+impl FromStr for Smoothing {
+    type Err = String;
+
+    /// Parses the name of a smoothing, such as `none`, `good-turing` or
+    /// `witten-bell`.
+    fn from_str(name: &str) -> Result<Smoothing, String> {
+        match name.trim().to_ascii_lowercase().replace('_', "-").as_str() {
+            "none" => Ok(Smoothing::None),
+            "good-turing" | "sgt" => Ok(Smoothing::GoodTuring),
+            "witten-bell" | "wb" => Ok(Smoothing::WittenBell),
+            _ => Err(format!("Unknown smoothing `{}`.", name)),
+        }
+    }
+}
+
+/// Markov chain whose transitions can be reweighted on sampling.
+///
+/// Each row of `transitions` has the indices of the next states and their
+/// weights. A state without next states moves to any state uniformly.
+/// `frequencies` has the number of times each state appeared in training.
+///
+/// The counts are kept as they are, and the distribution of each row is
+/// built on the first sampling of the row.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(from = "ChainData<T>")]
+pub(crate) struct MarkovChain<T> {
+    state_space: Vec<T>,
+    transitions: Vec<Vec<(usize, f64)>>,
+    frequencies: Vec<usize>,
+    #[serde(default, skip_serializing_if = "is_unsmoothed")]
+    smoothing: Smoothing,
+    /// The discounted counts of Good-Turing for the counts of the
+    /// transitions.
+    #[serde(skip)]
+    discounts: BTreeMap<u64, f64>,
+    /// The pseudo-counts of the Dirichlet prior added to the transitions
+    /// into each state, or empty without the prior.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    prior: Vec<f64>,
+    /// Whether the transitions run from the later states to the earlier
+    /// ones of the sequences.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    reversed: bool,
+    #[serde(skip)]
+    tables: SamplingTables,
+    #[serde(skip)]
+    prev: Option<usize>,
+    #[serde(skip)]
+    rng: Option<StdRng>,
+}
+
+/// The distributions of the rows built lazily for the sampling without
+/// bias, which are dropped whenever the weights change.
+///
+/// The tables are only a cache, so that they never make chains unequal.
+#[derive(Debug, Clone, Default)]
+struct SamplingTables {
+    /// The indices of the states counted in training, or all the indices
+    /// if no state is counted.
+    seen: OnceLock<Vec<usize>>,
+    rows: OnceLock<Vec<OnceLock<Option<RowTable>>>>,
+}
+
+impl PartialEq for SamplingTables {
+    fn eq(&self, _: &SamplingTables) -> bool {
+        true
+    }
+}
+
+/// The distribution of the next states of a row.
+#[derive(Debug, Clone)]
+struct RowTable {
+    /// The indices of the next states, or [`None`] for the indices of the
+    /// states counted in training.
+    indices: Option<Vec<usize>>,
+    distribution: WeightedIndex<f64>,
+}
+
+impl<T: Clone + Ord> MarkovChain<T> {
+    /// Builds a new chain from the sequence of states.
+    pub(crate) fn from(elements: &[T]) -> MarkovChain<T> {
+        MarkovChain::from_sequences(&[elements])
+    }
+
+    /// Builds a new chain from the sequences of states, where no transition
+    /// is counted between the sequences.
+    pub(crate) fn from_sequences<S: AsRef<[T]>>(sequences: &[S]) -> MarkovChain<T> {
+        let mut builder = ChainBuilder::new();
+        for sequence in sequences {
+            builder.add(sequence.as_ref());
+        }
+        builder.build()
+    }
+
+    /// Builds the chain of the reversed sequences, whose transitions run
+    /// from the later states to the earlier ones, with the same smoothing
+    /// and prior.
+    pub(crate) fn reversed(&self) -> MarkovChain<T> {
+        let mut transitions = vec![Vec::new(); self.state_space.len()];
+        for (i, row) in self.transitions.iter().enumerate() {
+            for (j, w) in row {
+                transitions[*j].push((i, *w));
+            }
+        }
+        let mut chain = MarkovChain {
+            state_space: self.state_space.clone(),
+            transitions,
+            frequencies: self.frequencies.clone(),
+            smoothing: Smoothing::None,
+            discounts: BTreeMap::new(),
+            prior: self.prior.clone(),
+            reversed: !self.reversed,
+            tables: SamplingTables::default(),
+            prev: None,
+            rng: None,
+        };
+        chain.set_smoothing(self.smoothing);
+        chain
+    }
+}
+
+/// Counter of the transitions of the sequences added one by one, which
+/// builds [`MarkovChain`] without holding the sequences.
+pub(crate) struct ChainBuilder<T> {
+    counts: BTreeMap<T, BTreeMap<T, f64>>,
+    frequencies: BTreeMap<T, usize>,
+}
+
+impl<T: Clone + Ord> ChainBuilder<T> {
+    /// Creates a new instance of [`ChainBuilder`] without transitions.
+    pub(crate) fn new() -> ChainBuilder<T> {
+        ChainBuilder {
+            counts: BTreeMap::new(),
+            frequencies: BTreeMap::new(),
+        }
+    }
+
+    /// Creates a new instance of [`ChainBuilder`] whose states include the
+    /// states given, which are never counted unless they are added.
+    pub(crate) fn with_states(states: &[T]) -> ChainBuilder<T> {
+        ChainBuilder {
+            counts: states
+                .iter()
+                .map(|s| (s.clone(), BTreeMap::new()))
+                .collect(),
+            frequencies: states.iter().map(|s| (s.clone(), 0)).collect(),
+        }
+    }
+
+    /// Counts the transitions in the sequence of states.
+    pub(crate) fn add(&mut self, sequence: &[T]) {
+        for state in sequence {
+            if !self.counts.contains_key(state) {
+                self.counts.insert(state.clone(), BTreeMap::new());
+            }
+            *self.frequencies.entry(state.clone()).or_insert(0) += 1;
+        }
+        for pair in sequence.windows(2) {
+            let row = self
+                .counts
+                .get_mut(&pair[0])
+                .expect("There is no state that should exist.");
+            *row.entry(pair[1].clone()).or_insert(0.0) += 1.0;
+        }
+    }
+
+    /// Builds the chain of the transitions counted so far.
+    pub(crate) fn build(self) -> MarkovChain<T> {
+        let state_space = self.counts.keys().cloned().collect::<Vec<T>>();
+        let index = |state: &T| {
+            state_space
+                .binary_search(state)
+                .expect("There is no state that should exist.")
+        };
+        let transitions = self
+            .counts
+            .values()
+            .map(|row| row.iter().map(|(s, w)| (index(s), *w)).collect())
+            .collect();
+
+        MarkovChain {
+            state_space,
+            transitions,
+            frequencies: self.frequencies.into_values().collect(),
+            smoothing: Smoothing::None,
+            discounts: BTreeMap::new(),
+            prior: Vec::new(),
+            reversed: false,
+            tables: SamplingTables::default(),
+            prev: None,
+            rng: None,
+        }
+    }
+}
+
+impl<T: Ord> MarkovChain<T> {
+    /// Sets the state chosen last, so that the next state follows it.
+    ///
+    /// `false` is returned if the state is not in the chain.
+    pub(crate) fn set_current(&mut self, state: &T) -> bool {
+        match self.state_space.binary_search(state) {
+            Ok(i) => {
+                self.prev = Some(i);
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Replaces the transitions of the states never counted with those of
+    /// the chain of the same states, such as the chain of the whole corpus.
+    ///
+    /// `false` is returned if the states of the chains differ.
+    pub(crate) fn back_off_to(&mut self, global: &MarkovChain<T>) -> bool {
+        if self.state_space != global.state_space {
+            return false;
+        }
+        for (i, row) in self.transitions.iter_mut().enumerate() {
+            if self.frequencies[i] == 0 {
+                row.clone_from(&global.transitions[i]);
+            }
+        }
+        self.set_smoothing(self.smoothing);
+        true
+    }
+
+    /// Calculates the natural log-likelihood of the sequence of states.
+    ///
+    /// The first state, and the states which can not follow the previous
+    /// ones because the chain was initialized, are regarded as chosen
+    /// uniformly. [`None`] is returned if a state is not in the chain.
+    pub(crate) fn log_likelihood(&self, states: &[T]) -> Option<f64> {
+        let uniform = -(self.state_space.len() as f64).ln();
+        let indices = states
+            .iter()
+            .map(|s| self.state_space.binary_search(s).ok())
+            .collect::<Option<Vec<usize>>>()?;
+
+        let mut likelihood = if indices.is_empty() { 0.0 } else { uniform };
+        for pair in indices.windows(2) {
+            likelihood += match self.transition(pair[0], pair[1]) {
+                Some(p) => p.ln(),
+                None => uniform,
+            };
+        }
+        Some(likelihood)
+    }
+
+    /// Calculates the probability that the states follow the first one in
+    /// order, the product of the probabilities of the transitions.
+    ///
+    /// [`None`] is returned if a state is not in the chain or can not follow
+    /// the previous one.
+    pub(crate) fn path_probability(&self, states: &[T]) -> Option<f64> {
+        let indices = states
+            .iter()
+            .map(|s| self.state_space.binary_search(s).ok())
+            .collect::<Option<Vec<usize>>>()?;
+        let mut probability = 1.0;
+        for pair in indices.windows(2) {
+            probability *= self.transition(pair[0], pair[1])?;
+        }
+        Some(probability)
+    }
+
+    /// Returns the probability that the state `to` follows the state `from`.
+    ///
+    /// `0.0` is returned if the states are not in the chain.
+    pub(crate) fn probability(&self, from: &T, to: &T) -> f64 {
+        match (
+            self.state_space.binary_search(from),
+            self.state_space.binary_search(to),
+        ) {
+            (Ok(i), Ok(j)) => self.transition(i, j).unwrap_or(0.0),
+            _ => 0.0,
+        }
+    }
+}
+
+impl<T> MarkovChain<T> {
+    /// Smooths the transitions of the chain, see [`Smoothing`].
+    pub(crate) fn set_smoothing(&mut self, smoothing: Smoothing) {
+        self.smoothing = smoothing;
+        self.discounts = match smoothing {
+            Smoothing::GoodTuring => simple_good_turing(
+                self.transitions
+                    .iter()
+                    .flatten()
+                    .map(|(_, w)| w.round() as u64),
+            ),
+            Smoothing::None | Smoothing::WittenBell => BTreeMap::new(),
+        };
+        self.tables = SamplingTables::default();
+    }
+
+    /// Adds the pseudo-counts of a Dirichlet prior to the transitions into
+    /// each state, which are given by `prior` with the indices of the states
+    /// and the states.
+    ///
+    /// The states without next states still move to any state uniformly.
+    /// The negative pseudo-counts are regarded as `0.0`.
+    pub(crate) fn set_prior<F: FnMut(usize, &T) -> f64>(&mut self, mut prior: F) {
+        self.prior = self
+            .state_space
+            .iter()
+            .enumerate()
+            .map(|(i, s)| prior(i, s).max(0.0))
+            .collect();
+        if self.prior.iter().all(|a| *a == 0.0) {
+            self.prior.clear();
+        }
+        self.tables = SamplingTables::default();
+    }
+
+    /// Returns the weights of the next states of the state at the index,
+    /// smoothed and added the prior, in the order of the indices.
+    ///
+    /// Nothing is returned for the state without next states, which moves to
+    /// any state uniformly.
+    fn weights(&self, index: usize) -> Cow<'_, [(usize, f64)]> {
+        let (row, unseen) = self.smoothed_row(index);
+        if row.is_empty() || (unseen <= 0.0 && self.prior.is_empty()) {
+            return row;
+        }
+        let share = match unseen > 0.0 {
+            true => unseen / (self.state_space.len() - row.len()) as f64,
+            false => 0.0,
+        };
+        let mut dense = vec![share; self.state_space.len()];
+        for (i, w) in row.iter() {
+            dense[*i] = *w;
+        }
+        for (w, a) in dense.iter_mut().zip(&self.prior) {
+            *w += a;
+        }
+        Cow::Owned(
+            dense
+                .into_iter()
+                .enumerate()
+                .filter(|(_, w)| *w > 0.0)
+                .collect(),
+        )
+    }
+
+    /// Returns the smoothed weights of the next states of the state at the
+    /// index, and the total weight of the other states.
+    ///
+    /// The weight of the other states is `0.0` if no state is left for it,
+    /// or for the state without next states.
+    fn smoothed_row(&self, index: usize) -> (Cow<'_, [(usize, f64)]>, f64) {
+        let row = &self.transitions[index];
+        if row.is_empty() || row.len() == self.state_space.len() {
+            return (Cow::Borrowed(row), 0.0);
+        }
+        match self.smoothing {
+            Smoothing::None => (Cow::Borrowed(row), 0.0),
+            Smoothing::GoodTuring => {
+                let discounted = row
+                    .iter()
+                    .map(|(i, w)| match self.discounts.get(&(w.round() as u64)) {
+                        Some(d) => (*i, d.min(*w)),
+                        None => (*i, *w),
+                    })
+                    .collect::<Vec<(usize, f64)>>();
+                let unseen = row.iter().map(|(_, w)| w).sum::<f64>()
+                    - discounted.iter().map(|(_, w)| w).sum::<f64>();
+                (Cow::Owned(discounted), unseen.max(0.0))
+            }
+            Smoothing::WittenBell => (Cow::Borrowed(row), row.len() as f64),
+        }
+    }
+
+    /// Returns the probability of the transition between the indices, if
+    /// the state `to` can follow the state `from`.
+    fn transition(&self, from: usize, to: usize) -> Option<f64> {
+        let row = self.weights(from);
+        if row.is_empty() {
+            return Some(1.0 / self.state_space.len() as f64);
+        }
+        let total = row.iter().map(|(_, w)| w).sum::<f64>();
+        let k = row.binary_search_by_key(&to, |(i, _)| *i).ok()?;
+        Some(row[k].1 / total)
+    }
+
+    /// Returns the probabilities of the transitions from the state at the
+    /// index like [`weights()`](#method.weights), without listing all the
+    /// states for the smoothing and the prior.
+    fn sparse_row(&self, index: usize) -> SparseRow {
+        let n = self.state_space.len() as f64;
+        let (row, unseen) = self.smoothed_row(index);
+        if row.is_empty() {
+            return SparseRow {
+                counted: Vec::new(),
+                uniform: 1.0 / n,
+                prior: 0.0,
+            };
+        }
+        let share = match unseen > 0.0 {
+            true => unseen / (n - row.len() as f64),
+            false => 0.0,
+        };
+        let total =
+            row.iter().map(|(_, w)| w).sum::<f64>() + unseen + self.prior.iter().sum::<f64>();
+        SparseRow {
+            counted: row.iter().map(|(j, w)| (*j, (w - share) / total)).collect(),
+            uniform: share / total,
+            prior: match self.prior.is_empty() {
+                true => 0.0,
+                false => 1.0 / total,
+            },
+        }
+    }
+
+    /// Returns a next state.
+    ///
+    /// The first state is chosen from the next states of a random state, as
+    /// [`markov_rs`](https://docs.rs/markov_rs) did.
+    pub(crate) fn next(&mut self) -> &T {
+        let next = match self.rng.take() {
+            Some(mut rng) => {
+                let next = self.next_index(&mut rng);
+                self.rng = Some(rng);
+                next
+            }
+            None => self.next_index(&mut rand::thread_rng()),
+        };
+        &self.state_space[next.expect("The chain has no states.")]
+    }
+
+    /// Returns a next state, whose transition weights are multiplied by the
+    /// factors of `bias`, which is given the indices of the states and the
+    /// states.
+    ///
+    /// The states whose factors are `0.0` are never chosen, and [`None`] is
+    /// returned if all the next states are masked.
+    ///
+    /// The seeded generator is used if the chain is seeded by
+    /// [`seed()`](#method.seed).
+    pub(crate) fn next_by<F: FnMut(usize, &T) -> f64>(&mut self, bias: F) -> Option<&T> {
+        match self.rng.take() {
+            Some(mut rng) => {
+                let next = self.next_index_by(&mut rng, bias);
+                self.rng = Some(rng);
+                next.map(move |i| &self.state_space[i])
+            }
+            None => self.next_by_rng(&mut rand::thread_rng(), bias),
+        }
+    }
+
+    /// Chooses any state in proportion to its frequency multiplied by the
+    /// factor of `weight`, and moves the chain to it.
+    ///
+    /// [`None`] is returned if all the states are masked.
+    pub(crate) fn sample_by<F: FnMut(usize, &T) -> f64>(&mut self, mut weight: F) -> Option<&T> {
+        let weights = self
+            .state_space
+            .iter()
+            .enumerate()
+            .map(|(i, s)| self.frequencies[i] as f64 * weight(i, s).max(0.0))
+            .collect::<Vec<f64>>();
+        let distribution = WeightedIndex::new(&weights).ok()?;
+        let next = match &mut self.rng {
+            Some(rng) => distribution.sample(rng),
+            None => distribution.sample(&mut rand::thread_rng()),
+        };
+        self.prev = Some(next);
+        Some(&self.state_space[next])
+    }
+
+    /// Seeds the random number generator of the chain, so that the states
+    /// are chosen reproducibly.
+    pub(crate) fn seed(&mut self, seed: u64) {
+        self.rng = Some(StdRng::seed_from_u64(seed));
+    }
+
+    /// Returns the seeded random number generator of the chain, or [`None`]
+    /// if the chain is not seeded.
+    pub(crate) fn rng(&self) -> Option<&StdRng> {
+        self.rng.as_ref()
+    }
+
+    /// Replaces the random number generator of the chain, which is unseeded
+    /// by [`None`].
+    pub(crate) fn set_rng(&mut self, rng: Option<StdRng>) {
+        self.rng = rng;
+    }
+
+    /// Draws a seed from the seeded random number generator of the chain, to
+    /// seed another chain reproducibly. [`None`] is returned if the chain is
+    /// not seeded.
+    pub(crate) fn draw_seed(&mut self) -> Option<u64> {
+        self.rng.as_mut().map(|rng| rng.gen())
+    }
+
+    /// Returns a next state like [`next_by()`](#method.next_by) using an
+    /// external random number generator.
+    pub(crate) fn next_by_rng<R: Rng, F: FnMut(usize, &T) -> f64>(
+        &mut self,
+        rng: &mut R,
+        bias: F,
+    ) -> Option<&T> {
+        let next = self.next_index_by(rng, bias)?;
+        Some(&self.state_space[next])
+    }
+
+    /// Chooses the index of a next state, and moves the chain to it.
+    fn next_index_by<R: Rng, F: FnMut(usize, &T) -> f64>(
+        &mut self,
+        rng: &mut R,
+        mut bias: F,
+    ) -> Option<usize> {
+        if self.state_space.is_empty() {
+            return None;
+        }
+        let row = self.current_row(rng);
+        let candidates = match self.weights(row) {
+            Cow::Borrowed([]) => self.seen().iter().map(|i| (*i, 1.0)).collect(),
+            successors => successors.into_owned(),
+        };
+        let weights = candidates
+            .iter()
+            .map(|(i, w)| w * bias(*i, &self.state_space[*i]).max(0.0))
+            .collect::<Vec<f64>>();
+        let index = WeightedIndex::new(&weights).ok()?.sample(rng);
+
+        let next = candidates[index].0;
+        self.prev = Some(next);
+        Some(next)
+    }
+
+    /// Chooses the index of a next state without bias like
+    /// [`next_index_by()`](#method.next_index_by), using the table of the
+    /// row built on its first sampling.
+    fn next_index<R: Rng>(&mut self, rng: &mut R) -> Option<usize> {
+        if self.state_space.is_empty() {
+            return None;
+        }
+        let row = self.current_row(rng);
+        let table = self.table(row).as_ref()?;
+        let index = table.distribution.sample(rng);
+
+        let next = match &table.indices {
+            Some(indices) => indices[index],
+            None => self.seen()[index],
+        };
+        self.prev = Some(next);
+        Some(next)
+    }
+
+    /// Returns the index of the state chosen last, or a random state
+    /// counted in training if the chain is initialized.
+    fn current_row<R: Rng>(&self, rng: &mut R) -> usize {
+        match self.prev {
+            Some(i) => i,
+            None => {
+                let seen = self.seen();
+                seen[rng.gen_range(0..seen.len())]
+            }
+        }
+    }
+
+    /// Returns the indices of the states counted in training, or all the
+    /// indices if no state is counted.
+    ///
+    /// The states never counted, such as the states of the other labels
+    /// sharing the states, are not chosen at random.
+    fn seen(&self) -> &[usize] {
+        self.tables.seen.get_or_init(|| {
+            let seen = (0..self.state_space.len())
+                .filter(|i| self.frequencies[*i] > 0)
+                .collect::<Vec<usize>>();
+            if seen.is_empty() {
+                (0..self.state_space.len()).collect()
+            } else {
+                seen
+            }
+        })
+    }
+
+    /// Returns the distribution of the next states of the state at the
+    /// index, which is built on the first call for the row.
+    ///
+    /// [`None`] is returned if no next state can be chosen.
+    fn table(&self, index: usize) -> &Option<RowTable> {
+        let rows = self.tables.rows.get_or_init(|| {
+            (0..self.state_space.len())
+                .map(|_| OnceLock::new())
+                .collect()
+        });
+        rows[index].get_or_init(|| match self.weights(index) {
+            Cow::Borrowed([]) => Some(RowTable {
+                indices: None,
+                distribution: WeightedIndex::new(vec![1.0; self.seen().len()]).ok()?,
+            }),
+            successors => Some(RowTable {
+                distribution: WeightedIndex::new(successors.iter().map(|(_, w)| *w)).ok()?,
+                indices: Some(successors.iter().map(|(i, _)| *i).collect()),
+            }),
+        })
+    }
+
+    /// Initializes the chain, so that the next state is chosen randomly.
+    pub(crate) fn initialize(&mut self) {
+        self.prev = None;
+    }
+
+    /// Returns the index of the state chosen last.
+    pub(crate) fn current(&self) -> Option<usize> {
+        self.prev
+    }
+
+    /// Returns whether the state chosen last has no next states, such as the
+    /// last state of a sequence.
+    pub(crate) fn is_end(&self) -> bool {
+        matches!(self.prev, Some(i) if self.transitions[i].is_empty())
+    }
+
+    /// Returns whether the chain is built from the reversed sequences.
+    pub(crate) fn is_reversed(&self) -> bool {
+        self.reversed
+    }
+
+    /// Returns the number of times the state at the index appeared in
+    /// training.
+    pub(crate) fn frequency(&self, index: usize) -> usize {
+        self.frequencies[index]
+    }
+
+    /// Returns the states of the chain.
+    pub(crate) fn states(&self) -> &[T] {
+        &self.state_space
+    }
+
+    /// Returns the indices of the next states of the state at the index, and
+    /// their probabilities.
+    ///
+    /// No next states are returned for the state which moves to any state
+    /// uniformly, and the unseen transitions given probabilities by the
+    /// smoothing or the prior are not returned.
+    pub(crate) fn successors(&self, index: usize) -> Vec<(usize, f64)> {
+        let seen = &self.transitions[index];
+        let row = self.weights(index);
+        let total = row.iter().map(|(_, w)| w).sum::<f64>();
+        row.iter()
+            .filter(|(i, _)| seen.binary_search_by_key(i, |(j, _)| *j).is_ok())
+            .map(|(i, w)| (*i, w / total))
+            .collect()
+    }
+
+    /// Returns the index of the most probable next state of the state at the
+    /// index, the smallest one among the ties.
+    ///
+    /// [`None`] is returned for the state without next states, which moves
+    /// to any state uniformly.
+    pub(crate) fn most_probable(&self, index: usize) -> Option<usize> {
+        self.weights(index)
+            .iter()
+            .fold(None, |best: Option<(usize, f64)>, (i, w)| match best {
+                Some((_, b)) if b >= *w => best,
+                _ => Some((*i, *w)),
+            })
+            .map(|(i, _)| i)
+    }
+
+    /// Writes the chain as a weighted acceptor in the AT&T text format of
+    /// OpenFST, in the tropical semiring.
+    ///
+    /// The state `0` is the start, which moves to any state uniformly, and
+    /// the state `i + 1` is reached by the label `i + 1` of the state at the
+    /// index `i`. The weights are the negative natural logarithms of the
+    /// probabilities. The states given by `is_final` are final with the
+    /// weight `0`, and the states without next states have no arcs.
+    pub(crate) fn to_fst<F: FnMut(usize, &T) -> bool>(&self, mut is_final: F) -> String {
+        let mut fst = String::new();
+        let uniform = (self.state_space.len() as f64).ln();
+        for j in 0..self.state_space.len() {
+            fst.push_str(&format!(
+                "0\t{}\t{}\t{}\t{}\n",
+                j + 1,
+                j + 1,
+                j + 1,
+                uniform
+            ));
+        }
+        for (i, state) in self.state_space.iter().enumerate() {
+            let row = self.weights(i);
+            let total = row.iter().map(|(_, w)| w).sum::<f64>();
+            for (j, w) in row.iter() {
+                let weight = (total / w).ln();
+                fst.push_str(&format!(
+                    "{}\t{}\t{}\t{}\t{}\n",
+                    i + 1,
+                    j + 1,
+                    j + 1,
+                    j + 1,
+                    weight
+                ));
+            }
+            if is_final(i, state) {
+                fst.push_str(&format!("{}\t0\n", i + 1));
+            }
+        }
+        fst
+    }
+
+    /// Returns the number of the transitions between the states.
+    pub(crate) fn transition_count(&self) -> usize {
+        self.transitions.iter().map(|row| row.len()).sum()
+    }
+
+    /// Estimates the number of bytes which the chain occupies, where
+    /// `heap_size` gives the bytes which each state owns on the heap.
+    pub(crate) fn memory_size<F: Fn(&T) -> usize>(&self, heap_size: F) -> usize {
+        let states = self.state_space.capacity() * size_of::<T>()
+            + self.state_space.iter().map(heap_size).sum::<usize>();
+        let transitions = self.transitions.capacity() * size_of::<Vec<(usize, f64)>>()
+            + self
+                .transitions
+                .iter()
+                .map(|row| row.capacity() * size_of::<(usize, f64)>())
+                .sum::<usize>();
+        size_of::<Self>() + states + transitions + self.frequencies.capacity() * size_of::<usize>()
+    }
+
+    /// Computes which total lengths can follow each state.
+    ///
+    /// `table[i][n]` is `true` if some states can follow the state `i` and
+    /// their lengths sum up to `n`, for `n` up to `max`. The table is filled
+    /// by dynamic programming from the smaller `n`, repeated until it
+    /// settles, since the states of the length `0` refer to the same `n`.
+    pub(crate) fn reachable(&self, lengths: &[usize], max: usize) -> Vec<Vec<bool>> {
+        let mut table = vec![vec![false; max + 1]; self.state_space.len()];
+        for row in &mut table {
+            row[0] = true;
+        }
+
+        // The rows which move to any state are `None`.
+        let rows = (0..self.state_space.len())
+            .map(|i| Some(self.weights(i)).filter(|w| !w.is_empty() && w.len() < lengths.len()))
+            .collect::<Vec<Option<Cow<[(usize, f64)]>>>>();
+        let fits =
+            |table: &[Vec<bool>], i: usize, n: usize| lengths[i] <= n && table[i][n - lengths[i]];
+        for n in 1..=max {
+            loop {
+                let any = (0..table.len()).any(|i| fits(&table, i, n));
+                let mut changed = false;
+                for (i, row) in rows.iter().enumerate() {
+                    let reach = match row {
+                        None => any,
+                        Some(successors) => successors.iter().any(|(j, _)| fits(&table, *j, n)),
+                    };
+                    if reach && !table[i][n] {
+                        table[i][n] = true;
+                        changed = true;
+                    }
+                }
+                if !changed {
+                    break;
+                }
+            }
+        }
+        table
+    }
+
+    /// Calculates the expected number of steps to reach any of the
+    /// absorbing states, given by `absorbing` with the indices of the states
+    /// and the states, from each state. The absorbing states themselves take
+    /// `0.0` steps.
+    ///
+    /// [`None`] is returned for the states which may never reach them, such
+    /// as the states in a loop without them. The steps are solved by the
+    /// Gauss-Seidel iteration until they settle.
+    pub(crate) fn hitting_times<F: FnMut(usize, &T) -> bool>(
+        &self,
+        mut absorbing: F,
+    ) -> Vec<Option<f64>> {
+        let n = self.state_space.len();
+        let absorbing = self
+            .state_space
+            .iter()
+            .enumerate()
+            .map(|(i, s)| absorbing(i, s))
+            .collect::<Vec<bool>>();
+        let rows = (0..n)
+            .map(|i| self.sparse_row(i))
+            .collect::<Vec<SparseRow>>();
+        let graph = Predecessors::new(&rows, &self.prior);
+
+        // The states which may move to a state never reaching the absorbing
+        // states never reach them surely.
+        let reaching = graph.spread(absorbing.clone(), |_| true);
+        let never = graph.spread(reaching.iter().map(|r| !r).collect(), |i| !absorbing[i]);
+        let mut times = vec![0.0; n];
+        for _ in 0..MAX_SWEEPS {
+            // The sums of the times over all the states, and weighted by the
+            // prior, for the shares of the rows.
+            let mut all = times.iter().sum::<f64>();
+            let mut prior = self
+                .prior
+                .iter()
+                .zip(&times)
+                .map(|(a, t)| a * t)
+                .sum::<f64>();
+            let mut change = 0.0f64;
+            for i in (0..n).filter(|i| !absorbing[*i] && !never[*i]) {
+                let row = &rows[i];
+                let time = 1.0
+                    + row.counted.iter().map(|(j, p)| p * times[*j]).sum::<f64>()
+                    + row.uniform * all
+                    + row.prior * prior;
+                change = change.max((time - times[i]) / time);
+                all += time - times[i];
+                if let Some(a) = self.prior.get(i) {
+                    prior += a * (time - times[i]);
+                }
+                times[i] = time;
+            }
+            if change < 1e-12 {
+                break;
+            }
+        }
+        times
+            .into_iter()
+            .zip(never)
+            .map(|(t, never)| Some(t).filter(|_| !never))
+            .collect()
+    }
+
+    /// Ranks the states by PageRank on the graph of the transitions, where
+    /// `damping` is the probability to follow a transition rather than to
+    /// jump to any state. The ranks sum up to `1.0`.
+    ///
+    /// The states without next states jump to any state.
+    pub(crate) fn pagerank(&self, damping: f64) -> Vec<f64> {
+        let n = self.state_space.len();
+        let rows = (0..n)
+            .map(|i| self.sparse_row(i))
+            .collect::<Vec<SparseRow>>();
+        let mut ranks = vec![1.0 / n as f64; n];
+        for _ in 0..MAX_SWEEPS {
+            // The ranks spread to all the states and to the states of the
+            // prior, including the jumps from the states without next states.
+            let uniform = rows
+                .iter()
+                .zip(&ranks)
+                .map(|(row, r)| r * row.uniform)
+                .sum::<f64>();
+            let prior = rows
+                .iter()
+                .zip(&ranks)
+                .map(|(row, r)| r * row.prior)
+                .sum::<f64>();
+            let mut next = vec![(1.0 - damping) / n as f64 + damping * uniform; n];
+            for (rank, a) in next.iter_mut().zip(&self.prior) {
+                *rank += damping * prior * a;
+            }
+            for (row, r) in rows.iter().zip(&ranks) {
+                for (j, p) in &row.counted {
+                    next[*j] += damping * r * p;
+                }
+            }
+            let change = next
+                .iter()
+                .zip(&ranks)
+                .map(|(a, b)| (a - b).abs())
+                .sum::<f64>();
+            ranks = next;
+            if change < 1e-12 {
+                break;
+            }
+        }
+        ranks
+    }
+}
+
+/// The probabilities of the transitions from a state, kept sparse with
+/// the shares of all the states instead of a dense row.
+///
+/// The probability of the state `j` is the probability of `j` in `counted`
+/// if any, plus `uniform`, plus `prior` times the pseudo-count of `j`.
+struct SparseRow {
+    counted: Vec<(usize, f64)>,
+    uniform: f64,
+    prior: f64,
+}
+
+/// The states which can move to each state, where the states moving to any
+/// state and to the states of the prior are kept apart.
+struct Predecessors<'a> {
+    counted: Vec<Vec<usize>>,
+    uniform: Vec<usize>,
+    prior: Vec<usize>,
+    pseudo_counts: &'a [f64],
+}
+
+impl<'a> Predecessors<'a> {
+    /// Lists the predecessors of the states from the rows.
+    fn new(rows: &[SparseRow], pseudo_counts: &'a [f64]) -> Predecessors<'a> {
+        let mut predecessors = Predecessors {
+            counted: vec![Vec::new(); rows.len()],
+            uniform: Vec::new(),
+            prior: Vec::new(),
+            pseudo_counts,
+        };
+        for (i, row) in rows.iter().enumerate() {
+            for (j, p) in &row.counted {
+                let a = pseudo_counts.get(*j).copied().unwrap_or(0.0);
+                if p + row.uniform + row.prior * a > 0.0 {
+                    predecessors.counted[*j].push(i);
+                }
+            }
+            if row.uniform > 0.0 {
+                predecessors.uniform.push(i);
+            }
+            if row.prior > 0.0 {
+                predecessors.prior.push(i);
+            }
+        }
+        predecessors
+    }
+
+    /// Marks the states which can reach the marked states, through the
+    /// states allowed by `passable`.
+    fn spread<F: Fn(usize) -> bool>(&self, mut marked: Vec<bool>, passable: F) -> Vec<bool> {
+        let mut stack = (0..marked.len())
+            .filter(|i| marked[*i])
+            .collect::<Vec<usize>>();
+        let (mut uniform, mut prior) = (false, false);
+        while let Some(j) = stack.pop() {
+            let mut sources = self.counted[j].iter().collect::<Vec<&usize>>();
+            if !uniform {
+                uniform = true;
+                sources.extend(&self.uniform);
+            }
+            if !prior && self.pseudo_counts.get(j).is_some_and(|a| *a > 0.0) {
+                prior = true;
+                sources.extend(&self.prior);
+            }
+            for &i in sources {
+                if !marked[i] && passable(i) {
+                    marked[i] = true;
+                    stack.push(i);
+                }
+            }
+        }
+        marked
+    }
+}
+
+/// The formats of [`MarkovChain`] in json.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ChainData<T> {
+    Weights {
+        state_space: Vec<T>,
+        transitions: Vec<Vec<(usize, f64)>>,
+        /// Missing in the models saved before the frequencies were kept.
+        #[serde(default)]
+        frequencies: Vec<usize>,
+        #[serde(default)]
+        smoothing: Smoothing,
+        #[serde(default)]
+        prior: Vec<f64>,
+        #[serde(default)]
+        reversed: bool,
+    },
+    /// The format of `markov_rs`, which the earlier models were saved in.
+    Walker {
+        state_space: Vec<T>,
+        wa_table: Vec<WalkerTable>,
+    },
+}
+
+/// Transition table by Walker's Alias Method of `markov_rs`.
+#[derive(Deserialize)]
+struct WalkerTable {
+    aliases: Vec<usize>,
+    probs: Vec<f32>,
+}
+
+impl WalkerTable {
+    /// Recovers the weights of the next states.
+    ///
+    /// An index `i` is drawn uniformly, and its alias is taken with the
+    /// probability `probs[i]`, so the weight of `i` is `1 - probs[i]` plus
+    /// the sum of `probs[j]` whose `aliases[j]` is `i`. The table whose
+    /// probabilities are all zero is uniform, and gives no next states.
+    fn weights(&self) -> Vec<(usize, f64)> {
+        if self.probs.iter().all(|p| *p <= 0.0) {
+            return Vec::new();
+        }
+
+        let mut weights = self
+            .probs
+            .iter()
+            .map(|p| 1.0 - *p as f64)
+            .collect::<Vec<f64>>();
+        for (alias, prob) in self.aliases.iter().zip(&self.probs) {
+            weights[*alias] += *prob as f64;
+        }
+        weights
+            .into_iter()
+            .enumerate()
+            .filter(|(_, w)| *w > 1e-4)
+            .collect()
+    }
+}
+
+impl<T> From<ChainData<T>> for MarkovChain<T> {
+    fn from(data: ChainData<T>) -> MarkovChain<T> {
+        let (state_space, transitions, frequencies, smoothing, prior, reversed) = match data {
+            ChainData::Weights {
+                state_space,
+                transitions,
+                frequencies,
+                smoothing,
+                prior,
+                reversed,
+            } => (
+                state_space,
+                transitions,
+                frequencies,
+                smoothing,
+                prior,
+                reversed,
+            ),
+            ChainData::Walker {
+                state_space,
+                wa_table,
+            } => (
+                state_space,
+                wa_table.iter().map(|t| t.weights()).collect(),
+                Vec::new(),
+                Smoothing::None,
+                Vec::new(),
+                false,
+            ),
+        };
+        let frequencies = if frequencies.len() == state_space.len() {
+            frequencies
+        } else {
+            estimate_frequencies(&transitions)
+        };
+        let mut chain = MarkovChain {
+            state_space,
+            transitions,
+            frequencies,
+            smoothing: Smoothing::None,
+            discounts: BTreeMap::new(),
+            prior: Vec::new(),
+            reversed,
+            tables: SamplingTables::default(),
+            prev: None,
+            rng: None,
+        };
+        chain.set_smoothing(smoothing);
+        if prior.len() == chain.state_space.len() {
+            chain.prior = prior;
+        }
+        chain
+    }
+}
+
+/// Returns whether the smoothing is [`Smoothing::None`], which is not saved.
+fn is_unsmoothed(smoothing: &Smoothing) -> bool {
+    *smoothing == Smoothing::None
+}
+
+/// Computes the discounted counts of Simple Good-Turing for each count.
+///
+/// The numbers `n` of the counts `r` are averaged over the gaps between the
+/// counts, and fitted by `log n = a + b log r` for the large counts whose
+/// numbers are sparse. The Turing estimates `(r + 1) n(r + 1) / n(r)` are
+/// used for the small counts, until they are not significantly different
+/// from the fitted ones. The estimates are scaled so that the transitions
+/// seen once give the total of the unseen ones, `n(1)`.
+fn simple_good_turing<I: Iterator<Item = u64>>(counts: I) -> BTreeMap<u64, f64> {
+    let mut numbers = BTreeMap::new();
+    for count in counts.filter(|c| *c > 0) {
+        *numbers.entry(count).or_insert(0.0) += 1.0;
+    }
+    if numbers.len() < 2 {
+        return BTreeMap::new();
+    }
+    let (rs, ns): (Vec<f64>, Vec<f64>) = numbers.iter().map(|(r, n)| (*r as f64, *n)).unzip();
+
+    let points = (0..rs.len())
+        .map(|k| {
+            let q = if k == 0 { 0.0 } else { rs[k - 1] };
+            let t = if k + 1 == rs.len() {
+                2.0 * rs[k] - q
+            } else {
+                rs[k + 1]
+            };
+            (rs[k].ln(), (2.0 * ns[k] / (t - q)).ln())
+        })
+        .collect::<Vec<(f64, f64)>>();
+    let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / points.len() as f64;
+    let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / points.len() as f64;
+    let slope = points
+        .iter()
+        .map(|(x, y)| (x - mean_x) * (y - mean_y))
+        .sum::<f64>()
+        / points
+            .iter()
+            .map(|(x, _)| (x - mean_x).powi(2))
+            .sum::<f64>();
+    let fitted = |r: f64| (mean_y + slope * (r.ln() - mean_x)).exp();
+
+    let mut turing = true;
+    let mut estimates = BTreeMap::new();
+    for (r, n) in &numbers {
+        let next = (*r + 1) as f64;
+        let y = next * fitted(next) / fitted(*r as f64);
+        let estimate = match numbers.get(&(r + 1)) {
+            Some(n_next) if turing => {
+                let x = next * n_next / n;
+                let deviation = (next.powi(2) * n_next / n.powi(2) * (1.0 + n_next / n)).sqrt();
+                if (x - y).abs() > 1.96 * deviation {
+                    x
+                } else {
+                    turing = false;
+                    y
+                }
+            }
+            _ => {
+                turing = false;
+                y
+            }
+        };
+        estimates.insert(*r, estimate);
+    }
+
+    let total = numbers.iter().map(|(r, n)| *r as f64 * n).sum::<f64>();
+    let unseen = numbers.get(&1).copied().unwrap_or(0.0);
+    let estimated = numbers.iter().map(|(r, n)| estimates[r] * n).sum::<f64>();
+    estimates
+        .into_iter()
+        .map(|(r, e)| (r, e * (total - unseen) / estimated))
+        .collect()
+}
+
+/// Estimates the frequencies of the states from the weights of the
+/// transitions from and to them, which miss the first or the last states of
+/// the sequences.
+fn estimate_frequencies(transitions: &[Vec<(usize, f64)>]) -> Vec<usize> {
+    let mut incoming = vec![0.0; transitions.len()];
+    for (i, w) in transitions.iter().flatten() {
+        incoming[*i] += w;
+    }
+    transitions
+        .iter()
+        .zip(incoming)
+        .map(|(row, i)| row.iter().map(|(_, w)| w).sum::<f64>().max(i).round() as usize)
+        .collect()
+}
+
+#[cfg(test)]
+mod markov_test {
+    use crate::markov::{simple_good_turing, MarkovChain, Smoothing};
+
+    const TEXT: [&str; 11] = [
+        "I", "think", "that", "that", "that", "that", "that", "boy", "wrote", "is", "wrong",
+    ];
+
+    #[test]
+    fn count_transitions() {
+        let chain = MarkovChain::from(&TEXT);
+        assert_eq!(
+            (chain.state_space[3], chain.transitions[3].clone()),
+            ("that", vec![(1, 1.0), (3, 4.0)])
+        )
+    }
+
+    #[test]
+    fn count_frequencies() {
+        let chain = MarkovChain::from_sequences(&[vec!["a", "b"], vec!["b", "a", "b"]]);
+        assert_eq!(chain.frequencies, vec![2, 3])
+    }
+
+    #[test]
+    fn separate_sequences() {
+        let chain = MarkovChain::from_sequences(&[vec!["a", "b"], vec!["b", "c"], vec!["c"]]);
+        assert_eq!(
+            chain.transitions,
+            vec![vec![(1, 1.0)], vec![(2, 1.0)], vec![]]
+        )
+    }
+
+    #[test]
+    fn mask_next_states() {
+        let mut chain = MarkovChain::from(&TEXT);
+        chain.prev = Some(3);
+        let only_boy = |_, s: &&str| if *s == "boy" { 1.0 } else { 0.0 };
+        let boy = chain.next_by(only_boy).copied();
+        assert_eq!((boy, chain.next_by(|_, _| 0.0)), (Some("boy"), None))
+    }
+
+    #[test]
+    fn choose_states_reproducibly_by_seed() {
+        let mut chain = MarkovChain::from(&TEXT);
+        let mut walk = || {
+            chain.seed(42);
+            chain.initialize();
+            (0..8)
+                .map(|_| chain.next().to_string())
+                .collect::<Vec<String>>()
+        };
+        assert_eq!(walk(), walk())
+    }
+
+    #[test]
+    fn build_tables_of_sampled_rows() {
+        let mut chain = MarkovChain::from(&TEXT);
+        let walk = |chain: &mut MarkovChain<&str>, unbiased: bool| {
+            chain.seed(42);
+            chain.initialize();
+            (0..8)
+                .map(|_| match unbiased {
+                    true => chain.next().to_string(),
+                    false => chain.next_by(|_, _| 1.0).unwrap().to_string(),
+                })
+                .collect::<Vec<String>>()
+        };
+        let biased = walk(&mut chain, false);
+        let built = |chain: &MarkovChain<&str>| {
+            chain
+                .tables
+                .rows
+                .get()
+                .map_or(0, |rows| rows.iter().filter(|r| r.get().is_some()).count())
+        };
+        let before = built(&chain);
+        let unbiased = walk(&mut chain, true);
+        let sampled = built(&chain);
+        chain.set_prior(|_, _| 1.0);
+        assert_eq!(
+            (before, biased == unbiased, sampled > 0, built(&chain)),
+            (0, true, true, 0)
+        )
+    }
+
+    #[test]
+    fn list_successors_with_probabilities() {
+        let chain = MarkovChain::from(&TEXT);
+        assert_eq!(
+            (
+                chain.successors(3),
+                chain.successors(5),
+                chain.transition_count()
+            ),
+            (vec![(1, 0.2), (3, 0.8)], vec![], 7)
+        )
+    }
+
+    #[test]
+    fn discount_counts_by_good_turing() {
+        let counts = [1, 1, 1, 1, 1, 1, 2, 2, 2, 3, 3, 5];
+        let discounts = simple_good_turing(counts.iter().copied());
+        let discounted = counts.iter().map(|c| discounts[c]).sum::<f64>();
+        assert!(
+            (discounted - 17.0).abs() < 1e-9
+                && discounts.iter().all(|(r, d)| *d < *r as f64)
+                && discounts[&1] < discounts[&2]
+        )
+    }
+
+    #[test]
+    fn give_probabilities_to_unseen_transitions() {
+        let mut chain = MarkovChain::from_sequences(&[
+            vec!["a", "b", "a", "b", "a", "c"],
+            vec!["b", "a", "d", "b", "c"],
+        ]);
+        let unsmoothed = chain.probability(&"a", &"a");
+        chain.set_smoothing(Smoothing::GoodTuring);
+        let total = ["a", "b", "c", "d"]
+            .iter()
+            .map(|s| chain.probability(&"a", s))
+            .sum::<f64>();
+        assert!(
+            unsmoothed == 0.0
+                && chain.probability(&"a", &"a") > 0.0
+                && (total - 1.0).abs() < 1e-9
+                && chain.successors(2).is_empty()
+        )
+    }
+
+    #[test]
+    fn smooth_by_witten_bell() {
+        let mut chain = MarkovChain::from_sequences(&[
+            vec!["a", "b", "a", "b", "a", "c"],
+            vec!["b", "a", "d", "b", "c"],
+        ]);
+        chain.set_smoothing(Smoothing::WittenBell);
+        // "a" is followed 4 times by 3 distinct states, and "a" is unseen.
+        assert_eq!(
+            (chain.probability(&"a", &"b"), chain.probability(&"a", &"a")),
+            (2.0 / 7.0, 3.0 / 7.0)
+        )
+    }
+
+    #[test]
+    fn add_dirichlet_prior() {
+        let mut chain = MarkovChain::from(&TEXT);
+        chain.set_prior(|_, s| if *s == "is" { 1.0 } else { 0.0 });
+        // "that" is followed by "boy" once and by "that" 4 times.
+        assert_eq!(
+            (
+                chain.probability(&"that", &"is"),
+                chain.probability(&"that", &"wrong"),
+                chain.successors(3)
+            ),
+            (1.0 / 6.0, 0.0, vec![(1, 1.0 / 6.0), (3, 4.0 / 6.0)])
+        )
+    }
+
+    #[test]
+    fn calculate_hitting_times() {
+        let chain = MarkovChain::from_sequences(&[
+            vec!["a", "b", "c"],
+            vec!["a", "a", "c"],
+            vec!["d", "d"],
+        ]);
+        let times = chain.hitting_times(|_, s| *s == "c");
+        assert!(
+            (times[0].unwrap() - 2.0).abs() < 1e-9
+                && times[1..] == [Some(1.0), Some(0.0), None][..]
+        )
+    }
+
+    #[test]
+    fn calculate_hitting_times_with_smoothing_and_prior() {
+        let mut chain = MarkovChain::from(&TEXT);
+        chain.set_smoothing(Smoothing::WittenBell);
+        chain.set_prior(|i, _| i as f64 / 10.0);
+        let times = chain
+            .hitting_times(|_, s| *s == "wrong")
+            .into_iter()
+            .map(|t| t.unwrap())
+            .collect::<Vec<f64>>();
+        // The times satisfy the equations of the dense transitions.
+        assert!((0..times.len()).filter(|i| times[*i] > 0.0).all(|i| {
+            let expected = 1.0
+                + (0..times.len())
+                    .map(|j| chain.transition(i, j).unwrap_or(0.0) * times[j])
+                    .sum::<f64>();
+            (times[i] - expected).abs() < 1e-6
+        }))
+    }
+
+    #[test]
+    fn rank_states_by_pagerank() {
+        let chain = MarkovChain::from_sequences(&[vec!["a", "b", "a"], vec!["c", "b"]]);
+        // "a" and "b" move to each other, and "c" is only jumped to.
+        let ranks = chain.pagerank(0.5);
+        let expected = [7.0 / 18.0, 4.0 / 9.0, 1.0 / 6.0];
+        assert!(ranks
+            .iter()
+            .zip(&expected)
+            .all(|(r, e)| (r - e).abs() < 1e-9))
+    }
+
+    #[test]
+    fn rank_states_with_smoothing_and_prior() {
+        let mut chain = MarkovChain::from(&TEXT);
+        chain.set_smoothing(Smoothing::WittenBell);
+        chain.set_prior(|i, _| i as f64 / 10.0);
+        let ranks = chain.pagerank(0.85);
+        // The ranks are stationary under the dense transitions.
+        let n = ranks.len();
+        assert!((0..n).all(|j| {
+            let expected = 0.15 / n as f64
+                + 0.85
+                    * (0..n)
+                        .map(|i| ranks[i] * chain.transition(i, j).unwrap_or(0.0))
+                        .sum::<f64>();
+            (ranks[j] - expected).abs() < 1e-9
+        }))
+    }
+
+    #[test]
+    fn back_off_to_global_chain() {
+        use crate::markov::ChainBuilder;
+
+        let global = MarkovChain::from_sequences(&[vec!["a", "b"], vec!["c", "a", "c"]]);
+        let mut builder = ChainBuilder::with_states(global.states());
+        builder.add(&["a", "b"]);
+        let mut label = builder.build();
+        let unseen = label.probability(&"c", &"a");
+        label.back_off_to(&global);
+        assert_eq!(
+            (
+                unseen,
+                label.probability(&"c", &"a"),
+                label.probability(&"a", &"c"),
+                label.frequencies.clone()
+            ),
+            (1.0 / 3.0, 1.0, 0.0, vec![1, 1, 0])
+        )
+    }
+
+    #[test]
+    fn reverse_transitions() {
+        let chain = MarkovChain::from(&TEXT).reversed();
+        assert_eq!(
+            (
+                chain.transitions[3].clone(),
+                chain.is_reversed(),
+                chain.reversed().transitions == MarkovChain::from(&TEXT).transitions
+            ),
+            (vec![(3, 4.0), (4, 1.0)], true, true)
+        )
+    }
+
+    #[test]
+    fn compute_reachable_lengths() {
+        let chain = MarkovChain::from(&TEXT);
+        let lengths = chain
+            .states()
+            .iter()
+            .map(|s| s.len())
+            .collect::<Vec<usize>>();
+        let table = chain.reachable(&lengths, 9);
+        // "boy" is followed by "wrote" (5), "is" (2) and "wrong" (5).
+        assert_eq!(
+            (1..=9).filter(|n| table[1][*n]).collect::<Vec<usize>>(),
+            vec![5, 7]
+        )
+    }
+
+    #[test]
+    fn calculate_log_likelihood() {
+        let chain = MarkovChain::from(&TEXT);
+        let expected = -(7.0f64).ln() + (0.8f64).ln();
+        let actual = chain.log_likelihood(&["that", "that"]).unwrap();
+        assert!((actual - expected).abs() < 1e-9 && chain.log_likelihood(&["you"]).is_none())
+    }
+
+    #[test]
+    fn choose_most_probable_states() {
+        let chain = MarkovChain::from(&TEXT);
+        let most_probable = (0..chain.state_space.len())
+            .map(|i| chain.most_probable(i).map(|j| chain.state_space[j]))
+            .collect::<Vec<Option<&str>>>();
+        assert_eq!(
+            most_probable,
+            vec![
+                Some("think"),
+                Some("wrote"),
+                Some("wrong"),
+                Some("that"),
+                Some("that"),
+                None,
+                Some("is")
+            ]
+        )
+    }
+
+    #[test]
+    fn write_fst_in_att_format() {
+        let chain = MarkovChain::from_sequences(&[vec!["a", "b"], vec!["a", "a"]]);
+        let half = 2.0f64.ln();
+        assert_eq!(
+            chain.to_fst(|_, s| *s == "b"),
+            format!(
+                "0\t1\t1\t1\t{0}\n0\t2\t2\t2\t{0}\n1\t1\t1\t1\t{0}\n1\t2\t2\t2\t{0}\n2\t0\n",
+                half
+            )
+        )
+    }
+
+    #[test]
+    fn calculate_path_probability() {
+        let chain = MarkovChain::from(&TEXT);
+        assert_eq!(
+            (
+                chain.path_probability(&["think", "that", "that", "boy"]),
+                chain.path_probability(&["that", "wrote"]),
+                chain.path_probability(&["you"])
+            ),
+            (Some(0.8 * 0.2), None, None)
+        )
+    }
+
+    #[test]
+    fn read_walker_table_of_markov_rs() {
+        let json = r#"{"state_space":["a","b","c"],"wa_table":[
+            {"aliases":[1,1,2],"probs":[1.0,0.0,0.5]},
+            {"aliases":[0,0,0],"probs":[0.0,0.0,0.0]},
+            {"aliases":[0,0,0],"probs":[1.0,1.0,1.0]}],"prev_index":3}"#;
+        let chain = serde_json::from_str::<MarkovChain<String>>(json).unwrap();
+        assert_eq!(
+            chain.transitions,
+            vec![vec![(1, 2.0), (2, 1.0)], vec![], vec![(0, 3.0)]]
+        )
+    }
+}
@@ -1,21 +1,204 @@
 //! Lyric generation with Markov chain.
 
+use std::collections::BTreeMap;
+use std::io::BufRead;
+#[cfg(feature = "tokenizer")]
+use std::path::Path;
+
+use crate::chars::hiragana_to_katakana;
+#[cfg(feature = "tokenizer")]
+use crate::corpus::{load_dir, load_file, read_aozora};
+use crate::corpus::{split_sentences, TERMINATORS};
 use crate::lyric::Lyric;
-use crate::morphological_analysis::{tokenize, LyrianToken};
-use markov_rs::MarkovChain;
+pub use crate::markov::Smoothing;
+use crate::markov::{ChainBuilder, MarkovChain};
+use crate::morphological_analysis::LyrianToken;
+#[cfg(feature = "tokenizer")]
+use crate::normalize::nfkc;
+use crate::pronunciation::{
+    count_morae, split_morae, try_count_morae, JapaneseCounter, MoraCounter,
+};
+#[cfg(feature = "tokenizer")]
+use crate::reading::ReadingOverrides;
+#[cfg(feature = "tokenizer")]
+use crate::tokenize::tokenize;
+use crate::tokenize::{surface_token, Tokenizer};
+#[cfg(feature = "tokenizer")]
+use crate::tokenize::{LinderaTokenizer, TokenFilter};
+use rand::rngs::StdRng;
 use serde::{Deserialize, Serialize};
 
+/// Summary of a model by [`LyrianModel::summary()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModelSummary {
+    /// The number of the distinct tokens.
+    pub vocabulary: usize,
+    /// The number of the tokens which each token depends on, which is
+    /// always `1` for the first-order Markov chain.
+    pub order: usize,
+    /// The number of the distinct transitions between the tokens.
+    pub transitions: usize,
+    /// The number of the tokens which no token followed in the corpus, such
+    /// as the last tokens of the sentences.
+    pub dead_ends: usize,
+    /// The estimated number of bytes which the model occupies in the memory,
+    /// including the fallback model of characters.
+    pub memory: usize,
+}
+
+/// What the generation does when a cycle of tokens repeats, see
+/// [`CycleGuard`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CycleAction {
+    /// Ends the sentence before the cycle repeats again.
+    Stop,
+    /// Discards the sentence, and generates it again from the first token.
+    Restart,
+}
+
+/// A guard against the generation repeating the same cycle of tokens, such
+/// as `ももももも…`, set by [`LyrianModel::with_cycle_guard()`].
+///
+/// The guard fires when the last tokens are a cycle of up to `max_period`
+/// tokens repeated more than `max_repeats` times in a row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CycleGuard {
+    /// The maximum number of the tokens of a cycle detected.
+    pub max_period: usize,
+    /// The number of the repeats of a cycle allowed.
+    pub max_repeats: usize,
+    /// What to do when the guard fires.
+    pub action: CycleAction,
+}
+
+impl CycleGuard {
+    /// Creates a new instance of [`CycleGuard`], which stops the sentences.
+    pub fn new(max_period: usize, max_repeats: usize) -> CycleGuard {
+        CycleGuard {
+            max_period,
+            max_repeats,
+            action: CycleAction::Stop,
+        }
+    }
+
+    /// Sets what to do when the guard fires.
+    pub fn with_action(mut self, action: CycleAction) -> CycleGuard {
+        self.action = action;
+        self
+    }
+
+    /// Returns the period of the cycle repeated too many times at the end of
+    /// the tokens, the shortest one if there are some.
+    ///
+    /// ```rust
+    /// use lyrian::model::CycleGuard;
+    ///
+    /// let guard = CycleGuard::new(2, 2);
+    /// assert_eq!(guard.detect(&["す", "も", "も", "も"]), Some(1));
+    /// assert_eq!(guard.detect(&["も", "の", "も", "の"]), None);
+    /// ```
+    pub fn detect<T: PartialEq>(&self, tokens: &[T]) -> Option<usize> {
+        (1..=self.max_period).find(|period| {
+            let length = period * (self.max_repeats + 1);
+            length <= tokens.len()
+                && (tokens.len() - length + period..tokens.len())
+                    .all(|i| tokens[i] == tokens[i - period])
+        })
+    }
+}
+
+/// A constraint on the sentences of [`LyrianModel::generate_constrained()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Constraint {
+    /// The token of the word must be at the position, counted from `0`.
+    At(usize, String),
+    /// A token of the word must be somewhere in the sentence.
+    Contains(String),
+}
+
+impl Constraint {
+    /// Returns whether the tokens satisfy the constraint.
+    fn is_satisfied(&self, tokens: &[LyrianToken]) -> bool {
+        match self {
+            Constraint::At(k, word) => tokens.get(*k).is_some_and(|t| t.word == *word),
+            Constraint::Contains(word) => tokens.iter().any(|t| t.word == *word),
+        }
+    }
+}
+
+/// Observer of the tokens chosen one by one in generation, such as
+/// [`LyrianModel::generate_sentence_observed()`], e.g. to stream them to a
+/// UI.
+///
+/// The closures of `(token, probability, step)` are also observers.
+///
+/// The observed tokens may not all be in the result with a
+/// [`CycleGuard`]: the tokens of a detected cycle are dropped from the end,
+/// and with [`CycleAction::Restart`] all the tokens but the first are
+/// thrown away, and the steps are counted from `1` again. A step not
+/// greater than the last one tells the observer of such a restart.
+pub trait TokenObserver {
+    /// Observes the token chosen at the step counted from `0`, and the
+    /// probability that it follows the previous token in the model. The
+    /// first token is given the probability `1.0`.
+    ///
+    /// The generation is aborted after the token if `false` is returned.
+    fn on_token(&mut self, token: &LyrianToken, probability: f64, step: usize) -> bool;
+}
+
+impl<F: FnMut(&LyrianToken, f64, usize) -> bool> TokenObserver for F {
+    fn on_token(&mut self, token: &LyrianToken, probability: f64, step: usize) -> bool {
+        self(token, probability, step)
+    }
+}
+
+/// Options to exclude tokens in sampling the next tokens.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+struct Sampling {
+    /// Excludes the last token.
+    no_repeat: bool,
+    /// Excludes the token before the last one.
+    no_repeat_previous: bool,
+    /// Excludes all the tokens in the sequence, and ends it if no token is
+    /// left.
+    unique: bool,
+}
+
+/// The state of the random number generators of a model, taken by
+/// [`LyrianModel::rng_state()`] to branch the generation.
+///
+/// The state of the model never seeded is empty, and makes the model
+/// unseeded again when restored.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RngState {
+    chain: Option<StdRng>,
+    fallback: Option<Box<RngState>>,
+}
+
 /// The structure of generating lyric.
+///
+/// The states of the Markov chain are the tokens with their surface forms,
+/// readings and parts of speech, so the homographs of different parts of
+/// speech do not share a state.
 #[derive(Serialize, Deserialize, PartialEq, Debug)]
 pub struct LyrianModel {
     markov: MarkovChain<LyrianToken>,
+    #[serde(skip)]
+    fallback: Option<Box<LyrianModel>>,
+    #[serde(skip)]
+    cycle_guard: Option<CycleGuard>,
+    #[serde(skip)]
+    sampling: Sampling,
 }
 
 impl<'a> LyrianModel {
     /// Creates a new instance of [`LyrianModel`].
-    fn new(markov_model: MarkovChain<LyrianToken>) -> LyrianModel {
+    pub(crate) fn new(markov_model: MarkovChain<LyrianToken>) -> LyrianModel {
         LyrianModel {
             markov: markov_model,
+            fallback: None,
+            cycle_guard: None,
+            sampling: Sampling::default(),
         }
     }
 
@@ -23,16 +206,249 @@ impl<'a> LyrianModel {
     ///
     /// Creates a [`LyrianModel`] by splitting text data into tokens and building
     /// a markov chain model from them.
+    #[cfg(feature = "tokenizer")]
     pub fn from_str(contents: &str) -> Result<LyrianModel, String> {
-        let tokens = tokenize(contents)?;
-        let markov_model = MarkovChain::from(&tokens);
-        let lyr_model = LyrianModel::new(markov_model);
-        Ok(lyr_model)
+        LyrianModel::from_str_with_overrides(contents, &ReadingOverrides::new())
+    }
+
+    /// Builds a new model from Japanese text, which is tokenized by
+    /// [`LinderaTokenizer`](crate::tokenize::LinderaTokenizer).
+    ///
+    /// The text is split into sentences by
+    /// [`corpus::split_sentences()`](crate::corpus::split_sentences), and no
+    /// transition is learned between the sentences.
+    ///
+    /// ```rust
+    /// use lyrian::model::LyrianModel;
+    ///
+    /// let mut model = LyrianModel::from_text("古池や蛙飛び込む水の音").unwrap();
+    /// println!("{}", model.generate_lyric(5, false).unwrap().join());
+    /// ```
+    #[cfg(feature = "tokenizer")]
+    pub fn from_text(contents: &str) -> Result<LyrianModel, String> {
+        LyrianModel::from_text_with(contents, &LinderaTokenizer::new()?)
+    }
+
+    /// Builds a new model from a file of Japanese text loaded by
+    /// [`corpus::load_file()`](crate::corpus::load_file).
+    #[cfg(feature = "tokenizer")]
+    pub fn from_text_file<P: AsRef<Path>>(path: P) -> Result<LyrianModel, String> {
+        LyrianModel::from_text(&load_file(path)?)
+    }
+
+    /// Builds a new model from all the files in the directory whose names
+    /// match the pattern, loaded by
+    /// [`corpus::load_dir()`](crate::corpus::load_dir).
+    ///
+    /// No transition is learned between the files, as well as between the
+    /// sentences.
+    #[cfg(feature = "tokenizer")]
+    pub fn from_text_dir<P: AsRef<Path>>(path: P, pattern: &str) -> Result<LyrianModel, String> {
+        let texts = load_dir(path, pattern)?
+            .into_iter()
+            .map(|(_, text)| text)
+            .collect::<Vec<String>>();
+        LyrianModel::from_texts_with(&texts, &LinderaTokenizer::new()?)
+    }
+
+    /// Builds a new model from a text of Aozora Bunko preprocessed by
+    /// [`corpus::read_aozora()`](crate::corpus::read_aozora), where the ruby
+    /// gives the readings of the words.
+    #[cfg(feature = "tokenizer")]
+    pub fn from_aozora(contents: &str) -> Result<LyrianModel, String> {
+        let aozora = read_aozora(contents);
+        let tokenizer = LinderaTokenizer::new()?;
+        LyrianModel::from_text_with(&aozora.text, &|text: &str| {
+            let mut tokens = tokenizer.tokenize(text)?;
+            aozora.readings.apply(&mut tokens);
+            Ok(tokens)
+        })
+    }
+
+    /// Builds a new model from Japanese text, whose tokens are filtered by
+    /// their parts of speech.
+    #[cfg(feature = "tokenizer")]
+    pub fn from_text_filtered(contents: &str, filter: &TokenFilter) -> Result<LyrianModel, String> {
+        let tokenizer = LinderaTokenizer::new()?;
+        LyrianModel::from_text_with(contents, &|text: &str| {
+            Ok(filter.filter(tokenizer.tokenize(text)?))
+        })
+    }
+
+    /// Builds a new model from text data tokenized by the tokenizer, split
+    /// into sentences like [`from_text()`](#method.from_text).
+    pub fn from_text_with<T: Tokenizer + ?Sized>(
+        contents: &str,
+        tokenizer: &T,
+    ) -> Result<LyrianModel, String> {
+        LyrianModel::from_texts_with(&[contents], tokenizer)
+    }
+
+    /// Builds a new model from the texts tokenized by the tokenizer, such as
+    /// the records of a corpus, where no transition is learned between the
+    /// texts and between their sentences.
+    ///
+    /// ```rust
+    /// use lyrian::corpus::read_jsonl;
+    /// use lyrian::model::LyrianModel;
+    /// use lyrian::tokenize::LinderaTokenizer;
+    ///
+    /// let jsonl = "{\"text\": \"古池や\"}\n{\"text\": \"蛙飛び込む水の音\"}\n";
+    /// let texts = read_jsonl(jsonl, "text").unwrap();
+    /// let model = LyrianModel::from_texts_with(&texts, &LinderaTokenizer::new().unwrap());
+    /// ```
+    pub fn from_texts_with<S: AsRef<str>, T: Tokenizer + ?Sized>(
+        texts: &[S],
+        tokenizer: &T,
+    ) -> Result<LyrianModel, String> {
+        let mut builder = ChainBuilder::new();
+        for text in texts {
+            for sentence in split_sentences(text.as_ref()) {
+                builder.add(&tokenizer.tokenize(sentence)?);
+            }
+        }
+        Ok(LyrianModel::new(builder.build()))
+    }
+
+    /// Builds a new model from text data read line by line, tokenized by the
+    /// tokenizer.
+    ///
+    /// Only the counts of the transitions are kept, so a corpus larger than
+    /// the memory can be used. The lines are split into sentences like
+    /// [`from_text()`](#method.from_text), and must be UTF-8.
+    ///
+    /// ```rust
+    /// use std::fs::File;
+    /// use std::io::BufReader;
+    ///
+    /// use lyrian::model::LyrianModel;
+    /// use lyrian::tokenize::LinderaTokenizer;
+    ///
+    /// let file = File::open("examples/sample_text.txt").unwrap();
+    /// let tokenizer = LinderaTokenizer::new().unwrap();
+    /// let model = LyrianModel::from_reader(BufReader::new(file), &tokenizer).unwrap();
+    /// ```
+    pub fn from_reader<R: BufRead, T: Tokenizer + ?Sized>(
+        mut reader: R,
+        tokenizer: &T,
+    ) -> Result<LyrianModel, String> {
+        let mut builder = ChainBuilder::new();
+        let mut line = String::new();
+        loop {
+            line.clear();
+            match reader.read_line(&mut line) {
+                Ok(0) => break,
+                Ok(_) => {
+                    for sentence in split_sentences(line.trim_start_matches('\u{feff}')) {
+                        builder.add(&tokenizer.tokenize(sentence)?);
+                    }
+                }
+                Err(e) => return Err(e.to_string()),
+            }
+        }
+        Ok(LyrianModel::new(builder.build()))
+    }
+
+    /// Builds a new model from the sequences of tokens, such as sentences,
+    /// where no transition is learned between the sequences.
+    pub fn from_sequences(sequences: &[Vec<LyrianToken>]) -> LyrianModel {
+        LyrianModel::new(MarkovChain::from_sequences(sequences))
+    }
+
+    /// Builds a new model of characters, where each character of the text is
+    /// a token.
+    ///
+    /// The text is split into sentences like
+    /// [`from_text()`](#method.from_text), and the whitespace is skipped.
+    /// The characters in kana have their readings, and the others are
+    /// counted as `0` morae. Small corpora often work better at this
+    /// granularity, and the sentences are generated back by
+    /// [`generate_sentence()`](#method.generate_sentence).
+    ///
+    /// ```rust
+    /// use lyrian::model::LyrianModel;
+    ///
+    /// let mut model = LyrianModel::chars_from("ふるいけや。かわずとびこむ。");
+    /// assert_eq!(model.tokens().len(), 13);
+    /// println!("{}", model.generate_sentence(20));
+    /// ```
+    pub fn chars_from(contents: &str) -> LyrianModel {
+        let mut builder = ChainBuilder::new();
+        for sentence in split_sentences(contents) {
+            let tokens = sentence
+                .chars()
+                .filter(|c| !c.is_whitespace())
+                .map(|c| surface_token(&c.to_string()))
+                .collect::<Vec<LyrianToken>>();
+            builder.add(&tokens);
+        }
+        LyrianModel::new(builder.build())
+    }
+
+    /// Builds a new model of morae, where each mora of the kana text is a
+    /// token.
+    ///
+    /// The text is split into sentences like
+    /// [`from_text()`](#method.from_text), and then into morae by
+    /// [`pronunciation::split_morae()`](crate::pronunciation::split_morae),
+    /// which skips the characters which are not counted, such as kanji and
+    /// symbols. Each token is counted as exactly one mora, which is a
+    /// natural granularity to generate poems.
+    ///
+    /// ```rust
+    /// use lyrian::model::LyrianModel;
+    ///
+    /// let model = LyrianModel::from_morae("きゃっとふーど");
+    /// let kya = model.tokens().iter().find(|t| t.word == "きゃ").unwrap();
+    /// assert_eq!((model.tokens().len(), model.token_morae(kya)), (6, Some(1)));
+    /// ```
+    pub fn from_morae(contents: &str) -> LyrianModel {
+        let mut builder = ChainBuilder::new();
+        for sentence in split_sentences(contents) {
+            let tokens = split_morae(sentence)
+                .into_iter()
+                .map(mora_token)
+                .collect::<Vec<LyrianToken>>();
+            builder.add(&tokens);
+        }
+        LyrianModel::new(builder.build())
+    }
+
+    /// Builds a new model from text data with the readings overridden by the
+    /// user.
+    ///
+    /// The lengths of the generated lyrics are calculated by the overridden
+    /// readings.
+    #[cfg(feature = "tokenizer")]
+    pub fn from_str_with_overrides(
+        contents: &str,
+        overrides: &ReadingOverrides,
+    ) -> Result<LyrianModel, String> {
+        let mut tokens = tokenize(contents)?;
+        overrides.apply(&mut tokens);
+        Ok(LyrianModel::from_tokens(&tokens))
+    }
+
+    /// Builds a new model from text data normalized in NFKC.
+    ///
+    /// Use this instead of [`from_str()`](#method.from_str) when the text
+    /// data contains full-width ASCII or compatibility characters, which
+    /// would otherwise be treated as different words. See
+    /// [`normalize::nfkc()`](crate::normalize::nfkc) for the details.
+    #[cfg(feature = "tokenizer")]
+    pub fn from_normalized_str(contents: &str) -> Result<LyrianModel, String> {
+        LyrianModel::from_str(&nfkc(contents))
+    }
+
+    /// Builds a new model from tokens.
+    pub(crate) fn from_tokens(tokens: &[LyrianToken]) -> LyrianModel {
+        LyrianModel::new(MarkovChain::from(tokens))
     }
 
     /// Builds a new model from json data.
     ///
     /// The json data is generated by [`to_json_str()`](#method.to_json_str) method.
+    /// The data saved by the versions using `markov_rs` can also be loaded.
     pub fn from_json(json: &'a str) -> Result<LyrianModel, String> {
         match serde_json::from_str::<'a, MarkovChain<LyrianToken>>(json) {
             Ok(markov_model) => {
@@ -57,15 +473,40 @@ impl<'a> LyrianModel {
     /// If you set `false` to all the arguments, you will get a lyric assigned
     /// by mora unit.
     pub fn generate_lyric(&mut self, num_of_notes: usize, syllable: bool) -> Result<Lyric, String> {
+        self.generate_lyric_with(num_of_notes, &JapaneseCounter::new(syllable))
+    }
+
+    /// Generates a lyric whose length is counted by the counter.
+    ///
+    /// See [`generate_lyric()`](#method.generate_lyric) for the details.
+    pub fn generate_lyric_with<C: MoraCounter + ?Sized>(
+        &mut self,
+        num_of_notes: usize,
+        counter: &C,
+    ) -> Result<Lyric, String> {
         for _ in 0..64 {
-            let mut lyric = Lyric::new(vec![self.get_first_token()]);
+            let mut lyric = match self.get_first_token() {
+                Some(token) => Lyric::new(vec![token]),
+                None => break,
+            };
             for _ in 0..64 {
-                if num_of_notes < lyric.length(syllable) {
+                if num_of_notes < lyric.length_by(counter) {
                     break;
-                } else if num_of_notes == lyric.length(syllable) {
+                } else if num_of_notes == lyric.length_by(counter) {
+                    if self.markov.is_reversed() {
+                        return Ok(Lyric::new(lyric.tokens().iter().rev().cloned().collect()));
+                    }
                     return Ok(lyric);
                 }
-                lyric.add_token(self.markov.next().clone());
+                match self.next_after(lyric.tokens()) {
+                    Some(token) => lyric.add_token(token),
+                    None => break,
+                }
+                if let Some(guard) = &self.cycle_guard {
+                    if guard.detect(lyric.tokens()).is_some() {
+                        break;
+                    }
+                }
             }
             self.markov.initialize();
         }
@@ -75,10 +516,570 @@ impl<'a> LyrianModel {
         ))
     }
 
+    /// Generates a sentence, and joins the words of its tokens.
+    ///
+    /// The sentence ends with a token which ends with a terminator such as
+    /// `。`, a token which was the last one of a sentence in the corpus, or
+    /// `max_tokens` tokens. The first token is neither a particle, an
+    /// auxiliary verb nor a symbol. An empty string is returned if no
+    /// token can start a sentence.
+    pub fn generate_sentence(&mut self, max_tokens: usize) -> String {
+        let sentence = match self.get_starting_token() {
+            Some(token) if max_tokens > 0 => self.continue_sentence(&token, max_tokens, None),
+            _ => String::new(),
+        };
+        self.markov.initialize();
+        sentence
+    }
+
+    /// Generates a sentence like
+    /// [`generate_sentence()`](#method.generate_sentence), letting the
+    /// observer see each token as it is chosen and abort the sentence.
+    ///
+    /// ```rust
+    /// use lyrian::model::LyrianModel;
+    /// use lyrian::tokenize::LyrianToken;
+    ///
+    /// let mut model = LyrianModel::chars_from("もももも");
+    /// let mut steps = Vec::new();
+    /// let sentence = model.generate_sentence_observed(20, &mut |t: &LyrianToken, p: f64, step: usize| {
+    ///     steps.push((t.word.clone(), p, step));
+    ///     step < 2
+    /// });
+    /// assert_eq!((sentence.chars().count(), steps.len()), (3, 3));
+    /// ```
+    pub fn generate_sentence_observed<O: TokenObserver + ?Sized>(
+        &mut self,
+        max_tokens: usize,
+        observer: &mut O,
+    ) -> String {
+        let sentence = match self.get_starting_token() {
+            Some(token) if max_tokens > 0 => {
+                self.continue_sentence(&token, max_tokens, Some(&mut Observe(observer)))
+            }
+            _ => String::new(),
+        };
+        self.markov.initialize();
+        sentence
+    }
+
+    /// Generates a sentence which starts from the word, like
+    /// [`generate_sentence()`](#method.generate_sentence).
+    ///
+    /// The most frequent token of the word is followed. If the word is out
+    /// of the vocabulary, the sentence is completed by the model of
+    /// characters set by [`with_char_fallback()`](#method.with_char_fallback)
+    /// from the last character of the word, or an error is returned without
+    /// the fallback.
+    ///
+    /// ```rust
+    /// use lyrian::model::LyrianModel;
+    ///
+    /// let corpus = "古池や。かわずとびこむみずのおと。";
+    /// let mut model = LyrianModel::from_text(corpus).unwrap().with_char_fallback(corpus);
+    /// assert!(model.generate_from("ふる池", 10).unwrap().starts_with("ふる池"));
+    /// ```
+    pub fn generate_from(&mut self, word: &str, max_tokens: usize) -> Result<String, String> {
+        if let Some(token) = self.most_frequent_token(word) {
+            self.markov.set_current(&token);
+            let sentence = self.continue_sentence(&token, max_tokens.max(1), None);
+            self.markov.initialize();
+            return Ok(sentence);
+        }
+
+        let fallback = match &mut self.fallback {
+            Some(fallback) => fallback,
+            None => return Err(format!("The word `{}` is not in the model.", word)),
+        };
+        let reversed = fallback.is_reversed();
+        let boundary = if reversed {
+            word.chars().next()
+        } else {
+            word.chars().last()
+        };
+        let last = match boundary {
+            Some(c) => surface_token(&c.to_string()),
+            None => return Ok(fallback.generate_sentence(max_tokens)),
+        };
+        let rest = if fallback.follow(&last) {
+            let sentence = fallback.continue_sentence(&last, max_tokens.max(1), None);
+            match reversed {
+                true => sentence[..sentence.len() - last.word.len()].to_string(),
+                false => sentence[last.word.len()..].to_string(),
+            }
+        } else {
+            fallback.generate_sentence(max_tokens)
+        };
+        fallback.initialize();
+        match reversed {
+            true => Ok(format!("{}{}", rest, word)),
+            false => Ok(format!("{}{}", word, rest)),
+        }
+    }
+
+    /// Generates a sentence around the word, which grows from the most
+    /// frequent token of the word in both directions, so that the word can
+    /// be in the middle of the sentence.
+    ///
+    /// Up to `before` tokens are generated backwards by the model of
+    /// [`reverse()`](#method.reverse) until the start of a sentence, and up
+    /// to `after` tokens forwards until the end of a sentence. The reversed
+    /// model is built on each call. An error is returned if the word is not
+    /// in the model.
+    ///
+    /// ```rust
+    /// use lyrian::model::LyrianModel;
+    ///
+    /// let mut model = LyrianModel::chars_from("ふるいけや。");
+    /// assert_eq!(model.generate_around("い", 10, 10), Ok("ふるいけや。".to_string()));
+    /// assert_eq!(model.generate_around("い", 1, 1), Ok("るいけ".to_string()));
+    /// ```
+    pub fn generate_around(
+        &mut self,
+        word: &str,
+        before: usize,
+        after: usize,
+    ) -> Result<String, String> {
+        let token = match self.most_frequent_token(word) {
+            Some(token) => token,
+            None => return Err(format!("The word `{}` is not in the model.", word)),
+        };
+        let mut other = LyrianModel {
+            markov: self.markov.reversed(),
+            fallback: None,
+            cycle_guard: self.cycle_guard,
+            sampling: self.sampling,
+        };
+        if let Some(seed) = self.markov.draw_seed() {
+            other.set_seed(seed);
+        }
+        let (backward, forward) = match self.is_reversed() {
+            true => (self, &mut other),
+            false => (&mut other, self),
+        };
+        backward.markov.set_current(&token);
+        let head = backward.continue_sentence(&token, before + 1, None);
+        backward.markov.initialize();
+        forward.markov.set_current(&token);
+        let tail = forward.continue_sentence(&token, after + 1, None);
+        forward.markov.initialize();
+        Ok(format!("{}{}", head, &tail[token.word.len()..]))
+    }
+
+    /// Generates a sentence which satisfies the constraints, like
+    /// [`generate_sentence()`](#method.generate_sentence).
+    ///
+    /// The tokens at the positions of [`Constraint::At`] are sampled only
+    /// from the tokens of the words, and the sentences which still break the
+    /// constraints are generated again up to `retries` times. The most
+    /// frequent token of the word is chosen at the position `0`, as in
+    /// [`generate_from()`](#method.generate_from). The positions are counted
+    /// from the end of the sentences in the models of
+    /// [`reverse()`](#method.reverse).
+    ///
+    /// ```rust
+    /// use lyrian::model::{Constraint, LyrianModel};
+    ///
+    /// let mut model = LyrianModel::chars_from("あいう。かいえ。");
+    /// let constraints = [Constraint::At(1, "い".to_string()), Constraint::Contains("え".to_string())];
+    /// let sentence = model.generate_constrained(&constraints, 10, 100).unwrap();
+    /// assert!(sentence == "あいえ。" || sentence == "かいえ。");
+    /// ```
+    pub fn generate_constrained(
+        &mut self,
+        constraints: &[Constraint],
+        max_tokens: usize,
+        retries: usize,
+    ) -> Result<String, String> {
+        let first = constraints.iter().find_map(|c| match c {
+            Constraint::At(0, word) => Some(word),
+            _ => None,
+        });
+        let first = match first {
+            Some(word) => match self.most_frequent_token(word) {
+                Some(token) => Some(token),
+                None => return Err(format!("The word `{}` is not in the model.", word)),
+            },
+            None => None,
+        };
+        for _ in 0..=retries {
+            let token = match &first {
+                Some(token) => {
+                    self.markov.set_current(token);
+                    token.clone()
+                }
+                None => match self.get_starting_token() {
+                    Some(token) => token,
+                    None => break,
+                },
+            };
+            let tokens = self.continue_tokens(&token, max_tokens.max(1), constraints, None);
+            self.markov.initialize();
+            if let Some(tokens) = tokens.filter(|t| constraints.iter().all(|c| c.is_satisfied(t))) {
+                return Ok(self.join_tokens(&tokens));
+            }
+        }
+        Err(String::from(
+            "Could not generate a sentence satisfying the constraints.",
+        ))
+    }
+
+    /// Seeds the random number generator of the model, so that the same
+    /// lyrics and sentences are generated from the same seed.
+    pub fn with_seed(mut self, seed: u64) -> LyrianModel {
+        self.set_seed(seed);
+        self
+    }
+
+    /// Seeds the random number generator of the model, see
+    /// [`with_seed()`](#method.with_seed). The model of characters of
+    /// [`with_char_fallback()`](#method.with_char_fallback) is also seeded.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.markov.seed(seed);
+        if let Some(fallback) = &mut self.fallback {
+            fallback.set_seed(seed);
+        }
+    }
+
+    /// Returns the state of the random number generator of the model, and
+    /// of the model of characters of
+    /// [`with_char_fallback()`](#method.with_char_fallback).
+    ///
+    /// The generation goes the same way again after the state is restored by
+    /// [`set_rng_state()`](#method.set_rng_state), so that some
+    /// continuations can be tried from the same point.
+    ///
+    /// ```rust
+    /// use lyrian::model::LyrianModel;
+    ///
+    /// let mut model = LyrianModel::chars_from("あいう。いあ。うい。").with_seed(42);
+    /// let state = model.rng_state();
+    /// let first = model.generate_sentence(10);
+    /// model.set_rng_state(&state);
+    /// assert_eq!(model.generate_sentence(10), first);
+    /// ```
+    pub fn rng_state(&self) -> RngState {
+        RngState {
+            chain: self.markov.rng().cloned(),
+            fallback: self.fallback.as_ref().map(|f| Box::new(f.rng_state())),
+        }
+    }
+
+    /// Restores the state of the random number generator taken by
+    /// [`rng_state()`](#method.rng_state).
+    ///
+    /// The model of characters is left as it is if the state has no state
+    /// of it.
+    pub fn set_rng_state(&mut self, state: &RngState) {
+        self.markov.set_rng(state.chain.clone());
+        if let (Some(fallback), Some(state)) = (&mut self.fallback, &state.fallback) {
+            fallback.set_rng_state(state);
+        }
+    }
+
+    /// Smooths the transitions of the model, so that the tokens never seen
+    /// after a token in the corpus can follow it. See [`Smoothing`] for the
+    /// strategies.
+    ///
+    /// The smoothing is saved in the json data of
+    /// [`to_json_str()`](#method.to_json_str).
+    ///
+    /// ```rust
+    /// use lyrian::model::{LyrianModel, Smoothing};
+    ///
+    /// let model = LyrianModel::chars_from("あいう。いいえ。うえ。").with_smoothing(Smoothing::GoodTuring);
+    /// let a = model.tokens().iter().find(|t| t.word == "あ").unwrap();
+    /// assert_eq!(model.successors(a).len(), 1);
+    /// ```
+    pub fn with_smoothing(mut self, smoothing: Smoothing) -> LyrianModel {
+        self.markov.set_smoothing(smoothing);
+        self
+    }
+
+    /// Adds a symmetric Dirichlet prior to the transitions of the model,
+    /// that is, the pseudo-count `alpha` to the transitions between all the
+    /// tokens. See [`with_prior_by()`](#method.with_prior_by).
+    pub fn with_prior(self, alpha: f64) -> LyrianModel {
+        self.with_prior_by(|_| alpha)
+    }
+
+    /// Adds a Dirichlet prior to the transitions of the model, which gives
+    /// the pseudo-counts of the transitions into each token. The prior is
+    /// applied after the smoothing of [`with_smoothing()`](#method.with_smoothing),
+    /// and saved in the json data of [`to_json_str()`](#method.to_json_str).
+    ///
+    /// The prior injects knowledge out of the corpus, e.g. to always allow
+    /// the transitions into the punctuation.
+    ///
+    /// ```rust
+    /// use lyrian::model::LyrianModel;
+    ///
+    /// let model = LyrianModel::chars_from("あいう。いいえ。").with_prior_by(|t| match t.word.as_str() {
+    ///     "。" => 0.5,
+    ///     _ => 0.0,
+    /// });
+    /// let a = model.tokens().iter().find(|t| t.word == "あ").unwrap();
+    /// // "い" follows "あ" once, and "。" may follow it with the pseudo-count.
+    /// assert_eq!(model.successors(a)[0].1, 1.0 / 1.5);
+    /// ```
+    pub fn with_prior_by<F: FnMut(&LyrianToken) -> f64>(mut self, mut prior: F) -> LyrianModel {
+        self.markov.set_prior(|_, t| prior(t));
+        self
+    }
+
+    /// Trains a model of characters alongside, which completes the
+    /// sentences from the words out of the vocabulary in
+    /// [`generate_from()`](#method.generate_from).
+    ///
+    /// See [`chars_from()`](#method.chars_from) for the model of characters.
+    /// The fallback is not saved in the json data of
+    /// [`to_json_str()`](#method.to_json_str), nor in any serialization of
+    /// the model.
+    pub fn with_char_fallback(mut self, contents: &str) -> LyrianModel {
+        let fallback = LyrianModel::chars_from(contents);
+        self.fallback = Some(Box::new(match self.is_reversed() {
+            true => fallback.reverse(),
+            false => fallback,
+        }));
+        self
+    }
+
+    /// Builds the model of the reversed sentences, which generates the
+    /// sentences backwards from their last tokens, e.g. to end a poem with a
+    /// kigo.
+    ///
+    /// The sentences are returned in the usual order, and end with the
+    /// tokens given to [`generate_from()`](#method.generate_from). They
+    /// start with the tokens which were the first ones of the sentences in
+    /// the corpus, or at `max_tokens` tokens. The model of characters of
+    /// [`with_char_fallback()`](#method.with_char_fallback) is also
+    /// reversed, and the model saved in json is still reversed.
+    ///
+    /// ```rust
+    /// use lyrian::model::LyrianModel;
+    ///
+    /// let mut model = LyrianModel::chars_from("ふるいけや。かわずとびこむ。").reverse();
+    /// assert_eq!(model.generate_from("や", 10), Ok("ふるいけや".to_string()));
+    /// ```
+    pub fn reverse(&self) -> LyrianModel {
+        LyrianModel {
+            markov: self.markov.reversed(),
+            fallback: self.fallback.as_ref().map(|f| Box::new(f.reverse())),
+            cycle_guard: self.cycle_guard,
+            sampling: self.sampling,
+        }
+    }
+
+    /// Returns whether the model is built by [`reverse()`](#method.reverse),
+    /// which generates the sentences backwards.
+    pub fn is_reversed(&self) -> bool {
+        self.markov.is_reversed()
+    }
+
+    /// Guards the generation against the cycles of tokens repeated too many
+    /// times, which otherwise may continue until `max_tokens`. See
+    /// [`CycleGuard`]. The model of characters of
+    /// [`with_char_fallback()`](#method.with_char_fallback) is also guarded.
+    ///
+    /// The lyrics are always generated again, since they can not be stopped
+    /// before their lengths.
+    ///
+    /// ```rust
+    /// use lyrian::model::{CycleGuard, LyrianModel};
+    ///
+    /// let mut model = LyrianModel::chars_from("もも").with_cycle_guard(CycleGuard::new(1, 3));
+    /// assert_eq!(model.generate_from("も", 100), Ok("ももも".to_string()));
+    /// ```
+    pub fn with_cycle_guard(mut self, guard: CycleGuard) -> LyrianModel {
+        self.cycle_guard = Some(guard);
+        if let Some(fallback) = self.fallback.take() {
+            self.fallback = Some(Box::new(fallback.with_cycle_guard(guard)));
+        }
+        self
+    }
+
+    /// Never chooses the same token as the last one in a row if the other
+    /// tokens can follow it, which prevents stuttering such as `もももも`.
+    /// The model of characters of
+    /// [`with_char_fallback()`](#method.with_char_fallback) is also set.
+    ///
+    /// ```rust
+    /// use lyrian::model::LyrianModel;
+    ///
+    /// let mut model = LyrianModel::chars_from("すもももももももものうち").with_no_repeat(true);
+    /// assert_eq!(model.generate_from("す", 10), Ok("すものうち".to_string()));
+    /// ```
+    pub fn with_no_repeat(mut self, no_repeat: bool) -> LyrianModel {
+        self.sampling.no_repeat = no_repeat;
+        self.fallback = self.fallback.map(|f| Box::new(f.with_no_repeat(no_repeat)));
+        self
+    }
+
+    /// Never chooses the same token as the one before the last, which
+    /// prevents alternating tokens such as `のもの`, like
+    /// [`with_no_repeat()`](#method.with_no_repeat).
+    pub fn with_no_repeat_previous(mut self, no_repeat: bool) -> LyrianModel {
+        self.sampling.no_repeat_previous = no_repeat;
+        self.fallback = self
+            .fallback
+            .map(|f| Box::new(f.with_no_repeat_previous(no_repeat)));
+        self
+    }
+
+    /// Uses each token at most once in a sentence or a lyric, which ends the
+    /// sentence if all the next tokens are used. This generates more varied
+    /// short outputs such as names.
+    ///
+    /// The model of characters of
+    /// [`with_char_fallback()`](#method.with_char_fallback) is also set.
+    ///
+    /// ```rust
+    /// use lyrian::model::LyrianModel;
+    ///
+    /// let mut model = LyrianModel::chars_from("すもももももももものうち").with_unique_tokens(true);
+    /// assert_eq!(model.generate_from("す", 10), Ok("すものうち".to_string()));
+    /// ```
+    pub fn with_unique_tokens(mut self, unique: bool) -> LyrianModel {
+        self.sampling.unique = unique;
+        self.fallback = self
+            .fallback
+            .map(|f| Box::new(f.with_unique_tokens(unique)));
+        self
+    }
+
+    /// Chooses the token which follows the tokens, excluding the tokens by
+    /// the options of sampling unless no token is left.
+    ///
+    /// [`None`] is returned if no token is left without the used tokens of
+    /// [`with_unique_tokens()`](#method.with_unique_tokens).
+    fn next_after(&mut self, tokens: &[LyrianToken]) -> Option<LyrianToken> {
+        let sampling = self.sampling;
+        if sampling != Sampling::default() {
+            let last = tokens.last();
+            let previous = tokens.len().checked_sub(2).map(|i| &tokens[i]);
+            let excluded = |t: &LyrianToken| {
+                (sampling.no_repeat && last == Some(t))
+                    || (sampling.no_repeat_previous && previous == Some(t))
+                    || (sampling.unique && tokens.contains(t))
+            };
+            let token = self
+                .markov
+                .next_by(|_, t| if excluded(t) { 0.0 } else { 1.0 });
+            if token.is_some() || sampling.unique {
+                return token.cloned();
+            }
+        }
+        Some(self.markov.next().clone())
+    }
+
+    /// Continues the sentence from the token chosen last, until the end of
+    /// the sentence or `max_tokens` tokens including it.
+    fn continue_sentence(
+        &mut self,
+        first: &LyrianToken,
+        max_tokens: usize,
+        observer: Option<&mut dyn TokenObserver>,
+    ) -> String {
+        let tokens = self.continue_tokens(first, max_tokens, &[], observer);
+        self.join_tokens(&tokens.unwrap_or_default())
+    }
+
+    /// Joins the words of the tokens of a sentence, in the reverse order if
+    /// the model is reversed.
+    fn join_tokens(&self, tokens: &[LyrianToken]) -> String {
+        match self.markov.is_reversed() {
+            true => tokens.iter().rev().map(|t| t.word.as_str()).collect(),
+            false => tokens.iter().map(|t| t.word.as_str()).collect(),
+        }
+    }
+
+    /// Continues the tokens of a sentence like
+    /// [`continue_sentence()`](#method.continue_sentence), sampling the
+    /// tokens at the positions of [`Constraint::At`] from their words.
+    ///
+    /// [`None`] is returned if no token of the word can be at the position.
+    fn continue_tokens(
+        &mut self,
+        first: &LyrianToken,
+        max_tokens: usize,
+        constraints: &[Constraint],
+        mut observer: Option<&mut dyn TokenObserver>,
+    ) -> Option<Vec<LyrianToken>> {
+        // The reversed sentences end before the terminators of the previous
+        // sentences.
+        let reversed = self.markov.is_reversed();
+        let mut tokens = vec![first.clone()];
+        let mut restarts = 0;
+        if let Some(observer) = observer.as_mut() {
+            if !observer.on_token(first, 1.0, 0) {
+                return Some(tokens);
+            }
+        }
+        while tokens.len() < max_tokens {
+            let last = &tokens[tokens.len() - 1];
+            if (!reversed && last.word.ends_with(TERMINATORS)) || self.markov.is_end() {
+                break;
+            }
+            let required = constraints.iter().find_map(|c| match c {
+                Constraint::At(k, word) if *k == tokens.len() => Some(word),
+                _ => None,
+            });
+            let token = match required {
+                Some(word) => Some(
+                    self.markov
+                        .next_by(|_, t| if t.word == *word { 1.0 } else { 0.0 })?
+                        .clone(),
+                ),
+                None => self.next_after(&tokens),
+            };
+            match token {
+                Some(token) if reversed && token.word.ends_with(TERMINATORS) => break,
+                Some(token) => tokens.push(token),
+                None => break,
+            }
+            if let Some(observer) = observer.as_mut() {
+                let (previous, token) = (&tokens[tokens.len() - 2], &tokens[tokens.len() - 1]);
+                let probability = self.markov.probability(previous, token);
+                if !observer.on_token(token, probability, tokens.len() - 1) {
+                    break;
+                }
+            }
+            let guard = match &self.cycle_guard {
+                Some(guard) => guard,
+                None => continue,
+            };
+            if let Some(period) = guard.detect(&tokens) {
+                if guard.action == CycleAction::Restart && restarts < 64 {
+                    restarts += 1;
+                    tokens.truncate(1);
+                    self.markov.set_current(first);
+                } else {
+                    tokens.truncate(tokens.len() - period);
+                    break;
+                }
+            }
+        }
+        Some(tokens)
+    }
+
+    /// Returns the most frequent token of the word.
+    pub(crate) fn most_frequent_token(&self, word: &str) -> Option<LyrianToken> {
+        self.markov
+            .states()
+            .iter()
+            .enumerate()
+            .filter(|(_, t)| t.word == word)
+            .max_by_key(|(i, _)| self.markov.frequency(*i))
+            .map(|(_, t)| t.clone())
+    }
+
     /// Converts the model to the json data.
     ///
     /// Use this to reduce computational costs and to correct words that
     /// are not in the corpus.
+    ///
+    /// The chain is saved as the weights of the transitions, which the
+    /// versions using `markov_rs` can not load.
     pub fn to_json_str(&self) -> Result<String, String> {
         match serde_json::to_string(&self.markov) {
             Ok(v) => Ok(v),
@@ -86,23 +1087,475 @@ impl<'a> LyrianModel {
         }
     }
 
-    // Gets a first token that is neither a particle nor an auxiliary verb.
-    fn get_first_token(&mut self) -> LyrianToken {
-        let mut token;
-        loop {
-            token = self.markov.next().clone();
-            if token.part_of_speech != "助詞" && token.part_of_speech != "助動詞" {
-                break;
+    /// Returns the tokens in the model, sorted and without duplicates.
+    pub fn tokens(&self) -> &[LyrianToken] {
+        self.markov.states()
+    }
+
+    /// Returns the number of times the token appeared in the corpus, or `0`
+    /// if it is not in the model.
+    ///
+    /// The models saved before the frequencies were kept estimate them from
+    /// the transitions.
+    pub fn frequency(&self, token: &LyrianToken) -> usize {
+        match self.markov.states().binary_search(token) {
+            Ok(i) => self.markov.frequency(i),
+            Err(_) => 0,
+        }
+    }
+
+    /// Returns the `k` most frequent tokens and their frequencies, in
+    /// descending order of the frequencies.
+    ///
+    /// ```rust
+    /// use lyrian::model::LyrianModel;
+    ///
+    /// let model = LyrianModel::chars_from("すもももももももものうち");
+    /// let (token, frequency) = model.most_common(1)[0];
+    /// assert_eq!((token.word.as_str(), frequency), ("も", 8));
+    /// ```
+    pub fn most_common(&self, k: usize) -> Vec<(&LyrianToken, usize)> {
+        let mut frequencies = self
+            .markov
+            .states()
+            .iter()
+            .enumerate()
+            .map(|(i, t)| (t, self.markov.frequency(i)))
+            .collect::<Vec<(&LyrianToken, usize)>>();
+        frequencies.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        frequencies.truncate(k);
+        frequencies
+    }
+
+    /// Ranks the tokens by PageRank on the graph of the transitions, in
+    /// descending order of the importance, which sums up to `1.0`.
+    ///
+    /// The tokens which many important tokens move to are important, which
+    /// reflects what the corpus is about better than the frequencies of
+    /// [`most_common()`](#method.most_common). `damping` is the probability
+    /// to follow a transition rather than to jump to any token, usually
+    /// `0.85`, and is clamped between `0.0` and `1.0`. NaN is regarded as
+    /// `0.0`.
+    ///
+    /// ```rust
+    /// use lyrian::model::LyrianModel;
+    ///
+    /// let model = LyrianModel::chars_from("すもももももももものうち");
+    /// let (token, _) = model.importance(0.85)[0];
+    /// assert_eq!(token.word, "も");
+    /// assert_eq!(model.importance(f32::NAN).len(), 5);
+    /// ```
+    pub fn importance(&self, damping: f32) -> Vec<(&LyrianToken, f32)> {
+        let damping = if damping.is_nan() { 0.0 } else { damping };
+        let ranks = self.markov.pagerank(damping.clamp(0.0, 1.0) as f64);
+        let mut importance = self
+            .markov
+            .states()
+            .iter()
+            .zip(ranks)
+            .map(|(t, r)| (t, r as f32))
+            .collect::<Vec<(&LyrianToken, f32)>>();
+        importance.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        importance
+    }
+
+    /// Summarizes the size and the shape of the model, to check it after
+    /// training.
+    ///
+    /// ```rust
+    /// use lyrian::model::LyrianModel;
+    ///
+    /// let summary = LyrianModel::chars_from("すもももももももものうち").summary();
+    /// assert_eq!((summary.vocabulary, summary.transitions, summary.dead_ends), (5, 5, 1));
+    /// ```
+    pub fn summary(&self) -> ModelSummary {
+        let states = self.markov.states();
+        let heap_size = |t: &LyrianToken| {
+            t.word.capacity()
+                + t.mora.capacity()
+                + t.syllable.capacity()
+                + t.part_of_speech.capacity()
+        };
+        let fallback = match &self.fallback {
+            Some(fallback) => fallback.summary().memory,
+            None => 0,
+        };
+        ModelSummary {
+            vocabulary: states.len(),
+            order: 1,
+            transitions: self.markov.transition_count(),
+            dead_ends: (0..states.len())
+                .filter(|i| self.markov.successors(*i).is_empty())
+                .count(),
+            memory: self.markov.memory_size(heap_size) + fallback,
+        }
+    }
+
+    /// Returns the tokens which follow the token and their probabilities, in
+    /// descending order of the probabilities.
+    ///
+    /// Nothing is returned if the token is not in the model or is a dead end.
+    ///
+    /// ```rust
+    /// use lyrian::model::LyrianModel;
+    ///
+    /// let model = LyrianModel::chars_from("すもももももももものうち");
+    /// let mo = model.tokens().iter().find(|t| t.word == "も").unwrap();
+    /// let (next, probability) = model.successors(mo)[0];
+    /// assert_eq!((next.word.as_str(), probability), ("も", 0.875));
+    /// ```
+    pub fn successors(&self, token: &LyrianToken) -> Vec<(&LyrianToken, f64)> {
+        let states = self.markov.states();
+        let index = match states.binary_search(token) {
+            Ok(i) => i,
+            Err(_) => return Vec::new(),
+        };
+        let mut successors = self
+            .markov
+            .successors(index)
+            .into_iter()
+            .map(|(i, p)| (&states[i], p))
+            .collect::<Vec<(&LyrianToken, f64)>>();
+        successors.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap().then_with(|| a.0.cmp(b.0)));
+        successors
+    }
+
+    /// Returns the expected number of the tokens which follow each token
+    /// until the sentence ends, in the order of [`tokens()`](#method.tokens).
+    ///
+    /// The sentences end with the tokens which end with a terminator such
+    /// as `。` or have no next tokens, as in
+    /// [`generate_sentence()`](#method.generate_sentence) without
+    /// `max_tokens`. [`None`] is returned for the tokens after which the
+    /// sentences may never end.
+    ///
+    /// ```rust
+    /// use lyrian::model::LyrianModel;
+    ///
+    /// let model = LyrianModel::chars_from("すもももももももものうち");
+    /// let steps = model.expected_steps_to_end();
+    /// let (_, to_end) = steps.iter().find(|(t, _)| t.word == "も").unwrap();
+    /// assert!((to_end.unwrap() - 10.0).abs() < 1e-9);
+    /// ```
+    pub fn expected_steps_to_end(&self) -> Vec<(&LyrianToken, Option<f64>)> {
+        let times = self.markov.hitting_times(|i, t| {
+            t.word.ends_with(TERMINATORS) || self.markov.successors(i).is_empty()
+        });
+        self.markov.states().iter().zip(times).collect()
+    }
+
+    /// Returns the expected number of the tokens which follow each token
+    /// until the target token, in the order of [`tokens()`](#method.tokens).
+    ///
+    /// The chain is followed across the ends of the sentences. [`None`] is
+    /// returned for the tokens after which the target may never appear, and
+    /// nothing is returned if the target is not in the model.
+    pub fn expected_steps_to(&self, target: &LyrianToken) -> Vec<(&LyrianToken, Option<f64>)> {
+        if self.markov.states().binary_search(target).is_err() {
+            return Vec::new();
+        }
+        let times = self.markov.hitting_times(|_, t| t == target);
+        self.markov.states().iter().zip(times).collect()
+    }
+
+    /// Calculates the probability that the tokens follow the first one in
+    /// order, which is the product of the probabilities of the transitions.
+    ///
+    /// Use this to check whether a poem written by hand could be generated
+    /// by the model. [`None`] is returned if a token is not in the model or
+    /// can never follow the previous one.
+    ///
+    /// ```rust
+    /// use lyrian::model::LyrianModel;
+    ///
+    /// let model = LyrianModel::chars_from("すもももももももものうち");
+    /// let tokens = |text: &str| {
+    ///     text.chars()
+    ///         .map(|c| model.tokens().iter().find(|t| t.word == c.to_string()).unwrap().clone())
+    ///         .collect::<Vec<_>>()
+    /// };
+    /// assert_eq!(model.path_probability(&tokens("ものうち")), Some(0.125));
+    /// assert_eq!(model.path_probability(&tokens("すの")), None);
+    /// ```
+    pub fn path_probability(&self, tokens: &[LyrianToken]) -> Option<f64> {
+        self.markov.path_probability(tokens)
+    }
+
+    /// Maps each token to its most probable next token, which outlines the
+    /// backbone of the model. The tokens without next tokens are not mapped.
+    ///
+    /// Following the map from a token gives the canonical sentence from it.
+    ///
+    /// ```rust
+    /// use lyrian::model::LyrianModel;
+    ///
+    /// let model = LyrianModel::chars_from("すもももももももものうち");
+    /// let map = model.to_deterministic();
+    /// let su = model.tokens().iter().find(|t| t.word == "す").unwrap();
+    /// assert_eq!(map[su].word, "も");
+    /// assert_eq!(map[&map[su]].word, "も");
+    /// ```
+    pub fn to_deterministic(&self) -> BTreeMap<LyrianToken, LyrianToken> {
+        let states = self.markov.states();
+        (0..states.len())
+            .filter_map(|i| {
+                self.markov
+                    .most_probable(i)
+                    .map(|j| (states[i].clone(), states[j].clone()))
+            })
+            .collect()
+    }
+
+    /// Exports the transitions of the model as a weighted acceptor in the
+    /// AT&T text format of OpenFST, to be compiled by `fstcompile` and
+    /// composed with other transducers such as lexica.
+    ///
+    /// The label of each token is its index in [`tokens()`](#method.tokens)
+    /// plus one, and the symbols are given by
+    /// [`fst_symbols()`](#method.fst_symbols). The weights are the negative
+    /// natural logarithms of the probabilities in the tropical semiring.
+    /// The acceptor starts from any token uniformly, and ends at the tokens
+    /// which end with a terminator such as `。` or have no next tokens.
+    ///
+    /// ```rust
+    /// use lyrian::model::LyrianModel;
+    ///
+    /// let model = LyrianModel::chars_from("あい。");
+    /// let fst = model.to_fst_str();
+    /// assert!(fst.lines().any(|l| l == "2\t3\t3\t3\t0"));
+    /// assert!(fst.lines().any(|l| l == "1\t0"));
+    /// ```
+    pub fn to_fst_str(&self) -> String {
+        self.markov
+            .to_fst(|i, t| t.word.ends_with(TERMINATORS) || self.markov.successors(i).is_empty())
+    }
+
+    /// Returns the symbol table of the labels of
+    /// [`to_fst_str()`](#method.to_fst_str), where `<eps>` is `0`.
+    ///
+    /// The symbols are the words of the tokens, whose whitespace is replaced
+    /// with `_`. The part of speech is appended after `/` to the words of
+    /// more than one token, and the readings too if the part of speech is
+    /// also shared, so that the symbols are unique.
+    ///
+    /// ```rust
+    /// use lyrian::model::LyrianModel;
+    ///
+    /// let model = LyrianModel::chars_from("あい。");
+    /// assert_eq!(model.fst_symbols(), "<eps>\t0\n。\t1\nあ\t2\nい\t3\n");
+    /// ```
+    pub fn fst_symbols(&self) -> String {
+        let states = self.markov.states();
+        let mut groups = BTreeMap::<String, Vec<usize>>::new();
+        for (i, token) in states.iter().enumerate() {
+            let word = token.word.replace(char::is_whitespace, "_");
+            groups.entry(word).or_default().push(i);
+        }
+
+        let mut names = vec![String::new(); states.len()];
+        for (word, group) in groups {
+            let name = |i: usize, detail: usize| {
+                let t = &states[i];
+                [&t.part_of_speech, &t.mora, &t.syllable][..detail]
+                    .iter()
+                    .fold(word.clone(), |name, field| format!("{}/{}", name, field))
+                    .replace(char::is_whitespace, "_")
+            };
+            for &i in &group {
+                let unique = |detail: usize| {
+                    group
+                        .iter()
+                        .all(|&j| j == i || name(j, detail) != name(i, detail))
+                };
+                names[i] = match (0..=3).find(|d| unique(*d)) {
+                    Some(detail) => name(i, detail),
+                    None => format!("{}/{}", name(i, 3), i + 1),
+                };
+            }
+        }
+
+        let mut symbols = String::from("<eps>\t0\n");
+        for (i, name) in names.iter().enumerate() {
+            symbols.push_str(&format!("{}\t{}\n", name, i + 1));
+        }
+        symbols
+    }
+
+    /// Returns the number of morae of the token in the model.
+    ///
+    /// The morae are counted from the katakana reading stored with the
+    /// token, so the tokens which contain kanji are also counted. The tokens
+    /// whose readings are unknown are counted by their surface forms if they
+    /// are written in kana. [`None`] is returned if the token is not in the
+    /// model or can not be counted.
+    ///
+    /// ```rust
+    /// use lyrian::model::LyrianModel;
+    ///
+    /// let model = LyrianModel::from_text("古池や").unwrap();
+    /// let furuike = model.tokens().iter().find(|t| t.word == "古池").unwrap();
+    /// assert_eq!(model.token_morae(furuike), Some(4));
+    /// ```
+    pub fn token_morae(&self, token: &LyrianToken) -> Option<usize> {
+        self.markov.states().binary_search(token).ok()?;
+        if token.mora == "unknown" {
+            try_count_morae(&token.word).ok()
+        } else {
+            Some(count_morae(&token.mora))
+        }
+    }
+
+    /// Returns the next token of the chain, whose transition weights are
+    /// multiplied by the factors of `bias`, which is given the indices of the
+    /// tokens and the tokens.
+    ///
+    /// [`None`] is returned if all the next tokens have the factor `0.0`.
+    pub(crate) fn next_token_by<F: FnMut(usize, &LyrianToken) -> f64>(
+        &mut self,
+        bias: F,
+    ) -> Option<LyrianToken> {
+        self.markov.next_by(bias).cloned()
+    }
+
+    /// Chooses any token in proportion to its frequency multiplied by the
+    /// factor of `weight`, and continues the chain from it.
+    ///
+    /// [`None`] is returned if all the tokens have the factor `0.0`.
+    pub(crate) fn sample_token_by<F: FnMut(usize, &LyrianToken) -> f64>(
+        &mut self,
+        weight: F,
+    ) -> Option<LyrianToken> {
+        self.markov.sample_by(weight).cloned()
+    }
+
+    /// Replaces the transitions of the tokens never seen in the model with
+    /// those of the global model of the same tokens.
+    pub(crate) fn back_off_to(&mut self, global: &LyrianModel) -> bool {
+        self.markov.back_off_to(&global.markov)
+    }
+
+    /// Returns the index of the token chosen last.
+    pub(crate) fn current_index(&self) -> Option<usize> {
+        self.markov.current()
+    }
+
+    /// Computes which numbers of morae up to `max` can follow each token.
+    ///
+    /// The table is indexed by the indices of the tokens and the numbers of
+    /// morae counted by the counter.
+    pub(crate) fn reachable_morae<C: MoraCounter + ?Sized>(
+        &self,
+        counter: &C,
+        max: usize,
+    ) -> Vec<Vec<bool>> {
+        let lengths = self
+            .markov
+            .states()
+            .iter()
+            .map(|t| counter.count_token(t))
+            .collect::<Vec<usize>>();
+        self.markov.reachable(&lengths, max)
+    }
+
+    /// Continues the chain from the token, so that the next token follows it.
+    ///
+    /// `false` is returned if the token is not in the model.
+    pub(crate) fn follow(&mut self, token: &LyrianToken) -> bool {
+        self.markov.set_current(token)
+    }
+
+    /// Returns the probability that the token `to` follows the token `from`.
+    pub(crate) fn probability(&self, from: &LyrianToken, to: &LyrianToken) -> f64 {
+        self.markov.probability(from, to)
+    }
+
+    /// Calculates the natural log-likelihood of the tokens in the model.
+    ///
+    /// [`None`] is returned if a token is not in the model.
+    pub(crate) fn log_likelihood(&self, tokens: &[LyrianToken]) -> Option<f64> {
+        self.markov.log_likelihood(tokens)
+    }
+
+    /// Initializes the chain of tokens.
+    pub(crate) fn initialize(&mut self) {
+        self.markov.initialize();
+    }
+
+    /// Gets a first token of a sentence, which is neither a particle, an
+    /// auxiliary verb nor a symbol.
+    pub(crate) fn get_starting_token(&mut self) -> Option<LyrianToken> {
+        let starts = |_: usize, t: &LyrianToken| match t.part_of_speech.as_str() {
+            "助詞" | "助動詞" | "記号" => 0.0,
+            _ => 1.0,
+        };
+        for _ in 0..64 {
+            self.markov.initialize();
+            if let Some(token) = self.markov.next_by(starts) {
+                return Some(token.clone());
             }
         }
-        token
+        None
+    }
+
+    /// Gets a first token that is neither a particle nor an auxiliary verb,
+    /// following the current token, or like
+    /// [`get_starting_token()`](#method.get_starting_token) if none follows
+    /// it.
+    pub(crate) fn get_first_token(&mut self) -> Option<LyrianToken> {
+        let first = |_: usize, t: &LyrianToken| match t.part_of_speech.as_str() {
+            "助詞" | "助動詞" => 0.0,
+            _ => 1.0,
+        };
+        match self.markov.next_by(first) {
+            Some(token) => Some(token.clone()),
+            None => self.get_starting_token(),
+        }
     }
 }
 
-#[cfg(test)]
+/// Wrapper of an observer which may be unsized, to pass it as a trait
+/// object.
+struct Observe<'a, O: ?Sized>(&'a mut O);
+
+impl<O: TokenObserver + ?Sized> TokenObserver for Observe<'_, O> {
+    fn on_token(&mut self, token: &LyrianToken, probability: f64, step: usize) -> bool {
+        self.0.on_token(token, probability, step)
+    }
+}
+
+/// Converts a mora into a token of [`LyrianModel::from_morae()`].
+fn mora_token(mora: &str) -> LyrianToken {
+    let reading = mora.chars().map(hiragana_to_katakana).collect::<String>();
+    LyrianToken::new(
+        mora.to_string(),
+        reading.clone(),
+        reading,
+        "文字".to_string(),
+    )
+}
+
+#[cfg(all(test, feature = "tokenizer"))]
 mod model_test {
     use crate::model::LyrianModel;
 
+    #[test]
+    fn name_fst_symbols_uniquely() {
+        use crate::morphological_analysis::LyrianToken;
+
+        let token = |reading: &str, pos: &str| {
+            LyrianToken::new("今日".into(), reading.into(), reading.into(), pos.into())
+        };
+        let model = LyrianModel::from_sequences(&[vec![
+            token("キョウ", "名詞"),
+            token("コンニチ", "名詞"),
+            token("キョウ", "副詞"),
+        ]]);
+        assert_eq!(
+            model.fst_symbols(),
+            "<eps>\t0\n今日/副詞\t1\n今日/名詞/キョウ\t2\n今日/名詞/コンニチ\t3\n"
+        )
+    }
+
     #[test]
     fn build_same_model_from_json() {
         let str_model = LyrianModel::from_str("もも").unwrap();
@@ -119,4 +1572,274 @@ mod model_test {
 
         assert_eq!((lyric_1.length(false), lyric_2.length(true)), (5, 5));
     }
+
+    #[test]
+    fn count_morae_of_tokens_by_readings() {
+        use crate::morphological_analysis::LyrianToken;
+
+        let model = LyrianModel::from_str("古池や").unwrap();
+        let morae = model
+            .tokens()
+            .iter()
+            .map(|t| model.token_morae(t))
+            .collect::<Option<Vec<usize>>>();
+        let absent = LyrianToken::new(
+            "蛙".to_string(),
+            "カワズ".to_string(),
+            "カワズ".to_string(),
+            "名詞".to_string(),
+        );
+        assert_eq!(
+            (
+                morae.map(|m| m.iter().sum::<usize>()),
+                model.token_morae(&absent)
+            ),
+            (Some(5), None)
+        )
+    }
+
+    #[test]
+    fn stream_lines_into_same_model() {
+        use crate::morphological_analysis::LyrianToken;
+
+        let chars = |text: &str| -> Result<Vec<LyrianToken>, String> {
+            Ok(text
+                .chars()
+                .map(|c| {
+                    let c = c.to_string();
+                    LyrianToken::new(c.clone(), c.clone(), c, "名詞".to_string())
+                })
+                .collect())
+        };
+        let text = "アイ。ウ\nエオ";
+        assert_eq!(
+            LyrianModel::from_reader(text.as_bytes(), &chars),
+            LyrianModel::from_text_with(text, &chars)
+        )
+    }
+
+    #[test]
+    fn generate_sentence_of_characters() {
+        let mut model = LyrianModel::chars_from("あいう。\nかきく");
+        let sentence = model.generate_sentence(10);
+        assert!(["あいう。", "いう。", "う。", "かきく", "きく", "く"].contains(&sentence.as_str()))
+    }
+
+    #[test]
+    fn count_one_mora_for_each_token() {
+        let model = LyrianModel::from_morae("しんじゅくの、ちょっと　きょうかい。");
+        assert!(model
+            .tokens()
+            .iter()
+            .all(|t| model.token_morae(t) == Some(1)))
+    }
+
+    #[test]
+    fn summarize_model() {
+        use crate::model::ModelSummary;
+
+        let model = LyrianModel::chars_from("あいう。\nかきく");
+        let summary = model.summary();
+        assert_eq!(
+            ModelSummary {
+                memory: 0,
+                ..summary
+            },
+            ModelSummary {
+                vocabulary: 7,
+                order: 1,
+                transitions: 5,
+                dead_ends: 2,
+                memory: 0
+            }
+        )
+    }
+
+    #[test]
+    fn save_smoothing_in_json() {
+        use crate::model::Smoothing;
+
+        let model =
+            LyrianModel::chars_from("あいう。いいえ。うえ。").with_smoothing(Smoothing::GoodTuring);
+        let json = LyrianModel::from_json(&model.to_json_str().unwrap()).unwrap();
+        assert_eq!(json, model)
+    }
+
+    #[test]
+    fn detect_tokens_never_ending() {
+        let model = LyrianModel::chars_from("あいあい\nうえ。");
+        let steps = model
+            .expected_steps_to_end()
+            .into_iter()
+            .map(|(t, s)| (t.word.as_str(), s))
+            .collect::<Vec<(&str, Option<f64>)>>();
+        assert_eq!(
+            steps,
+            vec![
+                ("。", Some(0.0)),
+                ("あ", None),
+                ("い", None),
+                ("う", Some(2.0)),
+                ("え", Some(1.0))
+            ]
+        )
+    }
+
+    #[test]
+    fn restart_sentences_repeating_cycles() {
+        use crate::model::{CycleAction, CycleGuard};
+
+        let guard = CycleGuard::new(2, 1).with_action(CycleAction::Restart);
+        let mut model = LyrianModel::chars_from("あいあいう。").with_cycle_guard(guard);
+        let sentences = (0..10)
+            .map(|_| model.generate_from("あ", 100).unwrap())
+            .collect::<Vec<String>>();
+        assert!(sentences.iter().all(|s| s == "あいう。"))
+    }
+
+    #[test]
+    fn avoid_repeating_tokens() {
+        let mut model = LyrianModel::chars_from("あいあいあいう。")
+            .with_no_repeat(true)
+            .with_no_repeat_previous(true);
+        let sentences = (0..10)
+            .map(|_| model.generate_from("あ", 100).unwrap())
+            .collect::<Vec<String>>();
+        assert!(sentences.iter().all(|s| s == "あいう。"))
+    }
+
+    #[test]
+    fn use_tokens_at_most_once() {
+        let mut model = LyrianModel::chars_from("あいうあえ。").with_unique_tokens(true);
+        let sentences = (0..10)
+            .map(|_| model.generate_from("あ", 100).unwrap())
+            .collect::<Vec<String>>();
+        assert!(sentences.iter().all(|s| s == "あいう" || s == "あえ。"))
+    }
+
+    #[test]
+    fn generate_sentences_with_constraints() {
+        use crate::model::Constraint;
+
+        let mut model = LyrianModel::chars_from("あいう。かいえ。");
+        let at = |k: usize, w: &str| Constraint::At(k, w.to_string());
+        let satisfied = model.generate_constrained(
+            &[
+                at(0, "か"),
+                at(1, "い"),
+                Constraint::Contains("え".to_string()),
+            ],
+            10,
+            100,
+        );
+        let impossible = model.generate_constrained(&[at(2, "か")], 10, 10);
+        assert_eq!(
+            (satisfied, impossible.is_err()),
+            (Ok("かいえ。".to_string()), true)
+        )
+    }
+
+    #[test]
+    fn generate_sentences_backwards() {
+        let mut model = LyrianModel::chars_from("あいう。かいえ。")
+            .with_char_fallback("おあい。")
+            .reverse();
+        let json = LyrianModel::from_json(&model.to_json_str().unwrap()).unwrap();
+        let sentence = model.generate_from("え", 10).unwrap();
+        assert!(
+            (sentence == "あいえ" || sentence == "かいえ")
+                && model.generate_from("おあ", 10) == Ok("おあ".to_string())
+                && json.is_reversed()
+        )
+    }
+
+    #[test]
+    fn grow_sentences_in_both_directions() {
+        let mut model = LyrianModel::chars_from("あいう。かいえ。").reverse();
+        let sentence = model.generate_around("い", 10, 10).unwrap();
+        assert!(
+            ["あいう。", "あいえ。", "かいう。", "かいえ。"].contains(&sentence.as_str())
+                && model.generate_around("い", 0, 0) == Ok("い".to_string())
+                && model.generate_around("き", 1, 1).is_err()
+        )
+    }
+
+    #[test]
+    fn observe_tokens_in_generation() {
+        use crate::morphological_analysis::LyrianToken;
+
+        let mut model = LyrianModel::chars_from("ふるいけや。");
+        let mut observed = Vec::new();
+        let sentence = model.generate_sentence_observed(10, &mut |t: &LyrianToken, p, step| {
+            observed.push((t.word.clone(), p, step));
+            t.word != "け"
+        });
+        let words = observed
+            .iter()
+            .map(|(w, _, _)| w.as_str())
+            .collect::<String>();
+        assert!(
+            sentence == words
+                && (!sentence.contains('け') || sentence.ends_with('け'))
+                && observed
+                    .iter()
+                    .enumerate()
+                    .all(|(i, (_, p, step))| *p == 1.0 && *step == i)
+        )
+    }
+
+    #[test]
+    fn branch_generation_by_rng_state() {
+        let mut model = LyrianModel::chars_from("あいう。いあう。うあい。")
+            .with_char_fallback("あいうえお。")
+            .with_seed(7);
+        model.generate_sentence(10);
+        let state = model.rng_state();
+        let mut branch = || {
+            model.set_rng_state(&state);
+            (0..3)
+                .map(|_| model.generate_from("え", 10).unwrap())
+                .collect::<Vec<String>>()
+        };
+        let first = branch();
+        let unseeded = LyrianModel::chars_from("あいう。").rng_state();
+        assert_eq!(
+            (
+                branch(),
+                unseeded == LyrianModel::chars_from("あ。").rng_state()
+            ),
+            (first, true)
+        )
+    }
+
+    #[test]
+    fn save_prior_in_json() {
+        let model = LyrianModel::chars_from("あいう。いいえ。うえ。").with_prior(0.5);
+        let json = LyrianModel::from_json(&model.to_json_str().unwrap()).unwrap();
+        assert_eq!(json, model)
+    }
+
+    #[test]
+    fn fall_back_to_characters_out_of_vocabulary() {
+        use crate::morphological_analysis::LyrianToken;
+
+        let noun = |w: &str| LyrianToken::new(w.into(), w.into(), w.into(), "名詞".into());
+        let mut model = LyrianModel::from_sequences(&[vec![noun("古池"), noun("ヤ")]]);
+        let oov = model.generate_from("カエ", 10);
+        let mut model = model.with_char_fallback("かえるとびこむ");
+        assert_eq!(
+            (
+                model.generate_from("古池", 10),
+                oov.is_err(),
+                model.generate_from("かえ", 10),
+                serde_json::to_string(&model).unwrap().contains("fallback")
+            ),
+            (
+                Ok("古池ヤ".to_string()),
+                true,
+                Ok("かえるとびこむ".to_string()),
+                false
+            )
+        )
+    }
 }
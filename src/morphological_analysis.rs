@@ -1,9 +1,8 @@
 //! Morphological analysis and the tokens generated by it.
 
-use lindera::tokenizer::Tokenizer;
 use serde::{Deserialize, Serialize};
 
-use crate::chars::*;
+use crate::pronunciation::CountingRules;
 
 /// Token structure
 #[derive(Debug, Serialize, Deserialize, Clone, Eq, Ord, PartialOrd, PartialEq)]
@@ -40,24 +39,22 @@ impl LyrianToken {
     /// If you set `false` to all the arguments, you will get the number by
     /// mora unit.
     pub fn length(&self, syllable: bool) -> usize {
-        if self.mora == String::from("unknown") {
-            return 0;
-        }
-
-        let mut sound_len = self.mora.chars().count();
-
-        sound_len -= dup_num(
-            &self.mora.chars().collect(),
-            &vec![LOWER_CASE.to_vec(), SYMBOLS.to_vec()].concat(),
-        );
+        self.length_with(&CountingRules::default(), syllable)
+    }
 
-        if syllable {
-            sound_len = self.syllable_len();
+    /// Calculates the number of pronunciation with the given rules.
+    ///
+    /// See [`length()`](#method.length) for the arguments.
+    pub fn length_with(&self, rules: &CountingRules, syllable: bool) -> usize {
+        if self.mora == "unknown" {
+            return 0;
         }
 
-        // if voiceless {
-        //     sound_len -= self.count_voiceless();
-        // }
+        let sound_len = if syllable {
+            rules.count(&self.syllable, true)
+        } else {
+            rules.count(&self.mora, false)
+        };
 
         // if smooth {
         //     sound_len -= self.count_smooth();
@@ -66,122 +63,33 @@ impl LyrianToken {
         sound_len
     }
 
-    /// Returns the length of the word by syllable unit.
-    fn syllable_len(&self) -> usize {
-        let length = self.syllable.chars().count();
-        let count = dup_num(&self.syllable.chars().collect(), &SYLLABLE_CHARS.to_vec());
-        length - count
-    }
-
-    // fn count_voiceless(&self) -> usize {
-    //     // TODO: Processing to calc number of voiceless sound
-    //     0
-    // }
-
     // fn count_smooth(&self) -> usize {
     //     // TODO: Processing to calc number of smooth vowel sound
     //     0
     // }
 }
 
-/// Tokenizes contents in morphological analysis.
-///
-/// Lyrian uses [lindera](https://github.com/lindera-morphology/lindera) crate
-/// for morphological analysis.
-pub fn tokenize(contents: &str) -> Result<Vec<LyrianToken>, String> {
-    let mut tokenizer;
-    let lin_tokens;
-
-    match Tokenizer::new() {
-        Ok(v) => tokenizer = v,
-        Err(e) => return Err(e.to_string()),
-    }
-
-    match tokenizer.tokenize(&*contents) {
-        Ok(v) => lin_tokens = v,
-        Err(e) => return Err(e.to_string()),
-    }
-
-    let mut lyr_tokens = Vec::with_capacity(lin_tokens.len());
-    for token in lin_tokens {
-        if token.detail.len() != 1 {
-            lyr_tokens.push(LyrianToken::new(
-                token.text.to_string(),
-                token.detail[7].to_string(),
-                token.detail[8].to_string(),
-                token.detail[0].to_string(),
-            ));
-        } else {
-            lyr_tokens.push(LyrianToken::new(
-                token.text.to_string(),
-                String::from("unknown"),
-                String::from("unknown"),
-                String::from("unknown"),
-            ));
-        }
-    }
+/// Creates a token of the part of speech whose readings are the word itself,
+/// for the tests.
+#[cfg(test)]
+pub(crate) fn token(word: &str, pos: &str) -> LyrianToken {
+    LyrianToken::new(
+        word.to_string(),
+        word.to_string(),
+        word.to_string(),
+        pos.to_string(),
+    )
+}
 
-    Ok(lyr_tokens)
+/// Creates a noun by [`token()`], for the tests.
+#[cfg(test)]
+pub(crate) fn noun(word: &str) -> LyrianToken {
+    token(word, "名詞")
 }
 
 #[cfg(test)]
 mod morphological_analysis_test {
-    use crate::morphological_analysis::{tokenize, LyrianToken};
-
-    #[test]
-    fn get_lyrian_tokens_from_text() {
-        let text = "すもももももももものうち";
-        match tokenize(text) {
-            Ok(tokens) => {
-                let expected = vec![
-                    LyrianToken::new(
-                        "すもも".to_string(),
-                        "スモモ".to_string(),
-                        "スモモ".to_string(),
-                        "名詞".to_string(),
-                    ),
-                    LyrianToken::new(
-                        "も".to_string(),
-                        "モ".to_string(),
-                        "モ".to_string(),
-                        "助詞".to_string(),
-                    ),
-                    LyrianToken::new(
-                        "もも".to_string(),
-                        "モモ".to_string(),
-                        "モモ".to_string(),
-                        "名詞".to_string(),
-                    ),
-                    LyrianToken::new(
-                        "も".to_string(),
-                        "モ".to_string(),
-                        "モ".to_string(),
-                        "助詞".to_string(),
-                    ),
-                    LyrianToken::new(
-                        "もも".to_string(),
-                        "モモ".to_string(),
-                        "モモ".to_string(),
-                        "名詞".to_string(),
-                    ),
-                    LyrianToken::new(
-                        "の".to_string(),
-                        "ノ".to_string(),
-                        "ノ".to_string(),
-                        "助詞".to_string(),
-                    ),
-                    LyrianToken::new(
-                        "うち".to_string(),
-                        "ウチ".to_string(),
-                        "ウチ".to_string(),
-                        "名詞".to_string(),
-                    ),
-                ];
-                assert_eq!(tokens, expected)
-            }
-            Err(msg) => panic!("{}", msg),
-        }
-    }
+    use crate::morphological_analysis::LyrianToken;
 
     #[test]
     fn get_word_length_on_mora() {
@@ -216,6 +124,17 @@ mod morphological_analysis_test {
         assert_eq!(token.length(false), 4)
     }
 
+    #[test]
+    fn get_syllable_length_of_word_that_has_lower_case() {
+        let token = LyrianToken::new(
+            "ジョバンニ".to_string(),
+            "ジョバンニ".to_string(),
+            "ジョバンニ".to_string(),
+            "名詞".to_string(),
+        );
+        assert_eq!(token.length(true), 3)
+    }
+
     #[test]
     fn get_symbol_length() {
         let token = LyrianToken::new(
@@ -227,11 +146,22 @@ mod morphological_analysis_test {
         assert_eq!(token.length(false), 0)
     }
 
-    // #[test]
-    // fn get_length_of_word_that_has_voiceless_sound() {
-    //     let token = LyrianToken::new("桜".to_string(), "サクラ".to_string(), "サクラ".to_string());
-    //     assert_eq!(token.length(false, true, false), 2)
-    // }
+    #[test]
+    fn get_length_of_word_that_has_voiceless_sound() {
+        use crate::pronunciation::CountingRules;
+
+        let token = LyrianToken::new(
+            "です".to_string(),
+            "デス".to_string(),
+            "デス".to_string(),
+            "助動詞".to_string(),
+        );
+        let rules = CountingRules {
+            devoicing: true,
+            ..CountingRules::default()
+        };
+        assert_eq!(token.length_with(&rules, false), 1)
+    }
 
     // #[test]
     // fn get_length_of_word_that_has_smooth_vowel_sound() {
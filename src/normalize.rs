@@ -0,0 +1,204 @@
+//! Normalization of the text before counting and tokenizing.
+
+use unicode_normalization::UnicodeNormalization;
+
+use crate::chars::{semi_voice, voice};
+
+/// Full-width forms of the half-width katakana from `U+FF61` to `U+FF9F`.
+const HALFWIDTH_KANA: [char; 63] = [
+    '。', '「', '」', '、', '・', 'ヲ', 'ァ', 'ィ', 'ゥ', 'ェ', 'ォ', 'ャ', 'ュ', 'ョ', 'ッ', 'ー',
+    'ア', 'イ', 'ウ', 'エ', 'オ', 'カ', 'キ', 'ク', 'ケ', 'コ', 'サ', 'シ', 'ス', 'セ', 'ソ', 'タ',
+    'チ', 'ツ', 'テ', 'ト', 'ナ', 'ニ', 'ヌ', 'ネ', 'ノ', 'ハ', 'ヒ', 'フ', 'ヘ', 'ホ', 'マ', 'ミ',
+    'ム', 'メ', 'モ', 'ヤ', 'ユ', 'ヨ', 'ラ', 'リ', 'ル', 'レ', 'ロ', 'ワ', 'ン', '゛', '゜',
+];
+
+/// Folds half-width katakana into full-width katakana.
+///
+/// The voiced sound marks which follow a kana, namely the half-width ones
+/// (`ﾞ`, `ﾟ`) and the combining ones (`U+3099`, `U+309A`), are composed into
+/// the previous character. For example, `ｶﾞｯｺｳ` becomes `ガッコウ`.
+pub fn fold_halfwidth_kana(text: &str) -> String {
+    let mut folded = String::with_capacity(text.len());
+    for c in text.chars() {
+        push_folded(&mut folded, c);
+    }
+    folded
+}
+
+/// Folds half-width katakana like [`fold_halfwidth_kana()`], with the byte
+/// offsets in the text of the characters at the byte offsets of the folded
+/// text, and the length of the text at the end.
+pub(crate) fn fold_halfwidth_kana_with_offsets(text: &str) -> (String, Vec<usize>) {
+    let mut folded = String::with_capacity(text.len());
+    let mut offsets = Vec::with_capacity(text.len() + 1);
+    for (i, c) in text.char_indices() {
+        push_folded(&mut folded, c);
+        // A voiced sound mark composed into the previous character keeps
+        // its offset.
+        offsets.truncate(folded.len());
+        offsets.resize(folded.len(), i);
+    }
+    offsets.push(text.len());
+    (folded, offsets)
+}
+
+/// Pushes the character folded, composing the voiced sound mark into the
+/// previous character.
+fn push_folded(folded: &mut String, c: char) {
+    let composed = match c {
+        '\u{FF9E}' | '\u{3099}' => folded.pop().map(|prev| voice(prev).ok_or(prev)),
+        '\u{FF9F}' | '\u{309A}' => folded.pop().map(|prev| semi_voice(prev).ok_or(prev)),
+        _ => None,
+    };
+
+    match composed {
+        Some(Ok(v)) => folded.push(v),
+        Some(Err(prev)) => {
+            folded.push(prev);
+            folded.push(to_fullwidth(c));
+        }
+        None => folded.push(to_fullwidth(c)),
+    }
+}
+
+/// Normalizes the text in NFKC with some fixes for kana.
+///
+/// Full-width ASCII, compatibility characters and decomposed voiced sound
+/// marks are unified, e.g. `ＡＢＣ` becomes `ABC` and `か\u{3099}` becomes `が`.
+///
+/// Unlike plain NFKC, the spacing voiced sound marks (`゛`, `゜`) are kept as
+/// they are, and the combining ones which can not be composed are converted
+/// into the spacing ones.
+pub fn nfkc(text: &str) -> String {
+    let mut normalized = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(i) = rest.find(['゛', '゜']) {
+        normalized.extend(rest[..i].nfkc());
+        normalized.push_str(&rest[i..i + '゛'.len_utf8()]);
+        rest = &rest[i + '゛'.len_utf8()..];
+    }
+    normalized.extend(rest.nfkc());
+
+    normalized
+        .chars()
+        .map(|c| match c {
+            '\u{3099}' => '゛',
+            '\u{309A}' => '゜',
+            _ => c,
+        })
+        .collect()
+}
+
+/// Converts full-width ASCII into half-width ASCII.
+///
+/// The ideographic space (`U+3000`) becomes a space. Unlike [`nfkc()`], the
+/// other characters are left as they are.
+///
+/// ```rust
+/// use lyrian::normalize::to_halfwidth_ascii;
+///
+/// assert_eq!(to_halfwidth_ascii("ＡＢＣ１２３！"), "ABC123!");
+/// ```
+pub fn to_halfwidth_ascii(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '\u{3000}' => ' ',
+            '\u{FF01}'..='\u{FF5E}' => std::char::from_u32(c as u32 - 0xFEE0).unwrap_or(c),
+            _ => c,
+        })
+        .collect()
+}
+
+/// Converts half-width ASCII into full-width ASCII.
+///
+/// A space becomes the ideographic space (`U+3000`), and the control
+/// characters are left as they are.
+///
+/// ```rust
+/// use lyrian::normalize::to_fullwidth_ascii;
+///
+/// assert_eq!(to_fullwidth_ascii("ABC123!"), "ＡＢＣ１２３！");
+/// ```
+pub fn to_fullwidth_ascii(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            ' ' => '\u{3000}',
+            '!'..='~' => std::char::from_u32(c as u32 + 0xFEE0).unwrap_or(c),
+            _ => c,
+        })
+        .collect()
+}
+
+/// Returns the full-width form of a half-width katakana.
+fn to_fullwidth(c: char) -> char {
+    match c {
+        '\u{FF61}'..='\u{FF9F}' => HALFWIDTH_KANA[c as usize - 0xFF61],
+        _ => c,
+    }
+}
+
+#[cfg(test)]
+mod normalize_test {
+    use crate::normalize::{
+        fold_halfwidth_kana, fold_halfwidth_kana_with_offsets, nfkc, to_fullwidth_ascii,
+        to_halfwidth_ascii,
+    };
+
+    #[test]
+    fn fold_halfwidth_katakana() {
+        assert_eq!(fold_halfwidth_kana("ｼﾞｮﾊﾞﾝﾆ｡"), "ジョバンニ。".to_string())
+    }
+
+    #[test]
+    fn fold_semi_voiced_halfwidth_katakana() {
+        assert_eq!(fold_halfwidth_kana("ﾊﾟﾝﾌﾟｷﾝ"), "パンプキン".to_string())
+    }
+
+    #[test]
+    fn map_offsets_of_folded_kana() {
+        let (folded, offsets) = fold_halfwidth_kana_with_offsets("ｶﾞaｷ");
+        assert_eq!(
+            (folded.as_str(), offsets),
+            ("ガaキ", vec![0, 0, 0, 6, 7, 7, 7, 10])
+        )
+    }
+
+    #[test]
+    fn compose_combining_voiced_marks() {
+        assert_eq!(
+            fold_halfwidth_kana("か\u{3099}ラス\u{309A}"),
+            "がラス\u{309A}".to_string()
+        )
+    }
+
+    #[test]
+    fn normalize_fullwidth_ascii_in_nfkc() {
+        assert_eq!(nfkc("ＡＢＣ１２３！"), "ABC123!".to_string())
+    }
+
+    #[test]
+    fn compose_kana_in_nfkc() {
+        assert_eq!(nfkc("ｶﾞｯｺｳ と か\u{3099}"), "ガッコウ と が".to_string())
+    }
+
+    #[test]
+    fn keep_spacing_voiced_marks_in_nfkc() {
+        assert_eq!(nfkc("゛ﾞ\u{309A}"), "゛゛゜".to_string())
+    }
+
+    #[test]
+    fn convert_fullwidth_ascii_into_halfwidth() {
+        assert_eq!(
+            to_halfwidth_ascii("ＬＹＲＩＡＮ　ｖｅｒ．１ のうた"),
+            "LYRIAN ver.1 のうた".to_string()
+        )
+    }
+
+    #[test]
+    fn convert_halfwidth_ascii_into_fullwidth() {
+        assert_eq!(
+            to_fullwidth_ascii("No.1 ソング\n"),
+            "Ｎｏ．１　ソング\n".to_string()
+        )
+    }
+}
@@ -0,0 +1,201 @@
+//! Fast generation of poems from the bank of phrases.
+
+use std::collections::BTreeMap;
+
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::Rng;
+
+use crate::model::LyrianModel;
+use crate::morphological_analysis::LyrianToken;
+use crate::poem::{can_start_line, Haiku, Poem, PoemLine, HAIKU, SENTENCE_ENDS};
+#[cfg(feature = "tokenizer")]
+use crate::pronunciation::JapaneseCounter;
+use crate::pronunciation::MoraCounter;
+#[cfg(feature = "tokenizer")]
+use crate::tokenize::tokenize;
+use crate::tokenize::Tokenizer;
+
+/// The numbers of morae of the phrases in [`PhraseBank`].
+pub const PHRASE_MORAE: [usize; 2] = [5, 7];
+
+/// The bank of the phrases of 5 and 7 morae in the corpus, which generates
+/// haiku instantly.
+///
+/// The corpus is indexed into the phrases which have exactly 5 or 7 morae
+/// and start with neither a particle nor an auxiliary verb. The phrases are
+/// chained by the Markov chain of the corpus, where the first token of the
+/// next phrase follows the last token of the previous one. The poems are
+/// always valid, but consist only of the phrases in the corpus.
+///
+/// ```rust
+/// use lyrian::phrase::PhraseBank;
+///
+/// let contents = std::fs::read_to_string("examples/sample_text.txt").unwrap();
+/// let bank = PhraseBank::from_text(&contents).unwrap();
+/// if let Ok(haiku) = bank.generate() {
+///     println!("{}", haiku.join());
+/// }
+/// ```
+pub struct PhraseBank {
+    model: LyrianModel,
+    phrases: BTreeMap<usize, Vec<Vec<LyrianToken>>>,
+}
+
+impl PhraseBank {
+    /// Builds a new bank from text data, counting the morae by
+    /// [`JapaneseCounter`].
+    #[cfg(feature = "tokenizer")]
+    pub fn from_text(contents: &str) -> Result<PhraseBank, String> {
+        PhraseBank::from_text_with(contents, &JapaneseCounter::new(false))
+    }
+
+    /// Builds a new bank from text data, counting the morae by the counter.
+    #[cfg(feature = "tokenizer")]
+    pub fn from_text_with<C: MoraCounter + ?Sized>(
+        contents: &str,
+        counter: &C,
+    ) -> Result<PhraseBank, String> {
+        PhraseBank::from_text_by(contents, &tokenize, counter)
+    }
+
+    /// Builds a new bank from text data tokenized by the tokenizer, counting
+    /// the morae by the counter.
+    pub fn from_text_by<T: Tokenizer + ?Sized, C: MoraCounter + ?Sized>(
+        contents: &str,
+        tokenizer: &T,
+        counter: &C,
+    ) -> Result<PhraseBank, String> {
+        Ok(PhraseBank::from_tokens(
+            &tokenizer.tokenize(contents)?,
+            counter,
+        ))
+    }
+
+    /// Builds a new bank from tokens.
+    pub(crate) fn from_tokens<C: MoraCounter + ?Sized>(
+        tokens: &[LyrianToken],
+        counter: &C,
+    ) -> PhraseBank {
+        let mut phrases = BTreeMap::new();
+        for morae in PHRASE_MORAE.iter() {
+            let mut found = (0..tokens.len())
+                .filter_map(|start| find_phrase(tokens, start, *morae, counter))
+                .collect::<Vec<Vec<LyrianToken>>>();
+            found.sort();
+            found.dedup();
+            phrases.insert(*morae, found);
+        }
+
+        PhraseBank {
+            model: LyrianModel::from_tokens(tokens),
+            phrases,
+        }
+    }
+
+    /// Returns the number of the phrases of the morae.
+    pub fn count(&self, morae: usize) -> usize {
+        self.phrases.get(&morae).map_or(0, |p| p.len())
+    }
+
+    /// Generates haiku whose lines have 5, 7 and 5 morae.
+    pub fn generate(&self) -> Result<Haiku, String> {
+        self.generate_pattern(&HAIKU)
+    }
+
+    /// Generates a poem whose lines have the given numbers of morae, which
+    /// must be 5 or 7.
+    pub fn generate_pattern(&self, pattern: &[usize]) -> Result<Poem, String> {
+        let mut rng = rand::thread_rng();
+        let mut lines: Vec<PoemLine> = Vec::with_capacity(pattern.len());
+        for morae in pattern {
+            let phrases = match self.phrases.get(morae) {
+                Some(p) if !p.is_empty() => p,
+                _ => return Err(format!("There are no phrases of {} morae.", morae)),
+            };
+
+            let prev = lines.last().and_then(|l| l.tokens().last());
+            let phrase = match prev {
+                Some(prev) => self.choose_next(prev, phrases, &mut rng),
+                None => &phrases[rng.gen_range(0..phrases.len())],
+            };
+            lines.push(PoemLine::new(phrase.clone(), *morae, *morae));
+        }
+        Ok(Poem { lines })
+    }
+
+    /// Chooses the phrase whose first token follows the token in the chain,
+    /// or any phrase if no phrase follows it.
+    fn choose_next<'a, R: Rng>(
+        &self,
+        prev: &LyrianToken,
+        phrases: &'a [Vec<LyrianToken>],
+        rng: &mut R,
+    ) -> &'a Vec<LyrianToken> {
+        let weights = phrases
+            .iter()
+            .map(|p| self.model.probability(prev, &p[0]))
+            .collect::<Vec<f64>>();
+        match WeightedIndex::new(&weights) {
+            Ok(index) => &phrases[index.sample(rng)],
+            Err(_) => &phrases[rng.gen_range(0..phrases.len())],
+        }
+    }
+}
+
+/// Finds the phrase of exactly the morae from the token at `start`, which
+/// does not span sentences.
+fn find_phrase<C: MoraCounter + ?Sized>(
+    tokens: &[LyrianToken],
+    start: usize,
+    morae: usize,
+    counter: &C,
+) -> Option<Vec<LyrianToken>> {
+    if !can_start_line(&tokens[start]) || counter.count_token(&tokens[start]) == 0 {
+        return None;
+    }
+
+    let mut count = 0;
+    for (i, token) in tokens.iter().enumerate().skip(start) {
+        if SENTENCE_ENDS.contains(&token.word.as_str()) {
+            return None;
+        }
+        count += counter.count_token(token);
+        if count == morae {
+            return Some(tokens[start..=i].to_vec());
+        } else if count > morae {
+            return None;
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod phrase_test {
+    use crate::morphological_analysis::noun;
+    use crate::phrase::PhraseBank;
+    use crate::pronunciation::JapaneseCounter;
+
+    #[test]
+    fn index_phrases_by_morae() {
+        let tokens = ["アア", "イイイ", "。", "アア", "イイ", "ウウウ"]
+            .iter()
+            .map(|w| noun(w))
+            .collect::<Vec<_>>();
+        let bank = PhraseBank::from_tokens(&tokens, &JapaneseCounter::new(false));
+        assert_eq!((bank.count(5), bank.count(7)), (2, 1))
+    }
+
+    #[test]
+    fn generate_haiku_from_phrases() {
+        let tokens = ["アア", "イイイ", "アア", "イイ", "ウウウ", "アア"]
+            .iter()
+            .map(|w| noun(w))
+            .collect::<Vec<_>>();
+        let bank = PhraseBank::from_tokens(&tokens, &JapaneseCounter::new(false));
+        let haiku = bank.generate().unwrap();
+        assert_eq!(
+            (haiku.morae(), bank.generate_pattern(&[6]).is_err()),
+            (vec![5, 7, 5], true)
+        )
+    }
+}
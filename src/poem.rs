@@ -0,0 +1,1768 @@
+//! Generation of poems in fixed forms, such as haiku.
+
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crate::chars::hiragana_to_katakana;
+use crate::kigo::{Saijiki, Season};
+use crate::lyric::join_words;
+use crate::model::LyrianModel;
+use crate::morphological_analysis::LyrianToken;
+use crate::normalize::fold_halfwidth_kana_with_offsets;
+use crate::pronunciation::{split_morae, JapaneseCounter, MoraCounter};
+use crate::rhyme::{vowel_pattern, RhymeSound};
+#[cfg(feature = "tokenizer")]
+use crate::tokenize::tokenize;
+use crate::tokenize::Tokenizer;
+
+/// The numbers of morae of the lines of haiku.
+pub const HAIKU: [usize; 3] = [5, 7, 5];
+
+/// The numbers of morae of the lines of tanka.
+pub const TANKA: [usize; 5] = [5, 7, 5, 7, 7];
+
+/// The eighteen kireji (cutting words) of renga.
+pub const KIREJI: [&str; 18] = [
+    "かな",
+    "もがな",
+    "し",
+    "じ",
+    "や",
+    "らん",
+    "か",
+    "けり",
+    "よ",
+    "ぞ",
+    "つ",
+    "せ",
+    "ず",
+    "れ",
+    "ぬ",
+    "へ",
+    "け",
+    "に",
+];
+
+/// The factor by which the transition weights of kireji are multiplied where
+/// a line has to end with a kireji.
+const KIREJI_BIAS: f64 = 8.0;
+
+/// Rules of kireji at the end of a line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KirejiRule {
+    /// The line must end with a kireji.
+    Require,
+    /// The line must not end with a kireji.
+    Forbid,
+}
+
+/// Rules of the part of speech of the last token of a line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EndingRule {
+    /// The line must end with the part of speech, e.g. `名詞` for
+    /// taigen-dome.
+    Require(String),
+    /// The line must not end with the part of speech.
+    Forbid(String),
+}
+
+impl EndingRule {
+    /// Returns whether the line can end with the token.
+    fn allows(&self, token: &LyrianToken) -> bool {
+        match self {
+            EndingRule::Require(pos) => token.part_of_speech == *pos,
+            EndingRule::Forbid(pos) => token.part_of_speech != *pos,
+        }
+    }
+}
+
+/// A line of a poem.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PoemLine {
+    tokens: Vec<LyrianToken>,
+    morae: usize,
+    target: usize,
+}
+
+impl PoemLine {
+    /// Creates a new instance of [`PoemLine`].
+    pub(crate) fn new(tokens: Vec<LyrianToken>, morae: usize, target: usize) -> PoemLine {
+        PoemLine {
+            tokens,
+            morae,
+            target,
+        }
+    }
+
+    /// Returns the tokens of the line.
+    pub fn tokens(&self) -> &[LyrianToken] {
+        &self.tokens
+    }
+
+    /// Returns the number of morae of the line counted by the generator.
+    pub fn morae(&self) -> usize {
+        self.morae
+    }
+
+    /// Returns the number of morae required for the line.
+    pub fn target(&self) -> usize {
+        self.target
+    }
+
+    /// Returns whether the line has exactly the required number of morae.
+    pub fn is_strict(&self) -> bool {
+        self.morae == self.target
+    }
+
+    /// Joins the words of the line.
+    pub fn join(&self) -> String {
+        join_words(&self.tokens)
+    }
+}
+
+/// A poem generated by [`HaikuGenerator`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Poem {
+    /// The lines of the poem.
+    pub lines: Vec<PoemLine>,
+}
+
+/// Haiku, which is a poem of 5, 7 and 5 morae.
+pub type Haiku = Poem;
+
+/// Tanka, which is a poem of 5, 7, 5, 7 and 7 morae.
+pub type Tanka = Poem;
+
+impl Poem {
+    /// Returns the numbers of morae of the lines.
+    pub fn morae(&self) -> Vec<usize> {
+        self.lines.iter().map(|l| l.morae).collect()
+    }
+
+    /// Returns whether all the lines have exactly the required numbers of
+    /// morae, that is, the poem has neither jiamari nor jitarazu.
+    pub fn is_strict(&self) -> bool {
+        self.lines.iter().all(|l| l.is_strict())
+    }
+
+    /// Joins the lines of the poem with newlines.
+    pub fn join(&self) -> String {
+        self.lines
+            .iter()
+            .map(|l| l.join())
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+/// Error returned when no poem satisfies the constraints within the attempts
+/// or the time given to [`HaikuGenerator`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GenerationTimeout {
+    /// The candidate which has the most lines generated so far, if any.
+    ///
+    /// The candidate may lack lines, or break the constraints of the whole
+    /// poem such as the season.
+    pub best: Option<Poem>,
+
+    /// The number of the attempts made.
+    pub attempts: usize,
+}
+
+impl fmt::Display for GenerationTimeout {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Could not generate a poem from the model in {} attempts.",
+            self.attempts
+        )
+    }
+}
+
+impl Error for GenerationTimeout {}
+
+/// Error returned by the generation of [`HaikuGenerator`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GenerationError {
+    /// No poem satisfied the constraints within the attempts or the time.
+    Timeout(GenerationTimeout),
+    /// The acrostic of [`HaikuGenerator::with_acrostic()`] does not have a
+    /// mora for each line of the pattern.
+    AcrosticLength {
+        /// The number of the morae of the acrostic.
+        acrostic: usize,
+        /// The number of the lines of the pattern.
+        lines: usize,
+    },
+    /// The line given to [`HaikuGenerator::regenerate_line()`] is not in the
+    /// poem.
+    NoLine {
+        /// The index of the line.
+        line: usize,
+        /// The number of the lines of the poem.
+        lines: usize,
+    },
+}
+
+impl fmt::Display for GenerationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GenerationError::Timeout(e) => e.fmt(f),
+            GenerationError::AcrosticLength { acrostic, lines } => write!(
+                f,
+                "The acrostic has {} morae, but the poem has {} lines.",
+                acrostic, lines
+            ),
+            GenerationError::NoLine { line, lines } => write!(
+                f,
+                "There is no line {} in the poem of {} lines.",
+                line, lines
+            ),
+        }
+    }
+}
+
+impl Error for GenerationError {}
+
+impl From<GenerationTimeout> for GenerationError {
+    fn from(e: GenerationTimeout) -> GenerationError {
+        GenerationError::Timeout(e)
+    }
+}
+
+/// Score of a poem by [`HaikuGenerator::score()`].
+///
+/// The scores are compared by [`total`](#structfield.total), the larger the
+/// better.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PoemScore {
+    /// The natural log-likelihood of the tokens in the model.
+    pub log_likelihood: f64,
+
+    /// The sum of the extra and missing morae of the lines, that is,
+    /// jiamari and jitarazu.
+    pub deviation: usize,
+
+    /// The number of the kigo in the poem, of the required season if any.
+    pub kigo: usize,
+
+    /// The number of the words which appear again in the poem, except
+    /// particles, auxiliary verbs and symbols.
+    pub repetitions: usize,
+
+    /// The score combining all the above.
+    pub total: f64,
+}
+
+impl PartialOrd for PoemScore {
+    fn partial_cmp(&self, other: &PoemScore) -> Option<Ordering> {
+        self.total.partial_cmp(&other.total)
+    }
+}
+
+/// Report of a poem by [`HaikuGenerator::explain()`], which is displayed as
+/// a commentary in Japanese.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HaikuReport {
+    /// The reports of the lines.
+    pub lines: Vec<LineReport>,
+
+    /// The kigo in the poem and their seasons.
+    pub kigo: Vec<(String, Season)>,
+
+    /// The transitions between the successive tokens of the poem.
+    pub transitions: Vec<TransitionReport>,
+}
+
+/// Report of a line in [`HaikuReport`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineReport {
+    /// The joined words of the line.
+    pub text: String,
+
+    /// The words of the tokens and their numbers of morae.
+    pub morae: Vec<(String, usize)>,
+
+    /// The number of morae of the line.
+    pub total: usize,
+
+    /// The number of morae required for the line.
+    pub target: usize,
+
+    /// Whether the line has more morae than required.
+    pub jiamari: bool,
+
+    /// Whether the line has fewer morae than required.
+    pub jitarazu: bool,
+
+    /// The kireji which the line ends with, if any.
+    pub kireji: Option<String>,
+}
+
+/// Transition between two tokens in [`HaikuReport`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransitionReport {
+    /// The word of the previous token.
+    pub from: String,
+
+    /// The word of the next token.
+    pub to: String,
+
+    /// The probability of the transition in the model, `0.0` if the model
+    /// does not have it.
+    pub probability: f64,
+}
+
+impl fmt::Display for HaikuReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, line) in self.lines.iter().enumerate() {
+            let morae = line
+                .morae
+                .iter()
+                .filter(|(_, n)| *n > 0)
+                .map(|(w, n)| format!("{}{}", w, n))
+                .collect::<Vec<String>>()
+                .join("・");
+            write!(
+                f,
+                "{}行目「{}」{}音（{}）",
+                i + 1,
+                line.text,
+                line.total,
+                morae
+            )?;
+            if line.jiamari {
+                write!(f, "、字余り")?;
+            } else if line.jitarazu {
+                write!(f, "、字足らず")?;
+            }
+            if let Some(kireji) = &line.kireji {
+                write!(f, "、切れ字「{}」", kireji)?;
+            }
+            writeln!(f)?;
+        }
+        for (word, season) in &self.kigo {
+            writeln!(f, "季語「{}」（{}）", word, season_name(*season))?;
+        }
+        Ok(())
+    }
+}
+
+/// The weight of the missing or extra morae in [`PoemScore`].
+const DEVIATION_PENALTY: f64 = 1.0;
+
+/// The weight of including kigo in [`PoemScore`].
+const KIGO_BONUS: f64 = 1.0;
+
+/// The weight of the repeated words in [`PoemScore`].
+const REPETITION_PENALTY: f64 = 0.5;
+
+/// The structure of generating haiku and tanka.
+///
+/// Tokens are sampled from the Markov chain line by line, and the lines
+/// which exceed their numbers of morae are sampled again.
+///
+/// ```rust
+/// use lyrian::model::LyrianModel;
+/// use lyrian::poem::HaikuGenerator;
+/// use lyrian::pronunciation::JapaneseCounter;
+///
+/// let json = std::fs::read_to_string("examples/sample_model.json").unwrap();
+/// let model = LyrianModel::from_json(&json).unwrap();
+/// let mut generator = HaikuGenerator::new(model, JapaneseCounter::new(false));
+/// if let Ok(haiku) = generator.generate() {
+///     println!("{}", haiku.join());
+/// }
+/// ```
+pub struct HaikuGenerator<C: MoraCounter = JapaneseCounter> {
+    model: LyrianModel,
+    counter: C,
+    tolerance: usize,
+    season: Option<Season>,
+    saijiki: Saijiki,
+    seed_kigo: bool,
+    kireji: Vec<String>,
+    kireji_rules: BTreeMap<usize, KirejiRule>,
+    acrostic: Vec<String>,
+    line_endings: Vec<EndingRule>,
+    poem_endings: Vec<EndingRule>,
+    max_attempts: usize,
+    timeout: Option<Duration>,
+    masking: bool,
+    reachable: Vec<Vec<bool>>,
+    multiset_dedup: bool,
+    mora_bias: f64,
+    excluded_pos: Vec<String>,
+}
+
+impl<C: MoraCounter> HaikuGenerator<C> {
+    /// Creates a new instance of [`HaikuGenerator`].
+    pub fn new(model: LyrianModel, counter: C) -> HaikuGenerator<C> {
+        HaikuGenerator {
+            model,
+            counter,
+            tolerance: 0,
+            season: None,
+            saijiki: Saijiki::default(),
+            seed_kigo: false,
+            kireji: KIREJI.iter().map(|k| to_katakana(k)).collect(),
+            kireji_rules: BTreeMap::new(),
+            acrostic: Vec::new(),
+            line_endings: Vec::new(),
+            poem_endings: Vec::new(),
+            max_attempts: 64,
+            timeout: None,
+            masking: false,
+            reachable: Vec::new(),
+            multiset_dedup: false,
+            mora_bias: 0.0,
+            excluded_pos: Vec::new(),
+        }
+    }
+
+    /// Allows each line to have more or fewer morae by the tolerance.
+    ///
+    /// Real haiku often have one extra mora (jiamari) or one missing mora
+    /// (jitarazu). Use [`Poem::is_strict()`] to check whether the generated
+    /// poem is relaxed. The tolerance is `0` by default.
+    pub fn with_tolerance(mut self, tolerance: usize) -> HaikuGenerator<C> {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Requires the poem to contain a kigo of the season.
+    ///
+    /// The poems which lack the kigo are generated again. The kigo are looked
+    /// up in the bundled [`Saijiki`] unless another one is given by
+    /// [`with_saijiki()`](#method.with_saijiki).
+    pub fn with_season(mut self, season: Season) -> HaikuGenerator<C> {
+        self.season = Some(season);
+        self
+    }
+
+    /// Uses the saijiki to look up kigo.
+    pub fn with_saijiki(mut self, saijiki: Saijiki) -> HaikuGenerator<C> {
+        self.saijiki = saijiki;
+        self
+    }
+
+    /// Starts the first line from a kigo of the season, if any.
+    ///
+    /// This takes effect only with [`with_season()`](#method.with_season).
+    pub fn with_kigo_seed(mut self, seed: bool) -> HaikuGenerator<C> {
+        self.seed_kigo = seed;
+        self
+    }
+
+    /// Uses the words as kireji instead of [`KIREJI`].
+    pub fn with_kireji(mut self, kireji: &[&str]) -> HaikuGenerator<C> {
+        self.kireji = kireji.iter().map(|k| to_katakana(k)).collect();
+        self
+    }
+
+    /// Requires or forbids a kireji at the end of the line, counted from `0`.
+    ///
+    /// Where a kireji is required, the transitions to kireji which end the
+    /// line are preferred.
+    ///
+    /// ```rust
+    /// use lyrian::model::LyrianModel;
+    /// use lyrian::poem::{HaikuGenerator, KirejiRule};
+    /// use lyrian::pronunciation::JapaneseCounter;
+    ///
+    /// let json = std::fs::read_to_string("examples/sample_model.json").unwrap();
+    /// let model = LyrianModel::from_json(&json).unwrap();
+    /// let mut generator = HaikuGenerator::new(model, JapaneseCounter::new(false))
+    ///     .with_kireji_at(0, KirejiRule::Require)
+    ///     .with_kireji_at(2, KirejiRule::Forbid);
+    /// if let Ok(haiku) = generator.generate() {
+    ///     println!("{}", haiku.join());
+    /// }
+    /// ```
+    pub fn with_kireji_at(mut self, line: usize, rule: KirejiRule) -> HaikuGenerator<C> {
+        self.kireji_rules.insert(line, rule);
+        self
+    }
+
+    /// Applies the rule to the part of speech at the end of every line.
+    ///
+    /// The rules are checked against the part of speech of the tokens, and
+    /// the next tokens which would break them at the end of a line are never
+    /// chosen.
+    ///
+    /// ```rust
+    /// use lyrian::model::LyrianModel;
+    /// use lyrian::poem::{EndingRule, HaikuGenerator};
+    /// use lyrian::pronunciation::JapaneseCounter;
+    ///
+    /// let json = std::fs::read_to_string("examples/sample_model.json").unwrap();
+    /// let model = LyrianModel::from_json(&json).unwrap();
+    /// let mut generator = HaikuGenerator::new(model, JapaneseCounter::new(false))
+    ///     .with_line_ending(EndingRule::Require("名詞".to_string()));
+    /// if let Ok(haiku) = generator.generate() {
+    ///     println!("{}", haiku.join());
+    /// }
+    /// ```
+    pub fn with_line_ending(mut self, rule: EndingRule) -> HaikuGenerator<C> {
+        self.line_endings.push(rule);
+        self
+    }
+
+    /// Applies the rule to the part of speech at the end of the poem, e.g.
+    /// `EndingRule::Forbid("助詞".to_string())` not to end with a particle.
+    pub fn with_poem_ending(mut self, rule: EndingRule) -> HaikuGenerator<C> {
+        self.poem_endings.push(rule);
+        self
+    }
+
+    /// Makes an acrostic (oriku), where the first morae of the lines spell
+    /// out the word.
+    ///
+    /// The word is given in kana, and must have as many morae as the lines of
+    /// the poem, or an error is returned. The seed of [`with_kigo_seed()`](#method.with_kigo_seed) is
+    /// not used for the acrostic. An empty word disables the acrostic.
+    ///
+    /// ```rust
+    /// use lyrian::model::LyrianModel;
+    /// use lyrian::poem::HaikuGenerator;
+    /// use lyrian::pronunciation::JapaneseCounter;
+    ///
+    /// let json = std::fs::read_to_string("examples/sample_model.json").unwrap();
+    /// let model = LyrianModel::from_json(&json).unwrap();
+    /// let mut generator =
+    ///     HaikuGenerator::new(model, JapaneseCounter::new(false)).with_acrostic("あさひ");
+    /// if let Ok(haiku) = generator.generate() {
+    ///     println!("{}", haiku.join());
+    /// }
+    /// ```
+    pub fn with_acrostic(mut self, word: &str) -> HaikuGenerator<C> {
+        self.acrostic = split_morae(&to_katakana(word))
+            .into_iter()
+            .map(String::from)
+            .collect();
+        self
+    }
+
+    /// Sets the number of the attempts to generate a poem, which is `64` by
+    /// default.
+    pub fn with_max_attempts(mut self, max_attempts: usize) -> HaikuGenerator<C> {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// Stops the generation when the time has passed.
+    ///
+    /// The time is checked before each line, so the generation may take a
+    /// little longer than the timeout. There is no timeout by default.
+    pub fn with_timeout(mut self, timeout: Duration) -> HaikuGenerator<C> {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Prefers the next tokens which fill exactly the rest of the line, e.g.
+    /// a word of 2 morae when 2 morae remain.
+    ///
+    /// The transition weights of such tokens are multiplied by
+    /// `1 + strength`, so the lines with jiamari and jitarazu become rare
+    /// with the tolerance. The strength is `0` by default, and the negative
+    /// ones are regarded as `0`.
+    pub fn with_mora_bias(mut self, strength: f64) -> HaikuGenerator<C> {
+        self.mora_bias = strength.max(0.0);
+        self
+    }
+
+    /// Masks out the next tokens which can not complete the line in exactly
+    /// the required number of morae.
+    ///
+    /// Which numbers of morae can follow each token are computed by dynamic
+    /// programming on the first generation, and cached in the generator.
+    /// The lines are hardly sampled again, but always have exactly the
+    /// required numbers of morae regardless of the tolerance. The masking is
+    /// off by default.
+    pub fn with_masking(mut self, masking: bool) -> HaikuGenerator<C> {
+        self.masking = masking;
+        self
+    }
+
+    /// Never uses the tokens of the part of speech, e.g. `感動詞`.
+    ///
+    /// The tokens of the model are distinguished by their parts of speech,
+    /// so the homographs of other parts of speech can still be used.
+    pub fn with_excluded_part_of_speech(mut self, pos: &str) -> HaikuGenerator<C> {
+        self.excluded_pos.push(pos.to_string());
+        self
+    }
+
+    /// Regards the poems which consist of the same tokens in another order as
+    /// duplicates in [`generate_many()`](#method.generate_many).
+    pub fn with_multiset_dedup(mut self, multiset_dedup: bool) -> HaikuGenerator<C> {
+        self.multiset_dedup = multiset_dedup;
+        self
+    }
+
+    /// Returns the model back, to reuse it with other constraints.
+    pub fn into_model(self) -> LyrianModel {
+        self.model
+    }
+
+    /// Generates haiku whose lines have 5, 7 and 5 morae.
+    pub fn generate(&mut self) -> Result<Haiku, GenerationError> {
+        self.generate_pattern(&HAIKU)
+    }
+
+    /// Generates tanka whose lines have 5, 7, 5, 7 and 7 morae.
+    pub fn generate_tanka(&mut self) -> Result<Tanka, GenerationError> {
+        self.generate_pattern(&TANKA)
+    }
+
+    /// Generates distinct haiku up to `n`, with their natural log-likelihoods
+    /// in the model.
+    ///
+    /// See [`generate_many_pattern()`](#method.generate_many_pattern) for the
+    /// details.
+    ///
+    /// ```rust
+    /// use lyrian::model::LyrianModel;
+    /// use lyrian::poem::HaikuGenerator;
+    /// use lyrian::pronunciation::JapaneseCounter;
+    ///
+    /// let json = std::fs::read_to_string("examples/sample_model.json").unwrap();
+    /// let model = LyrianModel::from_json(&json).unwrap();
+    /// let mut generator = HaikuGenerator::new(model, JapaneseCounter::new(false));
+    /// for (haiku, likelihood) in generator.generate_many(3) {
+    ///     println!("{} ({:.2})", haiku.join(), likelihood);
+    /// }
+    /// ```
+    pub fn generate_many(&mut self, n: usize) -> Vec<(Haiku, f64)> {
+        self.generate_many_pattern(&HAIKU, n)
+    }
+
+    /// Generates distinct poems of the pattern up to `n`, with their natural
+    /// log-likelihoods in the model.
+    ///
+    /// The poems identical to the ones already generated are discarded, and
+    /// so are the poems of the same tokens in another order with
+    /// [`with_multiset_dedup()`](#method.with_multiset_dedup). Fewer poems
+    /// are returned when a generation fails, or as many duplicates as the
+    /// max attempts are discarded.
+    pub fn generate_many_pattern(&mut self, pattern: &[usize], n: usize) -> Vec<(Poem, f64)> {
+        let mut poems: Vec<(Poem, f64)> = Vec::with_capacity(n);
+        let mut keys = Vec::with_capacity(n);
+        let mut duplicates = 0;
+        while poems.len() < n && duplicates < self.max_attempts {
+            let poem = match self.generate_pattern(pattern) {
+                Ok(poem) => poem,
+                Err(_) => break,
+            };
+            let mut tokens = poem
+                .lines
+                .iter()
+                .flat_map(|l| l.tokens().iter().cloned())
+                .collect::<Vec<LyrianToken>>();
+            let likelihood = self
+                .model
+                .log_likelihood(&tokens)
+                .unwrap_or(f64::NEG_INFINITY);
+            if self.multiset_dedup {
+                tokens.sort();
+            }
+
+            if poems.iter().any(|(p, _)| *p == poem) || keys.contains(&tokens) {
+                duplicates += 1;
+            } else {
+                keys.push(tokens);
+                poems.push((poem, likelihood));
+            }
+        }
+        poems
+    }
+
+    /// Scores the poem to compare it with the others.
+    ///
+    /// The log-likelihood per token is added to the total, with a bonus if
+    /// the poem has a kigo, and penalties for the jiamari, jitarazu and
+    /// repeated words.
+    ///
+    /// ```rust
+    /// use lyrian::model::LyrianModel;
+    /// use lyrian::poem::HaikuGenerator;
+    /// use lyrian::pronunciation::JapaneseCounter;
+    ///
+    /// let json = std::fs::read_to_string("examples/sample_model.json").unwrap();
+    /// let model = LyrianModel::from_json(&json).unwrap();
+    /// let mut generator = HaikuGenerator::new(model, JapaneseCounter::new(false));
+    /// let poems = generator.generate_many(20);
+    /// let best = generator.rank(poems.into_iter().map(|(p, _)| p).collect());
+    /// for (haiku, score) in best.iter().take(5) {
+    ///     println!("{} ({:.2})", haiku.join(), score.total);
+    /// }
+    /// ```
+    pub fn score(&self, poem: &Poem) -> PoemScore {
+        let tokens = poem
+            .lines
+            .iter()
+            .flat_map(|l| l.tokens().iter().cloned())
+            .collect::<Vec<LyrianToken>>();
+        let log_likelihood = self
+            .model
+            .log_likelihood(&tokens)
+            .unwrap_or(f64::NEG_INFINITY);
+        let deviation = poem
+            .lines
+            .iter()
+            .map(|l| (l.morae as isize - l.target as isize).unsigned_abs())
+            .sum::<usize>();
+        let kigo = self
+            .saijiki
+            .detect(poem)
+            .iter()
+            .filter(|(_, s)| self.season.is_none() || self.season == Some(*s))
+            .count();
+
+        let mut words = tokens
+            .iter()
+            .filter(|t| !matches!(t.part_of_speech.as_str(), "助詞" | "助動詞" | "記号"))
+            .map(|t| t.word.as_str())
+            .collect::<Vec<&str>>();
+        let len = words.len();
+        words.sort_unstable();
+        words.dedup();
+        let repetitions = len - words.len();
+
+        let mut total = log_likelihood / tokens.len().max(1) as f64
+            - DEVIATION_PENALTY * deviation as f64
+            - REPETITION_PENALTY * repetitions as f64;
+        if kigo > 0 {
+            total += KIGO_BONUS;
+        }
+        PoemScore {
+            log_likelihood,
+            deviation,
+            kigo,
+            repetitions,
+            total,
+        }
+    }
+
+    /// Scores the poems, and sorts them from the best.
+    pub fn rank(&self, poems: Vec<Poem>) -> Vec<(Poem, PoemScore)> {
+        let mut scored = poems
+            .into_iter()
+            .map(|p| {
+                let score = self.score(&p);
+                (p, score)
+            })
+            .collect::<Vec<(Poem, PoemScore)>>();
+        scored.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal));
+        scored
+    }
+
+    /// Explains the poem with the morae of its lines, kigo, kireji and the
+    /// probabilities of the transitions.
+    ///
+    /// ```rust
+    /// use lyrian::model::LyrianModel;
+    /// use lyrian::poem::HaikuGenerator;
+    /// use lyrian::pronunciation::JapaneseCounter;
+    ///
+    /// let json = std::fs::read_to_string("examples/sample_model.json").unwrap();
+    /// let model = LyrianModel::from_json(&json).unwrap();
+    /// let mut generator = HaikuGenerator::new(model, JapaneseCounter::new(false));
+    /// if let Ok(haiku) = generator.generate() {
+    ///     println!("{}\n\n{}", haiku.join(), generator.explain(&haiku));
+    /// }
+    /// ```
+    pub fn explain(&self, poem: &Poem) -> HaikuReport {
+        let lines = poem
+            .lines
+            .iter()
+            .map(|l| LineReport {
+                text: l.join(),
+                morae: l
+                    .tokens
+                    .iter()
+                    .map(|t| (t.word.clone(), self.counter.count_token(t)))
+                    .collect(),
+                total: l.morae,
+                target: l.target,
+                jiamari: l.morae > l.target,
+                jitarazu: l.morae < l.target,
+                kireji: match l.tokens.last() {
+                    Some(t) if is_kireji(&self.kireji, t) => Some(t.word.clone()),
+                    _ => None,
+                },
+            })
+            .collect();
+
+        let tokens = poem
+            .lines
+            .iter()
+            .flat_map(|l| l.tokens().iter())
+            .collect::<Vec<&LyrianToken>>();
+        let transitions = tokens
+            .windows(2)
+            .map(|pair| TransitionReport {
+                from: pair[0].word.clone(),
+                to: pair[1].word.clone(),
+                probability: self.model.probability(pair[0], pair[1]),
+            })
+            .collect();
+
+        HaikuReport {
+            lines,
+            kigo: self.saijiki.detect(poem),
+            transitions,
+        }
+    }
+
+    /// Generates a poem whose lines have the given numbers of morae.
+    ///
+    /// Any rhythm can be given, e.g. `&[7, 7]` for the response of renku.
+    /// An empty pattern gives an empty poem.
+    ///
+    /// [`GenerationError::Timeout`] is returned when the poem is not
+    /// generated within the attempts and the timeout, and
+    /// [`GenerationError::AcrosticLength`] without attempts if the acrostic
+    /// does not fit the pattern.
+    pub fn generate_pattern(&mut self, pattern: &[usize]) -> Result<Poem, GenerationError> {
+        self.generate_with(pattern, None)
+    }
+
+    /// Generates rhymed verse, e.g. of rap lyrics, whose lines have the same
+    /// number of morae.
+    ///
+    /// The last `rhyme_morae` morae of every line have the same sounds as the
+    /// first line, where the sounds of the readings are given by
+    /// [`rhyme::vowel_pattern()`](crate::rhyme::vowel_pattern) as in
+    /// [`rhyme::rhymes()`](crate::rhyme::rhymes). The tokens which would break the rhyme at the end of a line are never
+    /// chosen.
+    ///
+    /// ```rust
+    /// use lyrian::model::LyrianModel;
+    /// use lyrian::poem::HaikuGenerator;
+    /// use lyrian::pronunciation::JapaneseCounter;
+    ///
+    /// let json = std::fs::read_to_string("examples/sample_model.json").unwrap();
+    /// let model = LyrianModel::from_json(&json).unwrap();
+    /// let mut generator = HaikuGenerator::new(model, JapaneseCounter::new(false));
+    /// if let Ok(verse) = generator.generate_rhymed(4, 8, 2) {
+    ///     println!("{}", verse.join());
+    /// }
+    /// ```
+    pub fn generate_rhymed(
+        &mut self,
+        lines: usize,
+        morae_per_line: usize,
+        rhyme_morae: usize,
+    ) -> Result<Poem, GenerationError> {
+        self.generate_with(&vec![morae_per_line; lines], Some(rhyme_morae))
+    }
+
+    /// Generates the line of the poem again, keeping the other lines.
+    ///
+    /// The new line follows the last token of the previous line in the
+    /// chain, and has the same number of morae required. The rules of the line
+    /// are applied as in the generation, except the rhyme. The poem is left as
+    /// it is when no line is generated within the attempts and the timeout,
+    /// and [`GenerationError::NoLine`] is returned if the poem has no such
+    /// line.
+    ///
+    /// ```rust
+    /// use lyrian::model::LyrianModel;
+    /// use lyrian::poem::HaikuGenerator;
+    /// use lyrian::pronunciation::JapaneseCounter;
+    ///
+    /// let json = std::fs::read_to_string("examples/sample_model.json").unwrap();
+    /// let model = LyrianModel::from_json(&json).unwrap();
+    /// let mut generator = HaikuGenerator::new(model, JapaneseCounter::new(false));
+    /// if let Ok(mut haiku) = generator.generate() {
+    ///     if generator.regenerate_line(&mut haiku, 1).is_ok() {
+    ///         println!("{}", haiku.join());
+    ///     }
+    /// }
+    /// ```
+    pub fn regenerate_line(&mut self, poem: &mut Poem, line: usize) -> Result<(), GenerationError> {
+        let target = match poem.lines.get(line) {
+            Some(l) => l.target,
+            None => {
+                return Err(GenerationError::NoLine {
+                    line,
+                    lines: poem.lines.len(),
+                })
+            }
+        };
+        let mut error = GenerationTimeout {
+            best: None,
+            attempts: 0,
+        };
+        let prev = match line.checked_sub(1) {
+            Some(i) => poem.lines[i].tokens.last().cloned(),
+            None => None,
+        };
+        let last = line + 1 == poem.lines.len();
+
+        if self.masking && !matches!(self.reachable.first(), Some(r) if r.len() > target) {
+            self.reachable = self.model.reachable_morae(&self.counter, target);
+        }
+        let (start, timeout) = (Instant::now(), self.timeout);
+        while error.attempts < self.max_attempts
+            && !matches!(timeout, Some(t) if start.elapsed() >= t)
+        {
+            error.attempts += 1;
+            self.model.initialize();
+            if let Some(new_line) = self.generate_line(target, line, last, &[], prev.as_ref()) {
+                poem.lines[line] = new_line;
+                return Ok(());
+            }
+        }
+        Err(error.into())
+    }
+
+    /// Generates a poem of the pattern, whose lines rhyme with the first line
+    /// by the number of morae if any.
+    fn generate_with(
+        &mut self,
+        pattern: &[usize],
+        rhyme_morae: Option<usize>,
+    ) -> Result<Poem, GenerationError> {
+        if !self.acrostic.is_empty() && self.acrostic.len() != pattern.len() {
+            return Err(GenerationError::AcrosticLength {
+                acrostic: self.acrostic.len(),
+                lines: pattern.len(),
+            });
+        }
+        let mut error = GenerationTimeout {
+            best: None,
+            attempts: 0,
+        };
+
+        let longest = pattern.iter().max().copied().unwrap_or(0);
+        if self.masking && !matches!(self.reachable.first(), Some(r) if r.len() > longest) {
+            self.reachable = self.model.reachable_morae(&self.counter, longest);
+        }
+
+        let (start, timeout) = (Instant::now(), self.timeout);
+        let timed_out = || matches!(timeout, Some(t) if start.elapsed() >= t);
+        while error.attempts < self.max_attempts && !timed_out() {
+            error.attempts += 1;
+            self.model.initialize();
+            let mut lines: Vec<PoemLine> = Vec::with_capacity(pattern.len());
+            let mut rhyme = Vec::new();
+            for (i, morae) in pattern.iter().enumerate() {
+                if timed_out() {
+                    break;
+                }
+                match self.generate_line(*morae, i, i + 1 == pattern.len(), &rhyme, None) {
+                    Some(line) => lines.push(line),
+                    None => break,
+                }
+                if let (Some(n), [first]) = (rhyme_morae, lines.as_slice()) {
+                    let sounds = line_sounds(first.tokens());
+                    rhyme = sounds[sounds.len().saturating_sub(n)..].to_vec();
+                }
+            }
+
+            let poem = Poem { lines };
+            if poem.lines.len() == pattern.len() && self.has_season(&poem) {
+                return Ok(poem);
+            }
+            let best = error.best.as_ref().map_or(0, |b| b.lines.len());
+            if poem.lines.len() > best {
+                error.best = Some(poem);
+            }
+        }
+
+        Err(error.into())
+    }
+
+    /// Generates the line which has the given number of morae within the
+    /// tolerance, and ends with the sounds of the rhyme.
+    fn generate_line(
+        &mut self,
+        morae: usize,
+        line: usize,
+        last: bool,
+        rhyme: &[RhymeSound],
+        follow: Option<&LyrianToken>,
+    ) -> Option<PoemLine> {
+        let (min, max) = (morae.saturating_sub(self.tolerance), morae + self.tolerance);
+        let rule = self.kireji_rules.get(&line).copied();
+        let mut endings = self.line_endings.clone();
+        if last {
+            endings.extend(self.poem_endings.iter().cloned());
+        }
+        for _ in 0..64 {
+            let first_token = match (follow, self.acrostic.get(line).cloned()) {
+                (Some(prev), head) => {
+                    let excluded = &self.excluded_pos;
+                    self.model.follow(prev);
+                    self.model.next_token_by(|_, t| {
+                        let head = match &head {
+                            Some(h) => starts_with_mora(t, h),
+                            None => true,
+                        };
+                        if can_start_line(t) && head && !excluded.contains(&t.part_of_speech) {
+                            1.0
+                        } else {
+                            0.0
+                        }
+                    })?
+                }
+                (None, Some(head)) => self.get_head_token(&head)?,
+                (None, None) if line == 0 && self.seed_kigo => self.get_kigo_token()?,
+                (None, None) => self.model.get_first_token()?,
+            };
+            let mut tokens = vec![first_token];
+            let mut count = self.counter.count_token(&tokens[0]);
+            if self.masking {
+                let reach = match (self.model.current_index(), morae.checked_sub(count)) {
+                    (Some(i), Some(rest)) => self.reachable[i][rest],
+                    _ => false,
+                };
+                if !reach {
+                    continue;
+                }
+            }
+            while count < morae && tokens.len() < 64 {
+                let (counter, kireji, reachable) = (&self.counter, &self.kireji, &self.reachable);
+                let (masking, mora_bias) = (self.masking, self.mora_bias);
+                let excluded = &self.excluded_pos;
+                let token = match self.model.next_token_by(|i, t| {
+                    let total = count + counter.count_token(t);
+                    let masked = masking && (total > morae || !reachable[i][morae - total]);
+                    if masked || excluded.contains(&t.part_of_speech) {
+                        return 0.0;
+                    } else if !(morae..=max).contains(&total) {
+                        return 1.0;
+                    } else if !endings.iter().all(|e| e.allows(t)) || !ends_with_rhyme(t, rhyme) {
+                        return 0.0;
+                    }
+                    let fit = if total == morae { 1.0 + mora_bias } else { 1.0 };
+                    match rule {
+                        Some(KirejiRule::Require) if is_kireji(kireji, t) => KIREJI_BIAS * fit,
+                        Some(KirejiRule::Forbid) if is_kireji(kireji, t) => 0.0,
+                        _ => fit,
+                    }
+                }) {
+                    Some(token) => token,
+                    None => break,
+                };
+                let len = self.counter.count_token(&token);
+                if count + len > max && count >= min {
+                    break;
+                }
+                count += len;
+                tokens.push(token);
+            }
+
+            let end = tokens.last().expect("A line has the first token.");
+            let kireji = match rule {
+                Some(KirejiRule::Require) => is_kireji(&self.kireji, end),
+                Some(KirejiRule::Forbid) => !is_kireji(&self.kireji, end),
+                None => true,
+            };
+            let ending = endings.iter().all(|e| e.allows(end));
+            let rhymed = line_sounds(&tokens).ends_with(rhyme);
+            let allowed = tokens
+                .iter()
+                .all(|t| !self.excluded_pos.contains(&t.part_of_speech));
+            if (min..=max).contains(&count) && kireji && ending && rhymed && allowed {
+                return Some(PoemLine {
+                    tokens,
+                    morae: count,
+                    target: morae,
+                });
+            }
+        }
+        None
+    }
+
+    /// Gets a first token which is a kigo of the season, or any first token
+    /// if no kigo is found.
+    ///
+    /// [`None`] is returned if the model has no first token.
+    fn get_kigo_token(&mut self) -> Option<LyrianToken> {
+        let mut token = self.model.get_first_token()?;
+        for _ in 0..256 {
+            if self.season.is_none() || self.saijiki.season_of_token(&token) == self.season {
+                break;
+            }
+            token = self.model.get_first_token()?;
+        }
+        Some(token)
+    }
+
+    /// Gets a first token whose reading starts with the mora.
+    ///
+    /// The chain is initialized when no next token starts with the mora.
+    fn get_head_token(&mut self, head: &str) -> Option<LyrianToken> {
+        let starts = |t: &LyrianToken| can_start_line(t) && starts_with_mora(t, head);
+        for _ in 0..256 {
+            match self
+                .model
+                .next_token_by(|_, t| if starts(t) { 1.0 } else { 0.0 })
+            {
+                Some(token) => return Some(token),
+                None => self.model.initialize(),
+            }
+        }
+        None
+    }
+
+    /// Returns whether the poem contains a kigo of the required season.
+    fn has_season(&self, poem: &Poem) -> bool {
+        match self.season {
+            Some(season) => self.saijiki.detect(poem).iter().any(|(_, s)| *s == season),
+            None => true,
+        }
+    }
+}
+
+/// Haiku found in text by [`HaikuFinder`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FoundHaiku {
+    /// The haiku, whose lines are the tokens found.
+    pub haiku: Haiku,
+
+    /// The byte offset where the haiku starts in the text.
+    pub start: usize,
+
+    /// The byte offset where the haiku ends in the text.
+    pub end: usize,
+}
+
+/// The words which end sentences.
+pub(crate) const SENTENCE_ENDS: [&str; 6] = ["。", "！", "？", "!", "?", "\n"];
+
+/// The structure of finding haiku by chance (guuzen haiku) in text.
+///
+/// The text is tokenized, and the consecutive tokens whose morae form the
+/// pattern are found. Each line has to start with a word which is neither a
+/// particle, an auxiliary verb nor a symbol, and no haiku spans sentences.
+///
+/// ```rust
+/// use lyrian::poem::HaikuFinder;
+///
+/// let found = HaikuFinder::new().find("古池や蛙飛び込む水の音").unwrap();
+/// assert_eq!(found[0].haiku.join(), "古池や\n蛙飛び込む\n水の音");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HaikuFinder<C: MoraCounter = JapaneseCounter> {
+    counter: C,
+    pattern: Vec<usize>,
+    tolerance: usize,
+    sentence_aligned: bool,
+}
+
+impl HaikuFinder<JapaneseCounter> {
+    /// Creates a new instance of [`HaikuFinder`], which finds haiku by mora
+    /// unit.
+    pub fn new() -> HaikuFinder<JapaneseCounter> {
+        HaikuFinder::with_counter(JapaneseCounter::new(false))
+    }
+}
+
+impl Default for HaikuFinder<JapaneseCounter> {
+    fn default() -> HaikuFinder<JapaneseCounter> {
+        HaikuFinder::new()
+    }
+}
+
+impl<C: MoraCounter> HaikuFinder<C> {
+    /// Creates a new instance of [`HaikuFinder`] with the counter.
+    pub fn with_counter(counter: C) -> HaikuFinder<C> {
+        HaikuFinder {
+            counter,
+            pattern: HAIKU.to_vec(),
+            tolerance: 0,
+            sentence_aligned: false,
+        }
+    }
+
+    /// Finds the poems of the pattern instead of haiku, e.g. [`TANKA`].
+    pub fn with_pattern(mut self, pattern: &[usize]) -> HaikuFinder<C> {
+        self.pattern = pattern.to_vec();
+        self
+    }
+
+    /// Allows each line to have more or fewer morae by the tolerance.
+    pub fn with_tolerance(mut self, tolerance: usize) -> HaikuFinder<C> {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Requires the haiku to be whole sentences, which start and end at the
+    /// boundaries of sentences.
+    pub fn with_sentence_aligned(mut self, sentence_aligned: bool) -> HaikuFinder<C> {
+        self.sentence_aligned = sentence_aligned;
+        self
+    }
+
+    /// Finds haiku in the text, which do not overlap each other.
+    #[cfg(feature = "tokenizer")]
+    pub fn find(&self, text: &str) -> Result<Vec<FoundHaiku>, String> {
+        self.find_by(text, &tokenize)
+    }
+
+    /// Finds haiku in the text tokenized by the tokenizer, which do not
+    /// overlap each other.
+    pub fn find_by<T: Tokenizer + ?Sized>(
+        &self,
+        text: &str,
+        tokenizer: &T,
+    ) -> Result<Vec<FoundHaiku>, String> {
+        let tokens = tokenizer.tokenize(text)?;
+
+        // The tokens are searched in the text folded as the tokenizers do,
+        // and their offsets are mapped back to the text.
+        let (folded, origins) = fold_halfwidth_kana_with_offsets(text);
+        let mut offsets = Vec::with_capacity(tokens.len() + 1);
+        let mut offset = 0;
+        for token in &tokens {
+            if let Some(i) = folded[offset..].find(token.word.as_str()) {
+                offset += i;
+            }
+            offsets.push(origins[offset]);
+            offset = (offset + token.word.len()).min(folded.len());
+            while !folded.is_char_boundary(offset) {
+                offset += 1;
+            }
+        }
+        offsets.push(origins[offset]);
+
+        let is_end = |i: usize| SENTENCE_ENDS.contains(&tokens[i].word.as_str());
+        let mut found = Vec::new();
+        let mut start = 0;
+        while start < tokens.len() {
+            let aligned = start == 0 || is_end(start - 1);
+            let lengths = if aligned || !self.sentence_aligned {
+                self.match_lines(&tokens, start, 0)
+            } else {
+                None
+            };
+            let lengths = lengths.filter(|l| {
+                let end = start + l.iter().sum::<usize>();
+                !self.sentence_aligned || end == tokens.len() || is_end(end)
+            });
+
+            match lengths {
+                Some(lengths) => {
+                    let mut lines = Vec::with_capacity(lengths.len());
+                    let mut i = start;
+                    for (len, target) in lengths.iter().zip(&self.pattern) {
+                        let line = tokens[i..i + len].to_vec();
+                        let morae = line.iter().map(|t| self.counter.count_token(t)).sum();
+                        lines.push(PoemLine {
+                            tokens: line,
+                            morae,
+                            target: *target,
+                        });
+                        i += len;
+                    }
+                    found.push(FoundHaiku {
+                        haiku: Poem { lines },
+                        start: offsets[start],
+                        end: offsets[i],
+                    });
+                    start = i;
+                }
+                None => start += 1,
+            }
+        }
+        Ok(found)
+    }
+
+    /// Finds the numbers of tokens of the lines from the line-th line, which
+    /// start from the token at `start`.
+    fn match_lines(&self, tokens: &[LyrianToken], start: usize, line: usize) -> Option<Vec<usize>> {
+        let target = match self.pattern.get(line) {
+            Some(target) => *target,
+            None => return Some(Vec::new()),
+        };
+        let first = tokens.get(start)?;
+        if matches!(first.part_of_speech.as_str(), "助詞" | "助動詞" | "記号") {
+            return None;
+        }
+
+        let (min, max) = (
+            target.saturating_sub(self.tolerance),
+            target + self.tolerance,
+        );
+        let mut ends = Vec::new();
+        let mut count = 0;
+        for (i, token) in tokens.iter().enumerate().skip(start) {
+            if SENTENCE_ENDS.contains(&token.word.as_str()) {
+                break;
+            }
+            count += self.counter.count_token(token);
+            if count > max {
+                break;
+            } else if count >= min {
+                ends.push((i + 1, (count as isize - target as isize).abs()));
+            }
+        }
+        ends.sort_by_key(|(_, deviation)| *deviation);
+
+        ends.into_iter().find_map(|(end, _)| {
+            let mut lengths = self.match_lines(tokens, end, line + 1)?;
+            lengths.insert(0, end - start);
+            Some(lengths)
+        })
+    }
+}
+
+/// Finds haiku in the text by [`HaikuFinder`] with the default options.
+#[cfg(feature = "tokenizer")]
+pub fn find_haiku(text: &str) -> Result<Vec<FoundHaiku>, String> {
+    HaikuFinder::new().find(text)
+}
+
+/// Returns whether the token can start a line, that is, neither a particle
+/// nor an auxiliary verb.
+pub(crate) fn can_start_line(token: &LyrianToken) -> bool {
+    token.part_of_speech != "助詞" && token.part_of_speech != "助動詞"
+}
+
+/// Returns whether the reading of the token starts with the mora.
+fn starts_with_mora(token: &LyrianToken, mora: &str) -> bool {
+    split_morae(&token.mora).first() == Some(&mora)
+}
+
+/// Returns the sounds of the readings of the tokens to compare their rhymes.
+fn line_sounds(tokens: &[LyrianToken]) -> Vec<RhymeSound> {
+    tokens.iter().flat_map(token_sounds).collect()
+}
+
+/// Returns the sounds of the reading of the token.
+fn token_sounds(token: &LyrianToken) -> Vec<RhymeSound> {
+    if token.mora == "unknown" {
+        vowel_pattern(&token.word)
+    } else {
+        vowel_pattern(&token.mora)
+    }
+}
+
+/// Returns whether the token can end a line of the rhyme, that is, the
+/// sounds of the token match the end of the rhyme.
+fn ends_with_rhyme(token: &LyrianToken, rhyme: &[RhymeSound]) -> bool {
+    let sounds = token_sounds(token);
+    let n = sounds.len().min(rhyme.len());
+    sounds[sounds.len() - n..] == rhyme[rhyme.len() - n..]
+}
+
+/// Returns whether the word of the token is one of the kireji.
+fn is_kireji(kireji: &[String], token: &LyrianToken) -> bool {
+    kireji.contains(&to_katakana(&token.word))
+}
+
+/// Returns the name of the season in Japanese.
+fn season_name(season: Season) -> &'static str {
+    match season {
+        Season::Spring => "春",
+        Season::Summer => "夏",
+        Season::Autumn => "秋",
+        Season::Winter => "冬",
+        Season::NewYear => "新年",
+    }
+}
+
+/// Converts hiragana in the word into katakana.
+fn to_katakana(word: &str) -> String {
+    word.chars().map(hiragana_to_katakana).collect()
+}
+
+#[cfg(test)]
+mod poem_test {
+    use crate::kigo::{Saijiki, Season};
+    use crate::model::LyrianModel;
+    use crate::morphological_analysis::{noun, token, LyrianToken};
+    use std::time::Duration;
+
+    use crate::poem::{
+        line_sounds, EndingRule, GenerationError, GenerationTimeout, HaikuGenerator, KirejiRule,
+        Poem, PoemLine,
+    };
+    use crate::pronunciation::JapaneseCounter;
+
+    /// Creates a generator of the model of the nouns in order.
+    fn generator(words: &[&str]) -> HaikuGenerator<JapaneseCounter> {
+        let tokens = words.iter().map(|w| noun(w)).collect::<Vec<_>>();
+        HaikuGenerator::new(
+            LyrianModel::from_tokens(&tokens),
+            JapaneseCounter::new(false),
+        )
+    }
+
+    #[test]
+    fn generate_haiku_of_5_7_5() {
+        let mut generator = generator(&["ア", "イ", "ウ", "ア"]);
+        let haiku = generator.generate().unwrap();
+        assert_eq!(
+            haiku
+                .lines
+                .iter()
+                .map(|l| l.morae())
+                .collect::<Vec<usize>>(),
+            vec![5, 7, 5]
+        )
+    }
+
+    #[test]
+    fn generate_tanka_of_5_7_5_7_7() {
+        let mut generator = generator(&["ア", "イ", "ウ", "ア"]);
+        let tanka = generator.generate_tanka().unwrap();
+        assert_eq!(
+            (tanka.morae(), tanka.lines[4].tokens().len()),
+            (vec![5, 7, 5, 7, 7], 7)
+        )
+    }
+
+    #[test]
+    fn fail_to_generate_impossible_haiku() {
+        let mut generator = generator(&["アア", "イイ", "アア"]);
+        assert!(generator.generate().is_err())
+    }
+
+    #[test]
+    fn generate_poem_of_any_pattern() {
+        let mut generator = generator(&["ア", "イイ", "ア"]);
+        assert_eq!(
+            generator.generate_pattern(&[7, 7]).unwrap().morae(),
+            vec![7, 7]
+        )
+    }
+
+    #[test]
+    fn generate_relaxed_haiku_by_tolerance() {
+        let mut generator = generator(&["アア", "イイ", "アア"]).with_tolerance(1);
+        let haiku = generator.generate().unwrap();
+        assert!(!haiku.is_strict())
+    }
+
+    #[test]
+    fn generate_haiku_with_kigo_of_season() {
+        let mut saijiki = Saijiki::empty();
+        saijiki.insert("ユキ", Season::Winter);
+        let mut generator = generator(&["ユキ", "イ", "ウ", "エ", "オ", "カ", "ユキ"])
+            .with_season(Season::Winter)
+            .with_saijiki(saijiki.clone())
+            .with_kigo_seed(true);
+        let haiku = generator.generate().unwrap();
+        assert_eq!(
+            (
+                haiku.lines[0].tokens()[0].word.as_str(),
+                saijiki.detect(&haiku)[0].1
+            ),
+            ("ユキ", Season::Winter)
+        )
+    }
+
+    #[test]
+    fn require_and_forbid_kireji_at_line_ends() {
+        let mut tokens = ["ア", "イ", "ウ", "エ"]
+            .iter()
+            .map(|w| noun(w))
+            .collect::<Vec<_>>();
+        tokens.push(LyrianToken::new(
+            "や".to_string(),
+            "ヤ".to_string(),
+            "ヤ".to_string(),
+            "助詞".to_string(),
+        ));
+        tokens.push(noun("ア"));
+        let model = LyrianModel::from_tokens(&tokens);
+        let mut generator = HaikuGenerator::new(model, JapaneseCounter::new(false))
+            .with_kireji_at(0, KirejiRule::Require)
+            .with_kireji_at(2, KirejiRule::Forbid);
+        let haiku = generator.generate().unwrap();
+        let last = |i: usize| haiku.lines[i].tokens().last().unwrap().word.clone();
+        assert_eq!((last(0), last(2) != "や"), ("や".to_string(), true))
+    }
+
+    #[test]
+    fn generate_acrostic() {
+        let mut generator =
+            generator(&["ア", "イ", "ウ", "エ", "オ", "ア"]).with_acrostic("うえい");
+        let haiku = generator.generate().unwrap();
+        assert_eq!(
+            haiku
+                .lines
+                .iter()
+                .map(|l| l.tokens()[0].word.as_str())
+                .collect::<Vec<&str>>(),
+            vec!["ウ", "エ", "イ"]
+        )
+    }
+
+    #[test]
+    fn fail_to_generate_acrostic_of_other_length() {
+        let mut generator = generator(&["ア", "イ", "ア"]).with_acrostic("アイ");
+        assert_eq!(
+            generator.generate(),
+            Err(GenerationError::AcrosticLength {
+                acrostic: 2,
+                lines: 3
+            })
+        )
+    }
+
+    #[test]
+    fn apply_rules_to_line_endings() {
+        let mut tokens = ["ア", "イ", "ウ"]
+            .iter()
+            .map(|w| noun(w))
+            .collect::<Vec<_>>();
+        tokens.push(LyrianToken::new(
+            "て".to_string(),
+            "テ".to_string(),
+            "テ".to_string(),
+            "助詞".to_string(),
+        ));
+        tokens.push(noun("ア"));
+        let model = LyrianModel::from_tokens(&tokens);
+        let mut generator = HaikuGenerator::new(model, JapaneseCounter::new(false))
+            .with_line_ending(EndingRule::Require("名詞".to_string()))
+            .with_poem_ending(EndingRule::Forbid("助詞".to_string()));
+        let haiku = generator.generate().unwrap();
+        assert!(haiku
+            .lines
+            .iter()
+            .all(|l| l.tokens().last().unwrap().part_of_speech == "名詞"))
+    }
+
+    #[test]
+    fn return_best_candidate_after_max_attempts() {
+        let mut generator = generator(&["アア", "イイ", "アア"]).with_max_attempts(3);
+        let error = generator.generate_pattern(&[4, 5]).unwrap_err();
+        assert!(matches!(
+            error,
+            GenerationError::Timeout(GenerationTimeout { attempts: 3, best: Some(best) })
+                if best.morae() == vec![4]
+        ))
+    }
+
+    #[test]
+    fn stop_generation_by_timeout() {
+        let mut generator = generator(&["ア", "イ", "ア"]).with_timeout(Duration::from_secs(0));
+        assert!(matches!(
+            generator.generate(),
+            Err(GenerationError::Timeout(GenerationTimeout {
+                attempts: 0,
+                ..
+            }))
+        ))
+    }
+
+    #[test]
+    fn time_out_without_first_tokens() {
+        let mut empty =
+            HaikuGenerator::new(LyrianModel::chars_from(""), JapaneseCounter::new(false));
+        assert!(matches!(
+            empty.generate(),
+            Err(GenerationError::Timeout(GenerationTimeout {
+                best: None,
+                ..
+            }))
+        ));
+
+        let particle = token("ハ", "助詞");
+        let mut particles = HaikuGenerator::new(
+            LyrianModel::from_tokens(&[particle.clone(), particle]),
+            JapaneseCounter::new(false),
+        )
+        .with_max_attempts(3);
+        assert!(matches!(
+            particles.generate(),
+            Err(GenerationError::Timeout(GenerationTimeout {
+                best: None,
+                ..
+            }))
+        ))
+    }
+
+    #[test]
+    fn generate_exact_lines_by_masking() {
+        let mut generator = generator(&["アア", "イ", "アア", "ウウウ", "アア"])
+            .with_masking(true)
+            .with_max_attempts(1);
+        let haiku = generator.generate().unwrap();
+        assert_eq!(haiku.morae(), vec![5, 7, 5])
+    }
+
+    #[test]
+    fn generate_distinct_poems() {
+        let mut generator = generator(&["ア", "イ", "ウ", "ア"]);
+        let poems = generator.generate_many(5);
+        assert_eq!(
+            (
+                poems.len(),
+                poems.iter().all(|(_, l)| (l + (3.0f64).ln()).abs() < 1e-9)
+            ),
+            (3, true)
+        )
+    }
+
+    #[test]
+    fn remove_poems_of_same_tokens() {
+        let mut generator = generator(&["ア", "イ", "ア"]).with_multiset_dedup(true);
+        assert_eq!(generator.generate_many_pattern(&[1, 1], 5).len(), 1)
+    }
+
+    #[test]
+    fn score_poems_by_strictness_and_repetition() {
+        let generator = generator(&["ア", "イ", "ウ", "エ", "ア"]);
+        let poem = |words: &[&str], target: usize| Poem {
+            lines: vec![PoemLine {
+                tokens: words.iter().map(|w| noun(w)).collect(),
+                morae: words.len(),
+                target,
+            }],
+        };
+        let ranked = generator.rank(vec![poem(&["ア", "ア", "イ"], 2), poem(&["ア", "イ"], 2)]);
+        assert_eq!(
+            (
+                ranked[0].0.join(),
+                ranked[1].1.deviation,
+                ranked[1].1.repetitions
+            ),
+            ("アイ".to_string(), 1, 1)
+        )
+    }
+
+    #[test]
+    fn explain_lines_and_transitions() {
+        let mut saijiki = Saijiki::empty();
+        saijiki.insert("ユキ", Season::Winter);
+        let generator = generator(&["ユキ", "ヤ", "ユキ"])
+            .with_saijiki(saijiki)
+            .with_kireji(&["や"]);
+        let poem = Poem {
+            lines: vec![
+                PoemLine::new(vec![noun("ユキ"), noun("ヤ")], 3, 3),
+                PoemLine::new(vec![noun("ユキ")], 2, 3),
+            ],
+        };
+        let report = generator.explain(&poem);
+        assert_eq!(
+            (
+                report.lines[0].kireji.clone(),
+                report.lines[1].jitarazu,
+                report.kigo.len(),
+                report.transitions[1].probability
+            ),
+            (Some("ヤ".to_string()), true, 2, 1.0)
+        )
+    }
+
+    #[test]
+    fn exclude_part_of_speech_of_homograph() {
+        let verb = LyrianToken::new(
+            "カミ".to_string(),
+            "カミ".to_string(),
+            "カミ".to_string(),
+            "動詞".to_string(),
+        );
+        let mut tokens = vec![noun("カミ"), noun("ア"), verb.clone(), noun("ア")];
+        tokens.extend(vec![noun("カミ"), noun("ア"), noun("カミ")]);
+        let model = LyrianModel::from_tokens(&tokens);
+        let mut generator = HaikuGenerator::new(model, JapaneseCounter::new(false))
+            .with_excluded_part_of_speech("動詞");
+        let poem = generator.generate_pattern(&[3, 3]).unwrap();
+        assert!(poem
+            .lines
+            .iter()
+            .flat_map(|l| l.tokens())
+            .all(|t| *t != verb))
+    }
+
+    #[test]
+    fn find_haiku_by_another_tokenizer() {
+        use crate::poem::HaikuFinder;
+
+        let chars = |text: &str| -> Result<Vec<LyrianToken>, String> {
+            Ok(text.chars().map(|c| noun(&c.to_string())).collect())
+        };
+        let found = HaikuFinder::new()
+            .find_by("アイウエオカキクケコサシスセソタチ", &chars)
+            .unwrap();
+        assert_eq!(
+            found[0].haiku.join(),
+            "アイウエオ\nカキクケコサシ\nスセソタチ"
+        )
+    }
+
+    #[test]
+    fn find_haiku_after_halfwidth_kana() {
+        use crate::normalize::fold_halfwidth_kana;
+        use crate::poem::HaikuFinder;
+
+        let words = |text: &str| -> Result<Vec<LyrianToken>, String> {
+            Ok(fold_halfwidth_kana(text).split(' ').map(noun).collect())
+        };
+        let text = "ｶﾞa アイウエオ カキクケコサシ スセソタチ";
+        let found = HaikuFinder::new().find_by(text, &words).unwrap();
+        assert_eq!(
+            &text[found[0].start..found[0].end],
+            "アイウエオ カキクケコサシ スセソタチ"
+        )
+    }
+
+    #[cfg(feature = "tokenizer")]
+    #[test]
+    fn find_haiku_in_sentences() {
+        use crate::poem::HaikuFinder;
+
+        let text = "すもも。古池や蛙飛び込む水の音。";
+        let found = HaikuFinder::new()
+            .with_sentence_aligned(true)
+            .find(text)
+            .unwrap();
+        assert_eq!(
+            (found.len(), &text[found[0].start..found[0].end]),
+            (1, "古池や蛙飛び込む水の音")
+        )
+    }
+
+    #[test]
+    fn generate_rhymed_lines() {
+        let mut generator = generator(&["カ", "ナ", "イ", "サ", "ミ", "タ", "カ"]);
+        let verse = generator.generate_rhymed(3, 3, 2).unwrap();
+        let tails = verse
+            .lines
+            .iter()
+            .map(|l| line_sounds(l.tokens())[1..].to_vec())
+            .collect::<Vec<_>>();
+        assert!(tails.iter().all(|t| *t == tails[0]))
+    }
+
+    #[test]
+    fn regenerate_line_following_previous_line() {
+        let mut generator = generator(&["ア", "イ", "ウ", "エ", "オ", "ア"]);
+        let mut haiku = generator.generate().unwrap();
+        let (first, last) = (haiku.lines[0].clone(), haiku.lines[2].clone());
+        generator.regenerate_line(&mut haiku, 1).unwrap();
+        assert!(
+            generator.regenerate_line(&mut haiku, 3)
+                == Err(GenerationError::NoLine { line: 3, lines: 3 })
+                && haiku.lines[0] == first
+                && haiku.lines[2] == last
+                && "アイウエオ".repeat(5).contains(
+                    &haiku.lines[..2]
+                        .iter()
+                        .map(|l| l.join())
+                        .collect::<String>()
+                )
+        )
+    }
+
+    #[test]
+    fn prefer_tokens_filling_lines() {
+        let mut generator = generator(&["ア", "イイ", "ア", "ア", "イイ", "イイ", "ア"])
+            .with_tolerance(1)
+            .with_mora_bias(1e12);
+        assert!(generator.generate().unwrap().is_strict())
+    }
+}
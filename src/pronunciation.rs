@@ -0,0 +1,821 @@
+//! Counting the number of pronunciations.
+
+use std::error::Error;
+use std::fmt;
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::morphological_analysis::LyrianToken;
+#[cfg(feature = "tokenizer")]
+use crate::reading::ReadingOverrides;
+#[cfg(feature = "tokenizer")]
+use crate::tokenize::tokenize;
+use crate::tokenize::Tokenizer;
+
+use crate::chars::{
+    consonant_row_of, hiragana_to_katakana, is_kana, vowel_of, Row, Vowel, LONG_VOWEL, LOWER_CASE,
+    SOKUON, SYLLABLE_CHARS, SYMBOLS, VOICELESS,
+};
+
+/// Rules of counting the number of pronunciations.
+///
+/// The text is scanned by grapheme clusters, so a character followed by
+/// combining marks, like `カ\u{3099}`, and an emoji sequence joined by ZWJ are
+/// treated as single characters, classified by their first code point.
+///
+/// The default rules use the character classes defined in this crate. Each
+/// class is a plain [`Vec<char>`]; push characters to it to extend the rules.
+///
+/// ```rust
+/// use lyrian::pronunciation::CountingRules;
+///
+/// let mut rules = CountingRules::default();
+/// rules.symbols.push('♪');
+/// assert_eq!(rules.count("ラララ♪", false), 3);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CountingRules {
+    /// Characters that are pronounced as a set with the previous character.
+    pub lower_case: Vec<char>,
+
+    /// Characters that are ignored when counting by syllable unit.
+    pub syllable_chars: Vec<char>,
+
+    /// Symbols that cannot be counted as pronunciation numbers.
+    pub symbols: Vec<char>,
+
+    /// How to count the long vowel mark (`ー`).
+    pub long_vowel: MoraPolicy,
+
+    /// How to count the geminate consonant mark (`ッ`).
+    pub sokuon: MoraPolicy,
+
+    /// How to handle whitespace.
+    pub whitespace: NonKanaPolicy,
+
+    /// How to handle ASCII characters other than whitespace and symbols.
+    pub ascii: NonKanaPolicy,
+
+    /// How to handle emoji.
+    pub emoji: NonKanaPolicy,
+
+    /// How to handle the other characters which are not kana, such as kanji.
+    pub other: NonKanaPolicy,
+
+    /// Whether morae whose vowels are devoiced are not counted.
+    ///
+    /// The vowel `i` or `u` of [`VOICELESS`](crate::chars::VOICELESS) is
+    /// devoiced before a voiceless consonant or at the end of the text, as in
+    /// `デス` and `キタ`. Two morae in a row are not devoiced. This gives the
+    /// count of the spoken rhythm rather than the strict one.
+    pub devoicing: bool,
+}
+
+/// Policy of counting a special character.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MoraPolicy {
+    /// Counted by mora unit, and ignored by syllable unit.
+    #[default]
+    ByUnit,
+
+    /// Always counted.
+    Count,
+
+    /// Always ignored.
+    Ignore,
+}
+
+/// Policy of handling a character which is not kana.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NonKanaPolicy {
+    /// Removed from the text, as symbols are.
+    Ignore,
+
+    /// Makes [`CountingRules::try_count()`] fail.
+    Error,
+
+    /// Kept in the text as a unit which has no pronunciation.
+    CountAsZero,
+}
+
+/// Classification of a character in counting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CharClass {
+    /// Counted as a pronunciation.
+    Counted,
+
+    /// Pronounced as a set with the previous character.
+    LowerCase,
+
+    /// A special mora (`ン`, `ッ`, `ー`), which is ignored by syllable unit.
+    Special,
+
+    /// A symbol, which is ignored.
+    Symbol,
+
+    /// A character other than kana which is ignored by [`NonKanaPolicy::Ignore`].
+    Ignored,
+
+    /// A character other than kana which is kept by
+    /// [`NonKanaPolicy::CountAsZero`].
+    Zero,
+
+    /// A character other than kana which can not be counted by
+    /// [`NonKanaPolicy::Error`].
+    Uncountable,
+
+    /// A mora whose vowel is devoiced, which is not counted by
+    /// [`CountingRules::devoicing`].
+    Devoiced,
+}
+
+/// The result of [`CountingRules::analyze()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PronunciationReport {
+    /// The characters of the text.
+    pub chars: Vec<CharReport>,
+
+    /// The number of pronunciations.
+    pub total: usize,
+}
+
+/// How a character is counted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CharReport {
+    /// The character, which is a grapheme cluster.
+    pub character: String,
+
+    /// The byte offset of the character.
+    pub offset: usize,
+
+    /// The classification of the character.
+    pub class: CharClass,
+
+    /// The running total of pronunciations up to the character.
+    pub total: usize,
+}
+
+/// Error returned when the text contains a character which can not be
+/// counted, such as kanji or Latin letters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UncountableError {
+    /// The character which can not be counted, which is a grapheme cluster.
+    pub character: String,
+
+    /// The byte offset of the character.
+    pub offset: usize,
+
+    /// The offset of the character in grapheme clusters.
+    pub char_offset: usize,
+}
+
+impl fmt::Display for UncountableError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Could not count the pronunciation of '{}' at {}.",
+            self.character, self.char_offset
+        )
+    }
+}
+
+impl Error for UncountableError {}
+
+impl Default for CountingRules {
+    fn default() -> CountingRules {
+        CountingRules {
+            lower_case: LOWER_CASE.to_vec(),
+            syllable_chars: SYLLABLE_CHARS.to_vec(),
+            symbols: SYMBOLS.to_vec(),
+            long_vowel: MoraPolicy::default(),
+            sokuon: MoraPolicy::default(),
+            whitespace: NonKanaPolicy::Ignore,
+            ascii: NonKanaPolicy::Error,
+            emoji: NonKanaPolicy::Error,
+            other: NonKanaPolicy::Error,
+            devoicing: false,
+        }
+    }
+}
+
+impl CountingRules {
+    /// Calculates the number of pronunciations of kana text.
+    ///
+    /// - syllable: [`bool`]
+    ///     - Will calculate the number by syllable unit.
+    ///
+    /// If you set `false` to all the arguments, you will get the number by
+    /// mora unit.
+    pub fn count(&self, text: &str, syllable: bool) -> usize {
+        let count = text
+            .graphemes(true)
+            .filter(|g| self.is_counted(head(g), syllable))
+            .count();
+        count - self.devoiced(text).len()
+    }
+
+    /// Calculates the number of pronunciations of kana text, failing on the
+    /// first character which can not be counted.
+    ///
+    /// See [`count()`](#method.count) for the arguments.
+    pub fn try_count(&self, text: &str, syllable: bool) -> Result<usize, UncountableError> {
+        for (char_offset, (offset, g)) in text.grapheme_indices(true).enumerate() {
+            if self.classify(head(g)) == CharClass::Uncountable {
+                return Err(UncountableError {
+                    character: g.to_string(),
+                    offset,
+                    char_offset,
+                });
+            }
+        }
+        Ok(self.count(text, syllable))
+    }
+
+    /// Splits kana text into the units of pronunciation.
+    ///
+    /// A character which is not counted, such as a lower case character, is
+    /// joined to the previous unit, and symbols are removed. A character
+    /// kept by [`NonKanaPolicy::CountAsZero`] becomes a unit by itself.
+    ///
+    /// - syllable: [`bool`]
+    ///     - Will split the text by syllable unit.
+    ///
+    /// ```rust
+    /// use lyrian::pronunciation::CountingRules;
+    ///
+    /// let rules = CountingRules::default();
+    /// assert_eq!(rules.split("キャット", false), vec!["キャ", "ッ", "ト"]);
+    /// assert_eq!(rules.split("キャット", true), vec!["キャッ", "ト"]);
+    /// ```
+    pub fn split<'a>(&self, text: &'a str, syllable: bool) -> Vec<&'a str> {
+        let mut units = Vec::new();
+        let mut start = None;
+        for (i, g) in text.grapheme_indices(true) {
+            let c = head(g);
+            match self.classify(c) {
+                CharClass::Symbol | CharClass::Ignored | CharClass::Uncountable => {
+                    if let Some(s) = start.take() {
+                        units.push(&text[s..i]);
+                    }
+                }
+                CharClass::Zero => {
+                    if let Some(s) = start.take() {
+                        units.push(&text[s..i]);
+                    }
+                    units.push(g);
+                }
+                _ => {
+                    if start.is_none() || self.is_counted(c, syllable) {
+                        if let Some(s) = start {
+                            units.push(&text[s..i]);
+                        }
+                        start = Some(i);
+                    }
+                }
+            }
+        }
+        if let Some(s) = start {
+            units.push(&text[s..]);
+        }
+        units
+    }
+
+    /// Reports how each character of the text is counted.
+    ///
+    /// - syllable: [`bool`]
+    ///     - Will calculate the number by syllable unit.
+    pub fn analyze(&self, text: &str, syllable: bool) -> PronunciationReport {
+        let mut total = 0;
+        let mut chars = Vec::new();
+        let devoiced = self.devoiced(text);
+        for (offset, g) in text.grapheme_indices(true) {
+            let c = head(g);
+            let class = if devoiced.contains(&offset) {
+                CharClass::Devoiced
+            } else {
+                if self.is_counted(c, syllable) {
+                    total += 1;
+                }
+                self.classify(c)
+            };
+            chars.push(CharReport {
+                character: g.to_string(),
+                offset,
+                class,
+                total,
+            });
+        }
+        PronunciationReport { chars, total }
+    }
+
+    /// Classifies the character by the rules.
+    pub fn classify(&self, c: char) -> CharClass {
+        if self.symbols.contains(&c) {
+            CharClass::Symbol
+        } else if self.lower_case.contains(&c) {
+            CharClass::LowerCase
+        } else if !is_kana(c) {
+            let policy = if c.is_whitespace() {
+                self.whitespace
+            } else if c.is_ascii() {
+                self.ascii
+            } else if is_emoji(c) {
+                self.emoji
+            } else {
+                self.other
+            };
+
+            match policy {
+                NonKanaPolicy::Ignore => CharClass::Ignored,
+                NonKanaPolicy::Error => CharClass::Uncountable,
+                NonKanaPolicy::CountAsZero => CharClass::Zero,
+            }
+        } else {
+            // The hiragana are looked up as katakana, e.g. `ん` as `ン`.
+            let c = hiragana_to_katakana(c);
+            if c == LONG_VOWEL || SOKUON.contains(&c) || self.syllable_chars.contains(&c) {
+                CharClass::Special
+            } else {
+                CharClass::Counted
+            }
+        }
+    }
+
+    /// Returns the byte offsets of the morae whose vowels are devoiced.
+    ///
+    /// The offsets are empty unless [`devoicing`](#structfield.devoicing) is
+    /// enabled.
+    fn devoiced(&self, text: &str) -> Vec<usize> {
+        let mut offsets = Vec::new();
+        if !self.devoicing {
+            return offsets;
+        }
+
+        let mut previous = false;
+        let morae = self.split(text, false);
+        for (i, mora) in morae.iter().enumerate() {
+            let c = hiragana_to_katakana(head(mora));
+            let voiceless = VOICELESS.contains(&c)
+                && matches!(vowel_of(mora), Some(Vowel::I | Vowel::U))
+                && self.is_counted(c, false);
+            let before_voiceless = match morae.get(i + 1) {
+                Some(next) => matches!(
+                    consonant_row_of(next),
+                    Some(Row::K | Row::S | Row::T | Row::H | Row::P)
+                ),
+                None => true,
+            };
+
+            previous = voiceless && before_voiceless && !previous;
+            if previous {
+                offsets.push(mora.as_ptr() as usize - text.as_ptr() as usize);
+            }
+        }
+        offsets
+    }
+
+    /// Returns whether the character is counted as a pronunciation.
+    fn is_counted(&self, c: char, syllable: bool) -> bool {
+        match self.classify(c) {
+            CharClass::Counted => true,
+            CharClass::Special => {
+                let c = hiragana_to_katakana(c);
+                let policy = match c {
+                    LONG_VOWEL => self.long_vowel,
+                    _ if SOKUON.contains(&c) => self.sokuon,
+                    _ => MoraPolicy::ByUnit,
+                };
+
+                match policy {
+                    MoraPolicy::ByUnit => !(syllable && self.syllable_chars.contains(&c)),
+                    MoraPolicy::Count => true,
+                    MoraPolicy::Ignore => false,
+                }
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Counter of the units of pronunciation, such as morae or syllables.
+///
+/// The generators count the lengths of lyrics and poems through this trait,
+/// so they can target languages other than Japanese by another counter.
+///
+/// ```rust
+/// use lyrian::pronunciation::{JapaneseCounter, MoraCounter};
+///
+/// assert_eq!(JapaneseCounter::new(false).count("キャット"), 3);
+/// assert_eq!(JapaneseCounter::new(true).count("キャット"), 2);
+/// ```
+pub trait MoraCounter {
+    /// Calculates the number of units of the text.
+    fn count(&self, text: &str) -> usize;
+
+    /// Calculates the number of units of a token.
+    ///
+    /// The reading of the token is counted by default, or its word if the
+    /// reading is unknown.
+    fn count_token(&self, token: &LyrianToken) -> usize {
+        if token.mora == "unknown" {
+            self.count(&token.word)
+        } else {
+            self.count(&token.mora)
+        }
+    }
+}
+
+/// Counter of Japanese by mora or syllable unit.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct JapaneseCounter {
+    /// The rules of counting.
+    pub rules: CountingRules,
+
+    /// Whether to count by syllable unit.
+    pub syllable: bool,
+}
+
+impl JapaneseCounter {
+    /// Creates a new instance of [`JapaneseCounter`] with the default rules.
+    ///
+    /// - syllable: [`bool`]
+    ///     - Will calculate the number by syllable unit.
+    pub fn new(syllable: bool) -> JapaneseCounter {
+        JapaneseCounter {
+            rules: CountingRules::default(),
+            syllable,
+        }
+    }
+
+    /// Creates a new instance of [`JapaneseCounter`] with the given rules.
+    pub fn with_rules(rules: CountingRules, syllable: bool) -> JapaneseCounter {
+        JapaneseCounter { rules, syllable }
+    }
+}
+
+impl MoraCounter for JapaneseCounter {
+    fn count(&self, text: &str) -> usize {
+        self.rules.count(text, self.syllable)
+    }
+
+    fn count_token(&self, token: &LyrianToken) -> usize {
+        token.length_with(&self.rules, self.syllable)
+    }
+}
+
+/// Returns the first code point of a grapheme cluster.
+fn head(grapheme: &str) -> char {
+    grapheme.chars().next().unwrap_or_default()
+}
+
+/// Returns whether the character is used for emoji.
+fn is_emoji(c: char) -> bool {
+    matches!(
+        c,
+        '\u{200D}'
+            | '\u{20E3}'
+            | '\u{2600}'..='\u{27BF}'
+            | '\u{2B00}'..='\u{2BFF}'
+            | '\u{FE00}'..='\u{FE0F}'
+            | '\u{1F000}'..='\u{1FAFF}'
+            | '\u{E0020}'..='\u{E007F}'
+    )
+}
+
+/// Calculates the number of morae of kana text with the default rules.
+pub fn count_morae(text: &str) -> usize {
+    CountingRules::default().count(text, false)
+}
+
+/// Calculates the number of syllables of kana text with the default rules.
+pub fn count_syllables(text: &str) -> usize {
+    CountingRules::default().count(text, true)
+}
+
+/// Calculates the number of morae of kana text with the default rules,
+/// failing on the first character which can not be counted.
+pub fn try_count_morae(text: &str) -> Result<usize, UncountableError> {
+    CountingRules::default().try_count(text, false)
+}
+
+/// Splits kana text into morae with the default rules.
+pub fn split_morae(text: &str) -> Vec<&str> {
+    CountingRules::default().split(text, false)
+}
+
+/// Splits kana text into syllables with the default rules.
+pub fn split_syllables(text: &str) -> Vec<&str> {
+    CountingRules::default().split(text, true)
+}
+
+/// Calculates the number of morae of Japanese text which contains kanji.
+///
+/// The text is tokenized in morphological analysis, and the morae of the
+/// readings of the tokens are summed. The tokens whose readings are unknown
+/// are counted by their surface forms.
+///
+/// ```rust
+/// use lyrian::pronunciation::count_morae_tokenized;
+///
+/// assert_eq!(count_morae_tokenized("古池や").unwrap(), 5);
+/// ```
+#[cfg(feature = "tokenizer")]
+pub fn count_morae_tokenized(text: &str) -> Result<usize, String> {
+    count_morae_tokenized_with(text, &ReadingOverrides::new())
+}
+
+/// Calculates the number of morae of Japanese text which contains kanji,
+/// with the readings overridden by the user.
+///
+/// See [`count_morae_tokenized()`] for the details.
+#[cfg(feature = "tokenizer")]
+pub fn count_morae_tokenized_with(
+    text: &str,
+    overrides: &ReadingOverrides,
+) -> Result<usize, String> {
+    let mut tokens = tokenize(text)?;
+    overrides.apply(&mut tokens);
+    Ok(sum_morae(&tokens))
+}
+
+/// Calculates the number of morae of Japanese text tokenized by the
+/// tokenizer.
+///
+/// See [`count_morae_tokenized()`] for the details.
+pub fn count_morae_by<T: Tokenizer + ?Sized>(text: &str, tokenizer: &T) -> Result<usize, String> {
+    Ok(sum_morae(&tokenizer.tokenize(text)?))
+}
+
+/// Sums the morae of the readings of the tokens, or of the surface forms if
+/// the readings are unknown.
+fn sum_morae(tokens: &[LyrianToken]) -> usize {
+    tokens
+        .iter()
+        .map(|t| {
+            if t.mora == "unknown" {
+                count_morae(&t.word)
+            } else {
+                count_morae(&t.mora)
+            }
+        })
+        .sum()
+}
+
+/// Reports how each character of the text is counted by mora unit with the
+/// default rules.
+pub fn analyze(text: &str) -> PronunciationReport {
+    CountingRules::default().analyze(text, false)
+}
+
+#[cfg(test)]
+mod pronunciation_test {
+    use crate::pronunciation::{
+        analyze, count_morae, count_syllables, split_morae, split_syllables, try_count_morae,
+        CharClass, CountingRules, MoraPolicy, NonKanaPolicy, UncountableError,
+    };
+
+    #[test]
+    fn ignore_devoiced_vowels() {
+        let rules = CountingRules {
+            devoicing: true,
+            ..CountingRules::default()
+        };
+        assert_eq!(
+            (
+                rules.count("デス", false),
+                rules.count("シキ", false),
+                rules.count("ヒカリ", false),
+                rules.count("ダイスキ", false)
+            ),
+            (1, 1, 2, 3)
+        )
+    }
+
+    #[test]
+    fn report_devoiced_vowels() {
+        let rules = CountingRules {
+            devoicing: true,
+            ..CountingRules::default()
+        };
+        let report = rules.analyze("マスク", false);
+        assert_eq!(
+            (report.chars[1].class, report.chars[2].class, report.total),
+            (CharClass::Devoiced, CharClass::Counted, 2)
+        )
+    }
+
+    #[test]
+    fn count_morae_of_kana() {
+        assert_eq!(count_morae("キャット"), 3)
+    }
+
+    #[test]
+    fn count_syllables_of_kana() {
+        assert_eq!(count_syllables("キャット"), 2)
+    }
+
+    #[test]
+    fn count_syllables_of_hiragana_as_katakana() {
+        assert_eq!(count_syllables("きって"), count_syllables("キッテ"));
+        assert_eq!(split_syllables("ほんだ"), vec!["ほん", "だ"]);
+        assert_eq!(split_syllables("ホンダ"), vec!["ホン", "ダ"])
+    }
+
+    #[test]
+    fn count_small_kana_added_to_table() {
+        assert_eq!(count_morae("クヮシ"), 2)
+    }
+
+    #[test]
+    fn count_with_customized_rules() {
+        let mut rules = CountingRules::default();
+        rules.symbols.push('♪');
+        rules.lower_case.retain(|c| *c != 'ァ');
+        assert_eq!(rules.count("ファ♪", false), 2)
+    }
+
+    #[test]
+    fn ignore_long_vowel_by_mora_unit() {
+        let rules = CountingRules {
+            long_vowel: MoraPolicy::Ignore,
+            ..CountingRules::default()
+        };
+        assert_eq!(rules.count("ラーメン", false), 3)
+    }
+
+    #[test]
+    fn count_long_vowel_by_syllable_unit() {
+        let rules = CountingRules {
+            long_vowel: MoraPolicy::Count,
+            ..CountingRules::default()
+        };
+        assert_eq!(rules.count("ラーメン", true), 3)
+    }
+
+    #[test]
+    fn ignore_sokuon_by_mora_unit() {
+        let rules = CountingRules {
+            sokuon: MoraPolicy::Ignore,
+            ..CountingRules::default()
+        };
+        assert_eq!(rules.count("きって", false), 2)
+    }
+
+    #[test]
+    fn split_kana_into_morae() {
+        assert_eq!(
+            split_morae("シャーベット"),
+            vec!["シャ", "ー", "ベ", "ッ", "ト"]
+        )
+    }
+
+    #[test]
+    fn split_kana_into_syllables() {
+        assert_eq!(
+            split_syllables("シャーベット"),
+            vec!["シャー", "ベッ", "ト"]
+        )
+    }
+
+    #[test]
+    fn remove_symbols_when_splitting() {
+        assert_eq!(split_morae("ラ、ララ。"), vec!["ラ", "ラ", "ラ"])
+    }
+
+    #[test]
+    fn analyze_each_character() {
+        let report = analyze("キャン、空");
+        let classes = report.chars.iter().map(|c| c.class).collect::<Vec<_>>();
+        let totals = report.chars.iter().map(|c| c.total).collect::<Vec<_>>();
+        assert_eq!(
+            classes,
+            vec![
+                CharClass::Counted,
+                CharClass::LowerCase,
+                CharClass::Special,
+                CharClass::Symbol,
+                CharClass::Uncountable,
+            ]
+        );
+        assert_eq!((totals, report.total), (vec![1, 1, 2, 2, 2], 2))
+    }
+
+    #[test]
+    fn do_not_count_uncountable_characters() {
+        assert_eq!(count_morae("東京タワー"), 3)
+    }
+
+    #[test]
+    fn return_position_of_uncountable_character() {
+        let expected = UncountableError {
+            character: "空".to_string(),
+            offset: 6,
+            char_offset: 2,
+        };
+        assert_eq!(try_count_morae("アオ空"), Err(expected))
+    }
+
+    #[test]
+    fn try_to_count_kana() {
+        assert_eq!(try_count_morae("アオゾラ。"), Ok(4))
+    }
+
+    #[test]
+    fn ignore_whitespace_by_default() {
+        assert_eq!(try_count_morae("アオ　ゾラ"), Ok(4))
+    }
+
+    #[test]
+    fn keep_characters_counted_as_zero() {
+        let rules = CountingRules {
+            emoji: NonKanaPolicy::CountAsZero,
+            ..CountingRules::default()
+        };
+        assert_eq!(
+            (
+                rules.try_count("ネコ🐈", false),
+                rules.split("ネコ🐈", false)
+            ),
+            (Ok(2), vec!["ネ", "コ", "🐈"])
+        )
+    }
+
+    #[test]
+    fn fail_on_ascii_by_policy() {
+        let rules = CountingRules {
+            ascii: NonKanaPolicy::Error,
+            emoji: NonKanaPolicy::Ignore,
+            ..CountingRules::default()
+        };
+        assert_eq!(
+            (
+                rules.try_count("ネコ🐈", false),
+                rules.try_count("ネコcat", false).map_err(|e| e.char_offset)
+            ),
+            (Ok(2), Err(2))
+        )
+    }
+
+    #[test]
+    fn count_decomposed_kana_as_one_character() {
+        assert_eq!(
+            (
+                try_count_morae("カ\u{3099}ラス"),
+                split_morae("カ\u{3099}ラス")
+            ),
+            (Ok(3), vec!["カ\u{3099}", "ラ", "ス"])
+        )
+    }
+
+    #[test]
+    fn keep_emoji_sequence_as_one_character() {
+        let rules = CountingRules {
+            emoji: NonKanaPolicy::CountAsZero,
+            ..CountingRules::default()
+        };
+        let family = "\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}";
+        assert_eq!(
+            rules.split(&format!("イエ{}", family), false),
+            vec!["イ", "エ", family]
+        )
+    }
+
+    #[cfg(feature = "tokenizer")]
+    #[test]
+    fn count_morae_of_text_with_kanji() {
+        use crate::pronunciation::count_morae_tokenized;
+
+        assert_eq!(count_morae_tokenized("古池や蛙飛び込む水の音"), Ok(17))
+    }
+
+    #[cfg(feature = "tokenizer")]
+    #[test]
+    fn count_morae_with_overridden_readings() {
+        use crate::pronunciation::count_morae_tokenized_with;
+        use crate::reading::ReadingOverrides;
+
+        let mut overrides = ReadingOverrides::new();
+        overrides.insert("私", "わたくし");
+        assert_eq!(count_morae_tokenized_with("私は猫", &overrides), Ok(7))
+    }
+
+    #[test]
+    fn count_tokens_by_counter() {
+        use crate::morphological_analysis::LyrianToken;
+        use crate::pronunciation::{JapaneseCounter, MoraCounter};
+
+        let token = LyrianToken::new(
+            "大空".to_string(),
+            "オオゾラ".to_string(),
+            "オーゾラ".to_string(),
+            "名詞".to_string(),
+        );
+        assert_eq!(
+            (
+                JapaneseCounter::new(false).count_token(&token),
+                JapaneseCounter::new(true).count_token(&token)
+            ),
+            (4, 3)
+        )
+    }
+}
@@ -0,0 +1,540 @@
+//! Readings of the text which contains kanji.
+
+use std::collections::BTreeMap;
+
+#[cfg(feature = "tokenizer")]
+use std::cell::RefCell;
+
+#[cfg(feature = "tokenizer")]
+use lindera::tokenizer::Tokenizer;
+
+use crate::chars::{hiragana_to_katakana, is_kana, is_kanji, katakana_to_hiragana};
+#[cfg(feature = "tokenizer")]
+use crate::morphological_analysis::LyrianToken;
+use crate::pronunciation::count_morae;
+#[cfg(feature = "tokenizer")]
+use crate::tokenize::tokenize_with;
+
+/// Dictionary which gives the katakana readings of surface forms.
+pub trait ReadingDictionary {
+    /// Converts the text into its reading.
+    ///
+    /// The parts of the text whose readings are unknown are left as they are.
+    fn read(&self, text: &str) -> String;
+
+    /// Calculates the number of morae of the text by its reading.
+    fn count_morae(&self, text: &str) -> usize {
+        count_morae(&self.read(text))
+    }
+}
+
+/// Dictionary of words and their readings given by the user.
+///
+/// The text is read by the longest match of the words.
+///
+/// ```rust
+/// use lyrian::reading::{ReadingDictionary, WordDictionary};
+///
+/// let mut dict = WordDictionary::new();
+/// dict.insert("東京", "トウキョウ");
+/// assert_eq!(dict.count_morae("東京タワー"), 7);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WordDictionary {
+    words: BTreeMap<String, String>,
+    max_len: usize,
+}
+
+impl WordDictionary {
+    /// Creates a new empty instance of [`WordDictionary`].
+    pub fn new() -> WordDictionary {
+        WordDictionary::default()
+    }
+
+    /// Adds a word and its reading.
+    pub fn insert(&mut self, surface: &str, reading: &str) {
+        self.max_len = self.max_len.max(surface.chars().count());
+        self.words.insert(surface.to_string(), reading.to_string());
+    }
+
+    /// Returns the reading of the word.
+    pub fn get(&self, surface: &str) -> Option<&str> {
+        self.words.get(surface).map(|r| r.as_str())
+    }
+}
+
+impl WordDictionary {
+    /// Scans the text by the longest match of the words, and calls `f` with
+    /// each matched word and its reading, or with each unmatched character.
+    fn scan<'a, F: FnMut(&'a str, Option<&str>)>(&self, text: &'a str, mut f: F) {
+        let chars = text.char_indices().map(|(i, _)| i).collect::<Vec<usize>>();
+        let mut i = 0;
+        while i < chars.len() {
+            let longest = (1..=self.max_len.min(chars.len() - i))
+                .rev()
+                .find_map(|len| {
+                    let end = chars.get(i + len).copied().unwrap_or(text.len());
+                    self.get(&text[chars[i]..end]).map(|r| (len, end, r))
+                });
+
+            match longest {
+                Some((len, end, r)) => {
+                    f(&text[chars[i]..end], Some(r));
+                    i += len;
+                }
+                None => {
+                    let end = chars.get(i + 1).copied().unwrap_or(text.len());
+                    f(&text[chars[i]..end], None);
+                    i += 1;
+                }
+            }
+        }
+    }
+}
+
+impl ReadingDictionary for WordDictionary {
+    fn read(&self, text: &str) -> String {
+        let mut reading = String::with_capacity(text.len());
+        self.scan(text, |surface, r| reading.push_str(r.unwrap_or(surface)));
+        reading
+    }
+}
+
+/// Readings given by the user which take precedence over any dictionary.
+///
+/// This is useful to choose a reading for a style, e.g. `私` as `ワタクシ`
+/// rather than `ワタシ`. The text is read by the longest match of the
+/// overridden words, and the rest is read by the dictionary.
+///
+/// ```rust
+/// use lyrian::reading::{ReadingDictionary, ReadingOverrides, WordDictionary};
+///
+/// let mut dict = WordDictionary::new();
+/// dict.insert("私", "ワタシ");
+/// dict.insert("猫", "ネコ");
+///
+/// let mut overrides = ReadingOverrides::new();
+/// overrides.insert("私", "わたくし");
+/// assert_eq!(overrides.read_with(&dict, "私は猫"), "わたくしはネコ");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReadingOverrides {
+    words: WordDictionary,
+}
+
+impl ReadingOverrides {
+    /// Creates a new empty instance of [`ReadingOverrides`].
+    pub fn new() -> ReadingOverrides {
+        ReadingOverrides::default()
+    }
+
+    /// Overrides the reading of a word.
+    pub fn insert(&mut self, surface: &str, reading: &str) {
+        self.words.insert(surface, reading);
+    }
+
+    /// Returns the overridden reading of the word.
+    pub fn get(&self, surface: &str) -> Option<&str> {
+        self.words.get(surface)
+    }
+
+    /// Returns whether no reading is overridden.
+    pub fn is_empty(&self) -> bool {
+        self.words.words.is_empty()
+    }
+
+    /// Reads the text with the overrides, and then with the dictionary.
+    pub fn read_with<D: ReadingDictionary + ?Sized>(&self, dict: &D, text: &str) -> String {
+        let mut reading = String::with_capacity(text.len());
+        let mut rest = String::new();
+        self.words
+            .scan(text, |surface, overridden| match overridden {
+                Some(r) => {
+                    if !rest.is_empty() {
+                        reading.push_str(&dict.read(&std::mem::take(&mut rest)));
+                    }
+                    reading.push_str(r);
+                }
+                None => rest.push_str(surface),
+            });
+        if !rest.is_empty() {
+            reading.push_str(&dict.read(&rest));
+        }
+        reading
+    }
+
+    /// Replaces the readings of the tokens whose words are overridden.
+    #[cfg(feature = "tokenizer")]
+    pub(crate) fn apply(&self, tokens: &mut [LyrianToken]) {
+        if self.is_empty() {
+            return;
+        }
+        for token in tokens {
+            if let Some(r) = self.get(&token.word) {
+                let katakana = r.chars().map(hiragana_to_katakana).collect::<String>();
+                token.mora = katakana.clone();
+                token.syllable = katakana;
+            }
+        }
+    }
+}
+
+impl ReadingDictionary for ReadingOverrides {
+    fn read(&self, text: &str) -> String {
+        self.words.read(text)
+    }
+}
+
+/// Dictionary of the readings by IPADIC, which is bundled with lindera.
+///
+/// ```rust
+/// use lyrian::reading::{IpadicDictionary, ReadingDictionary};
+///
+/// let dict = IpadicDictionary::new().unwrap();
+/// assert_eq!(dict.count_morae("東京"), 4);
+/// ```
+#[cfg(feature = "tokenizer")]
+pub struct IpadicDictionary {
+    tokenizer: RefCell<Tokenizer>,
+}
+
+#[cfg(feature = "tokenizer")]
+impl IpadicDictionary {
+    /// Creates a new instance of [`IpadicDictionary`].
+    ///
+    /// Loading the dictionary takes a while, so reuse the instance.
+    pub fn new() -> Result<IpadicDictionary, String> {
+        match Tokenizer::new() {
+            Ok(tokenizer) => Ok(IpadicDictionary {
+                tokenizer: RefCell::new(tokenizer),
+            }),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+}
+
+#[cfg(feature = "tokenizer")]
+impl ReadingDictionary for IpadicDictionary {
+    fn read(&self, text: &str) -> String {
+        match tokenize_with(&mut self.tokenizer.borrow_mut(), text) {
+            Ok(tokens) => tokens
+                .iter()
+                .map(|t| {
+                    if t.mora == "unknown" {
+                        &t.word
+                    } else {
+                        &t.mora
+                    }
+                })
+                .fold(String::new(), |acc, cur| acc + cur),
+            Err(_) => text.to_string(),
+        }
+    }
+}
+
+/// Text annotated with furigana.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FuriganaText {
+    /// The segments of the text.
+    pub segments: Vec<FuriganaSegment>,
+}
+
+/// A segment of [`FuriganaText`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FuriganaSegment {
+    /// The base text.
+    pub surface: String,
+
+    /// The reading given by the furigana, if any.
+    pub reading: Option<String>,
+}
+
+impl FuriganaText {
+    /// Returns the text without furigana.
+    pub fn surface(&self) -> String {
+        self.segments
+            .iter()
+            .fold(String::new(), |acc, cur| acc + &cur.surface)
+    }
+
+    /// Returns the text whose annotated parts are replaced with their
+    /// readings.
+    pub fn reading(&self) -> String {
+        self.segments.iter().fold(String::new(), |acc, cur| {
+            acc + cur.reading.as_ref().unwrap_or(&cur.surface)
+        })
+    }
+
+    /// Calculates the number of morae of the text by its reading.
+    pub fn count_morae(&self) -> usize {
+        count_morae(&self.reading())
+    }
+}
+
+/// Aligns the reading with the surface form, and gives furigana only to the
+/// parts other than kana, such as kanji.
+///
+/// The kana in the surface form, e.g. okurigana, are matched with the
+/// reading. The readings are converted into hiragana. If the reading does
+/// not match, the whole surface form is given the reading.
+///
+/// ```rust
+/// use lyrian::reading::align_furigana;
+///
+/// let text = align_furigana("飛び込む", "トビコム");
+/// assert_eq!(text.segments[0].reading, Some("と".to_string()));
+/// assert_eq!(text.segments[3].reading, None);
+/// ```
+pub fn align_furigana(surface: &str, reading: &str) -> FuriganaText {
+    let mut runs: Vec<(String, bool)> = Vec::new();
+    for c in surface.chars() {
+        match runs.last_mut() {
+            Some((run, kana)) if *kana == is_kana(c) => run.push(c),
+            _ => runs.push((c.to_string(), is_kana(c))),
+        }
+    }
+    let reading = reading
+        .chars()
+        .map(katakana_to_hiragana)
+        .collect::<Vec<char>>();
+
+    let segments = match align_runs(&runs, &reading) {
+        Some(lengths) => {
+            let mut start = 0;
+            runs.into_iter()
+                .zip(lengths)
+                .map(|((surface, kana), len)| {
+                    let part = reading[start..start + len].iter().collect::<String>();
+                    start += len;
+                    FuriganaSegment {
+                        surface,
+                        reading: if kana { None } else { Some(part) },
+                    }
+                })
+                .collect()
+        }
+        None => vec![FuriganaSegment {
+            surface: surface.to_string(),
+            reading: Some(reading.iter().collect()),
+        }],
+    };
+    FuriganaText { segments }
+}
+
+/// Finds the lengths of the reading which the runs of the surface form
+/// correspond to, where the runs of kana must match the reading.
+fn align_runs(runs: &[(String, bool)], reading: &[char]) -> Option<Vec<usize>> {
+    let ((run, kana), rest) = match runs.split_first() {
+        Some(first) => first,
+        None if reading.is_empty() => return Some(Vec::new()),
+        None => return None,
+    };
+
+    let candidates = if *kana {
+        let len = run.chars().count();
+        let matches = reading.len() >= len
+            && run
+                .chars()
+                .zip(reading)
+                .all(|(a, b)| hiragana_to_katakana(a) == hiragana_to_katakana(*b));
+        if matches {
+            len..len + 1
+        } else {
+            return None;
+        }
+    } else {
+        1..reading.len() + 1
+    };
+
+    for len in candidates {
+        if let Some(mut lengths) = align_runs(rest, &reading[len..]) {
+            lengths.insert(0, len);
+            return Some(lengths);
+        }
+    }
+    None
+}
+
+/// Parses text annotated with furigana.
+///
+/// The following styles are supported.
+///
+/// - `漢字(かんじ)` and `漢字（かんじ）`
+///     - Only kana in brackets just after kanji are regarded as furigana.
+/// - `漢字《かんじ》` and `｜漢字《かんじ》` of Aozora Bunko
+///     - `｜` marks the start of the base text.
+///
+/// ```rust
+/// use lyrian::reading::parse_furigana;
+///
+/// let text = parse_furigana("銀河《ぎんが》の｜お祭《まつ》り");
+/// assert_eq!(text.surface(), "銀河のお祭り");
+/// assert_eq!(text.reading(), "ぎんがのまつり");
+/// ```
+pub fn parse_furigana(text: &str) -> FuriganaText {
+    let mut segments = Vec::new();
+    let mut plain = String::new();
+    let mut marker: Option<(usize, char)> = None;
+
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        rest = &rest[c.len_utf8()..];
+
+        if c == '｜' || c == '|' {
+            if let Some((i, m)) = marker.replace((plain.len(), c)) {
+                plain.insert(i, m);
+            }
+            continue;
+        }
+
+        let close = match c {
+            '《' => '》',
+            '(' => ')',
+            '（' => '）',
+            _ => {
+                plain.push(c);
+                continue;
+            }
+        };
+
+        let reading = match rest.find(close) {
+            Some(end) => &rest[..end],
+            None => {
+                plain.push(c);
+                continue;
+            }
+        };
+
+        let start = match marker {
+            Some((i, _)) => i,
+            None => plain
+                .char_indices()
+                .rev()
+                .take_while(|(_, k)| is_kanji(*k) || *k == 'ヶ')
+                .last()
+                .map_or(plain.len(), |(i, _)| i),
+        };
+
+        let is_ruby = c == '《' || (!reading.is_empty() && reading.chars().all(is_kana));
+        if start == plain.len() || !is_ruby {
+            plain.push(c);
+            continue;
+        }
+
+        let surface = plain.split_off(start);
+        if !plain.is_empty() {
+            segments.push(FuriganaSegment {
+                surface: std::mem::take(&mut plain),
+                reading: None,
+            });
+        }
+        segments.push(FuriganaSegment {
+            surface,
+            reading: Some(reading.to_string()),
+        });
+        marker = None;
+        rest = &rest[reading.len() + close.len_utf8()..];
+    }
+
+    if let Some((i, m)) = marker {
+        plain.insert(i, m);
+    }
+    if !plain.is_empty() {
+        segments.push(FuriganaSegment {
+            surface: plain,
+            reading: None,
+        });
+    }
+
+    FuriganaText { segments }
+}
+
+#[cfg(test)]
+mod reading_test {
+    use crate::reading::{
+        align_furigana, parse_furigana, ReadingDictionary, ReadingOverrides, WordDictionary,
+    };
+
+    #[test]
+    fn read_text_by_longest_match() {
+        let mut dict = WordDictionary::new();
+        dict.insert("銀河", "ギンガ");
+        dict.insert("銀河鉄道", "ギンガテツドウ");
+        dict.insert("夜", "ヨル");
+        assert_eq!(
+            dict.read("銀河鉄道の夜"),
+            "ギンガテツドウのヨル".to_string()
+        )
+    }
+
+    #[test]
+    fn count_morae_by_reading() {
+        let mut dict = WordDictionary::new();
+        dict.insert("東京", "トウキョウ");
+        assert_eq!(dict.count_morae("東京"), 4)
+    }
+
+    #[test]
+    fn read_overridden_words_first() {
+        let mut dict = WordDictionary::new();
+        dict.insert("今日", "キョウ");
+        let mut overrides = ReadingOverrides::new();
+        overrides.insert("今日", "コンニチ");
+        assert_eq!(
+            overrides.read_with(&dict, "今日は今日"),
+            "コンニチはコンニチ".to_string()
+        )
+    }
+
+    #[cfg(feature = "tokenizer")]
+    #[test]
+    fn read_text_with_ipadic() {
+        use crate::reading::IpadicDictionary;
+
+        let dict = IpadicDictionary::new().unwrap();
+        assert_eq!(dict.read("東京"), "トウキョウ".to_string())
+    }
+
+    #[test]
+    fn parse_aozora_furigana() {
+        let text = parse_furigana("｜銀河鉄道《ぎんがてつどう》の夜《よる》");
+        assert_eq!(
+            (text.surface(), text.reading(), text.count_morae()),
+            (
+                "銀河鉄道の夜".to_string(),
+                "ぎんがてつどうのよる".to_string(),
+                10
+            )
+        )
+    }
+
+    #[test]
+    fn parse_furigana_in_brackets() {
+        let text = parse_furigana("古池（ふるいけ）や蛙(かわず)");
+        assert_eq!(text.reading(), "ふるいけやかわず".to_string())
+    }
+
+    #[test]
+    fn leave_brackets_which_are_not_furigana() {
+        let text = parse_furigana("手紙(笑)と(かな)");
+        assert_eq!(
+            (text.surface(), text.reading()),
+            (
+                "手紙(笑)と(かな)".to_string(),
+                "手紙(笑)と(かな)".to_string()
+            )
+        )
+    }
+
+    #[test]
+    fn align_reading_with_okurigana() {
+        let text = align_furigana("お祭り", "オマツリ");
+        assert_eq!(
+            text.segments
+                .iter()
+                .map(|s| s.reading.clone())
+                .collect::<Vec<_>>(),
+            vec![None, Some("まつ".to_string()), None]
+        )
+    }
+}
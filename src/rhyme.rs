@@ -0,0 +1,171 @@
+//! Detection of rhymes between phrases.
+
+use crate::chars::{
+    consonant_row_of, hiragana_to_katakana, vowel_of, Row, Vowel, LONG_VOWEL, SOKUON,
+};
+use crate::normalize::nfkc;
+use crate::pronunciation::split_morae;
+
+/// Sound of a mora which is compared in rhyming.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RhymeSound {
+    /// A mora which has a vowel.
+    Vowel(Vowel),
+
+    /// The moraic nasal (`ン`).
+    Nasal,
+
+    /// The geminate consonant (`ッ`).
+    Geminate,
+}
+
+/// Returns the sounds of the morae of kana text.
+///
+/// The text is normalized in NFKC before splitting into morae. `ー` has the
+/// same vowel as the previous mora.
+///
+/// ```rust
+/// use lyrian::chars::Vowel;
+/// use lyrian::rhyme::{vowel_pattern, RhymeSound};
+///
+/// assert_eq!(
+///     vowel_pattern("ラーメン"),
+///     vec![
+///         RhymeSound::Vowel(Vowel::A),
+///         RhymeSound::Vowel(Vowel::A),
+///         RhymeSound::Vowel(Vowel::E),
+///         RhymeSound::Nasal,
+///     ]
+/// );
+/// ```
+pub fn vowel_pattern(kana: &str) -> Vec<RhymeSound> {
+    let kana = nfkc(kana);
+    let mut pattern = Vec::new();
+    for mora in split_morae(&kana) {
+        let head = mora.chars().next().unwrap_or_default();
+        let sound = if head == LONG_VOWEL {
+            match pattern.last() {
+                Some(RhymeSound::Vowel(v)) => Some(RhymeSound::Vowel(*v)),
+                _ => None,
+            }
+        } else if SOKUON.contains(&head) {
+            Some(RhymeSound::Geminate)
+        } else if head == 'ン' || head == 'ん' {
+            Some(RhymeSound::Nasal)
+        } else {
+            vowel_of(mora).map(RhymeSound::Vowel)
+        };
+
+        if let Some(sound) = sound {
+            pattern.push(sound);
+        }
+    }
+    pattern
+}
+
+/// Returns whether the final morae of two phrases rhyme.
+///
+/// The phrases rhyme when the sounds of their last `morae` morae are the
+/// same. The phrases must be kana; read the ones which contain kanji with
+/// [`ReadingDictionary`](crate::reading::ReadingDictionary) in advance.
+///
+/// ```rust
+/// use lyrian::rhyme::rhymes;
+///
+/// assert!(rhymes("カンパイ", "ランタイ", 3));
+/// assert!(!rhymes("カンパイ", "センパイ", 4));
+/// ```
+pub fn rhymes(a: &str, b: &str, morae: usize) -> bool {
+    let a = vowel_pattern(a);
+    let b = vowel_pattern(b);
+    if morae == 0 || a.len() < morae || b.len() < morae {
+        return false;
+    }
+    a[a.len() - morae..] == b[b.len() - morae..]
+}
+
+/// Returns the rows of the morae of kana text.
+///
+/// The text is normalized in NFKC before splitting into morae. `ン`, `ッ` and
+/// `ー` have no row.
+///
+/// ```rust
+/// use lyrian::chars::Row;
+/// use lyrian::rhyme::consonant_pattern;
+///
+/// assert_eq!(
+///     consonant_pattern("カッパ"),
+///     vec![Some(Row::K), None, Some(Row::P)]
+/// );
+/// ```
+pub fn consonant_pattern(kana: &str) -> Vec<Option<Row>> {
+    split_morae(&nfkc(kana))
+        .into_iter()
+        .map(consonant_row_of)
+        .collect()
+}
+
+/// Returns whether the initial morae of two phrases alliterate.
+///
+/// The phrases alliterate when the rows of their first `morae` morae are the
+/// same. The morae which have no row, such as `ン`, must be the same
+/// characters. The phrases must be kana like [`rhymes()`].
+///
+/// ```rust
+/// use lyrian::rhyme::alliterates;
+///
+/// assert!(alliterates("かえるぴょこぴょこ", "くるみ", 1));
+/// assert!(!alliterates("なまむぎ", "なまたまご", 3));
+/// ```
+pub fn alliterates(a: &str, b: &str, morae: usize) -> bool {
+    let a = nfkc(a);
+    let b = nfkc(b);
+    let a = split_morae(&a);
+    let b = split_morae(&b);
+    if morae == 0 || a.len() < morae || b.len() < morae {
+        return false;
+    }
+    a.iter().zip(b.iter()).take(morae).all(|(x, y)| {
+        match (consonant_row_of(x), consonant_row_of(y)) {
+            (None, None) => x
+                .chars()
+                .map(hiragana_to_katakana)
+                .eq(y.chars().map(hiragana_to_katakana)),
+            (x, y) => x == y,
+        }
+    })
+}
+
+#[cfg(test)]
+mod rhyme_test {
+    use crate::rhyme::{alliterates, rhymes};
+
+    #[test]
+    fn detect_end_rhyme() {
+        assert!(rhymes("きみのゆめ", "あかいつめ", 2))
+    }
+
+    #[test]
+    fn detect_rhyme_with_long_vowel() {
+        assert!(rhymes("コーヒー", "ほおびい", 4))
+    }
+
+    #[test]
+    fn do_not_rhyme_shorter_phrase() {
+        assert!(!rhymes("ア", "カ", 2))
+    }
+
+    #[test]
+    fn detect_alliteration() {
+        assert!(alliterates(
+            "とうきょうとっきょきょかきょく",
+            "たけたてかけた",
+            1
+        ))
+    }
+
+    #[test]
+    fn distinguish_the_moraic_nasal_from_the_geminate() {
+        assert!(!alliterates("カンパ", "カッパ", 2))
+    }
+}
@@ -0,0 +1,357 @@
+//! Conversion between romaji and kana.
+
+use crate::chars::hiragana_to_katakana;
+
+/// Romaji syllables and their katakana, including both Hepburn and Kunrei
+/// spellings.
+#[rustfmt::skip]
+const ROMAJI_TABLE: &[(&str, &str)] = &[
+    ("a", "ア"), ("i", "イ"), ("u", "ウ"), ("e", "エ"), ("o", "オ"),
+    ("ka", "カ"), ("ki", "キ"), ("ku", "ク"), ("ke", "ケ"), ("ko", "コ"),
+    ("kya", "キャ"), ("kyu", "キュ"), ("kyo", "キョ"),
+    ("ga", "ガ"), ("gi", "ギ"), ("gu", "グ"), ("ge", "ゲ"), ("go", "ゴ"),
+    ("gya", "ギャ"), ("gyu", "ギュ"), ("gyo", "ギョ"),
+    ("sa", "サ"), ("si", "シ"), ("shi", "シ"), ("su", "ス"), ("se", "セ"), ("so", "ソ"),
+    ("sya", "シャ"), ("syu", "シュ"), ("syo", "ショ"), ("sye", "シェ"),
+    ("sha", "シャ"), ("shu", "シュ"), ("sho", "ショ"), ("she", "シェ"),
+    ("za", "ザ"), ("zi", "ジ"), ("ji", "ジ"), ("zu", "ズ"), ("ze", "ゼ"), ("zo", "ゾ"),
+    ("zya", "ジャ"), ("zyu", "ジュ"), ("zyo", "ジョ"), ("zye", "ジェ"),
+    ("ja", "ジャ"), ("ju", "ジュ"), ("jo", "ジョ"), ("je", "ジェ"),
+    ("jya", "ジャ"), ("jyu", "ジュ"), ("jyo", "ジョ"),
+    ("ta", "タ"), ("ti", "チ"), ("chi", "チ"), ("tu", "ツ"), ("tsu", "ツ"), ("te", "テ"), ("to", "ト"),
+    ("tya", "チャ"), ("tyu", "チュ"), ("tyo", "チョ"), ("tye", "チェ"),
+    ("cha", "チャ"), ("chu", "チュ"), ("cho", "チョ"), ("che", "チェ"),
+    ("tsa", "ツァ"), ("tsi", "ツィ"), ("tse", "ツェ"), ("tso", "ツォ"),
+    ("da", "ダ"), ("di", "ヂ"), ("du", "ヅ"), ("de", "デ"), ("do", "ド"),
+    ("dya", "ヂャ"), ("dyu", "ヂュ"), ("dyo", "ヂョ"),
+    ("na", "ナ"), ("ni", "ニ"), ("nu", "ヌ"), ("ne", "ネ"), ("no", "ノ"),
+    ("nya", "ニャ"), ("nyu", "ニュ"), ("nyo", "ニョ"),
+    ("ha", "ハ"), ("hi", "ヒ"), ("hu", "フ"), ("fu", "フ"), ("he", "ヘ"), ("ho", "ホ"),
+    ("hya", "ヒャ"), ("hyu", "ヒュ"), ("hyo", "ヒョ"),
+    ("fa", "ファ"), ("fi", "フィ"), ("fe", "フェ"), ("fo", "フォ"),
+    ("ba", "バ"), ("bi", "ビ"), ("bu", "ブ"), ("be", "ベ"), ("bo", "ボ"),
+    ("bya", "ビャ"), ("byu", "ビュ"), ("byo", "ビョ"),
+    ("pa", "パ"), ("pi", "ピ"), ("pu", "プ"), ("pe", "ペ"), ("po", "ポ"),
+    ("pya", "ピャ"), ("pyu", "ピュ"), ("pyo", "ピョ"),
+    ("ma", "マ"), ("mi", "ミ"), ("mu", "ム"), ("me", "メ"), ("mo", "モ"),
+    ("mya", "ミャ"), ("myu", "ミュ"), ("myo", "ミョ"),
+    ("ya", "ヤ"), ("yu", "ユ"), ("ye", "イェ"), ("yo", "ヨ"),
+    ("ra", "ラ"), ("ri", "リ"), ("ru", "ル"), ("re", "レ"), ("ro", "ロ"),
+    ("rya", "リャ"), ("ryu", "リュ"), ("ryo", "リョ"),
+    ("wa", "ワ"), ("wi", "ウィ"), ("we", "ウェ"), ("wo", "ヲ"),
+    ("va", "ヴァ"), ("vi", "ヴィ"), ("vu", "ヴ"), ("ve", "ヴェ"), ("vo", "ヴォ"),
+    ("xa", "ァ"), ("xi", "ィ"), ("xu", "ゥ"), ("xe", "ェ"), ("xo", "ォ"),
+    ("la", "ァ"), ("li", "ィ"), ("lu", "ゥ"), ("le", "ェ"), ("lo", "ォ"),
+    ("xya", "ャ"), ("xyu", "ュ"), ("xyo", "ョ"), ("xwa", "ヮ"),
+    ("lya", "ャ"), ("lyu", "ュ"), ("lyo", "ョ"), ("lwa", "ヮ"),
+    ("xtu", "ッ"), ("xtsu", "ッ"), ("ltu", "ッ"), ("ltsu", "ッ"),
+    ("-", "ー"),
+];
+
+/// Katakana and their Hepburn romaji.
+#[rustfmt::skip]
+const KANA_TABLE: &[(&str, &str)] = &[
+    ("ア", "a"), ("イ", "i"), ("ウ", "u"), ("エ", "e"), ("オ", "o"),
+    ("カ", "ka"), ("キ", "ki"), ("ク", "ku"), ("ケ", "ke"), ("コ", "ko"),
+    ("ガ", "ga"), ("ギ", "gi"), ("グ", "gu"), ("ゲ", "ge"), ("ゴ", "go"),
+    ("サ", "sa"), ("シ", "shi"), ("ス", "su"), ("セ", "se"), ("ソ", "so"),
+    ("ザ", "za"), ("ジ", "ji"), ("ズ", "zu"), ("ゼ", "ze"), ("ゾ", "zo"),
+    ("タ", "ta"), ("チ", "chi"), ("ツ", "tsu"), ("テ", "te"), ("ト", "to"),
+    ("ダ", "da"), ("ヂ", "ji"), ("ヅ", "zu"), ("デ", "de"), ("ド", "do"),
+    ("ナ", "na"), ("ニ", "ni"), ("ヌ", "nu"), ("ネ", "ne"), ("ノ", "no"),
+    ("ハ", "ha"), ("ヒ", "hi"), ("フ", "fu"), ("ヘ", "he"), ("ホ", "ho"),
+    ("バ", "ba"), ("ビ", "bi"), ("ブ", "bu"), ("ベ", "be"), ("ボ", "bo"),
+    ("パ", "pa"), ("ピ", "pi"), ("プ", "pu"), ("ペ", "pe"), ("ポ", "po"),
+    ("マ", "ma"), ("ミ", "mi"), ("ム", "mu"), ("メ", "me"), ("モ", "mo"),
+    ("ヤ", "ya"), ("ユ", "yu"), ("ヨ", "yo"),
+    ("ラ", "ra"), ("リ", "ri"), ("ル", "ru"), ("レ", "re"), ("ロ", "ro"),
+    ("ワ", "wa"), ("ヰ", "i"), ("ヱ", "e"), ("ヲ", "o"), ("ン", "n"), ("ヴ", "vu"),
+    ("ァ", "a"), ("ィ", "i"), ("ゥ", "u"), ("ェ", "e"), ("ォ", "o"),
+    ("ャ", "ya"), ("ュ", "yu"), ("ョ", "yo"), ("ヮ", "wa"), ("ヵ", "ka"), ("ヶ", "ke"),
+    ("キャ", "kya"), ("キュ", "kyu"), ("キョ", "kyo"),
+    ("ギャ", "gya"), ("ギュ", "gyu"), ("ギョ", "gyo"),
+    ("シャ", "sha"), ("シュ", "shu"), ("ショ", "sho"), ("シェ", "she"),
+    ("ジャ", "ja"), ("ジュ", "ju"), ("ジョ", "jo"), ("ジェ", "je"),
+    ("チャ", "cha"), ("チュ", "chu"), ("チョ", "cho"), ("チェ", "che"),
+    ("ヂャ", "ja"), ("ヂュ", "ju"), ("ヂョ", "jo"),
+    ("ニャ", "nya"), ("ニュ", "nyu"), ("ニョ", "nyo"),
+    ("ヒャ", "hya"), ("ヒュ", "hyu"), ("ヒョ", "hyo"),
+    ("ビャ", "bya"), ("ビュ", "byu"), ("ビョ", "byo"),
+    ("ピャ", "pya"), ("ピュ", "pyu"), ("ピョ", "pyo"),
+    ("ミャ", "mya"), ("ミュ", "myu"), ("ミョ", "myo"),
+    ("リャ", "rya"), ("リュ", "ryu"), ("リョ", "ryo"),
+    ("ファ", "fa"), ("フィ", "fi"), ("フェ", "fe"), ("フォ", "fo"),
+    ("ヴァ", "va"), ("ヴィ", "vi"), ("ヴェ", "ve"), ("ヴォ", "vo"),
+    ("ウィ", "wi"), ("ウェ", "we"), ("ウォ", "wo"), ("イェ", "ye"),
+    ("ティ", "ti"), ("ディ", "di"), ("トゥ", "tu"), ("ドゥ", "du"),
+    ("テュ", "tyu"), ("デュ", "dyu"),
+    ("ツァ", "tsa"), ("ツィ", "tsi"), ("ツェ", "tse"), ("ツォ", "tso"),
+];
+
+/// Converts romaji into katakana.
+///
+/// Both Hepburn (`shi`, `tsu`, `cha`) and Kunrei (`si`, `tu`, `tya`) spellings
+/// are supported. Doubled consonants become `ッ`, and `n` which is not
+/// followed by a vowel becomes `ン`; write `n'` to separate `ン` from the next
+/// vowel, like `kan'i`. Vowels with a macron or a circumflex are followed by
+/// `ー`. Characters which are not romaji are left as they are.
+///
+/// ```rust
+/// use lyrian::romaji::to_katakana;
+///
+/// assert_eq!(to_katakana("shinkansen"), "シンカンセン");
+/// assert_eq!(to_katakana("kitte"), "キッテ");
+/// ```
+pub fn to_katakana(romaji: &str) -> String {
+    let chars = romaji
+        .chars()
+        .flat_map(|c| c.to_lowercase())
+        .flat_map(expand_long_vowel)
+        .collect::<Vec<char>>();
+
+    let mut kana = String::with_capacity(romaji.len() * 3);
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+
+        if c == 'n' && next == Some('\'') {
+            kana.push('ン');
+            i += 2;
+            continue;
+        }
+
+        if let Some((len, syllable)) = longest_syllable(&chars[i..]) {
+            kana.push_str(syllable);
+            i += len;
+            continue;
+        }
+
+        match (c, next) {
+            ('n', _) => kana.push('ン'),
+            ('m', Some('b')) | ('m', Some('p')) | ('m', Some('m')) => kana.push('ン'),
+            ('t', Some('c')) => kana.push('ッ'),
+            (c, Some(n)) if c == n && c.is_ascii_alphabetic() && !is_vowel(c) => kana.push('ッ'),
+            _ => kana.push(c),
+        }
+        i += 1;
+    }
+
+    kana
+}
+
+/// Converts katakana and hiragana into Hepburn romaji.
+///
+/// `ッ` doubles the next consonant, or is written as `'` when no consonant
+/// follows it, `ン` before a vowel is written as `n'`, and `ー` puts a macron
+/// on the previous vowel, or is written as `-` after other than a vowel.
+/// Characters which are not kana are left as they are.
+///
+/// ```rust
+/// use lyrian::romaji::to_romaji;
+///
+/// assert_eq!(to_romaji("しんかんせん"), "shinkansen");
+/// assert_eq!(to_romaji("ラーメン"), "rāmen");
+/// assert_eq!(to_romaji("アッ"), "a'");
+/// ```
+pub fn to_romaji(kana: &str) -> String {
+    let chars = kana
+        .chars()
+        .map(hiragana_to_katakana)
+        .collect::<Vec<char>>();
+
+    let mut romaji = String::with_capacity(kana.len());
+    let mut geminate = false;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+
+        if c == 'ッ' {
+            geminate = true;
+            i += 1;
+            continue;
+        }
+
+        if c == 'ー' {
+            match romaji.pop() {
+                Some(v) if is_vowel(v) => romaji.push(long_vowel(v)),
+                Some(v) if "āīūēō".contains(v) => romaji.push(v),
+                Some(v) => {
+                    romaji.push(v);
+                    romaji.push('-');
+                }
+                None => romaji.push('-'),
+            }
+            i += 1;
+            continue;
+        }
+
+        let (len, syllable) = match longest_kana(&chars[i..]) {
+            Some(v) => v,
+            None => {
+                if geminate {
+                    romaji.push('\'');
+                }
+                romaji.push(c);
+                geminate = false;
+                i += 1;
+                continue;
+            }
+        };
+
+        if geminate {
+            if syllable.starts_with("ch") {
+                romaji.push('t');
+            } else {
+                match syllable.chars().next().filter(|h| !is_vowel(*h)) {
+                    Some(head) => romaji.push(head),
+                    None => romaji.push('\''),
+                }
+            }
+            geminate = false;
+        }
+
+        if i > 0 && chars[i - 1] == 'ン' && syllable.starts_with(|h| is_vowel(h) || h == 'y') {
+            romaji.push('\'');
+        }
+
+        romaji.push_str(syllable);
+        i += len;
+    }
+    if geminate {
+        romaji.push('\'');
+    }
+
+    romaji
+}
+
+/// Returns the length and romaji of the longest katakana syllable at the head
+/// of `chars`.
+fn longest_kana(chars: &[char]) -> Option<(usize, &'static str)> {
+    (1..=2.min(chars.len())).rev().find_map(|len| {
+        let head = chars[..len].iter().collect::<String>();
+        KANA_TABLE
+            .iter()
+            .find(|(kana, _)| *kana == head)
+            .map(|(_, romaji)| (len, *romaji))
+    })
+}
+
+/// Puts a macron on the vowel.
+fn long_vowel(c: char) -> char {
+    match c {
+        'a' => 'ā',
+        'i' => 'ī',
+        'u' => 'ū',
+        'e' => 'ē',
+        'o' => 'ō',
+        _ => c,
+    }
+}
+
+/// Returns the length and katakana of the longest romaji syllable at the head
+/// of `chars`.
+fn longest_syllable(chars: &[char]) -> Option<(usize, &'static str)> {
+    (1..=4.min(chars.len())).rev().find_map(|len| {
+        let head = chars[..len].iter().collect::<String>();
+        ROMAJI_TABLE
+            .iter()
+            .find(|(romaji, _)| *romaji == head)
+            .map(|(_, kana)| (len, *kana))
+    })
+}
+
+/// Expands a vowel with a macron or a circumflex into the vowel and `-`.
+fn expand_long_vowel(c: char) -> Vec<char> {
+    match c {
+        'ā' | 'â' => vec!['a', '-'],
+        'ī' | 'î' => vec!['i', '-'],
+        'ū' | 'û' => vec!['u', '-'],
+        'ē' | 'ê' => vec!['e', '-'],
+        'ō' | 'ô' => vec!['o', '-'],
+        _ => vec![c],
+    }
+}
+
+/// Returns whether the character is a vowel of romaji.
+fn is_vowel(c: char) -> bool {
+    matches!(c, 'a' | 'i' | 'u' | 'e' | 'o')
+}
+
+#[cfg(test)]
+mod romaji_test {
+    use crate::romaji::{to_katakana, to_romaji};
+
+    #[test]
+    fn convert_hepburn_romaji() {
+        assert_eq!(
+            to_katakana("Tsukemono to shōchū"),
+            "ツケモノ ト ショーチュー"
+        )
+    }
+
+    #[test]
+    fn convert_kunrei_romaji() {
+        assert_eq!(
+            to_katakana("tukemono to syoochuu"),
+            "ツケモノ ト ショオチュウ"
+        )
+    }
+
+    #[test]
+    fn convert_doubled_consonants() {
+        assert_eq!(to_katakana("matcha to kitte"), "マッチャ ト キッテ")
+    }
+
+    #[test]
+    fn disambiguate_syllabic_n() {
+        assert_eq!(
+            (
+                to_katakana("kon'ya"),
+                to_katakana("konya"),
+                to_katakana("shimbun")
+            ),
+            (
+                "コンヤ".to_string(),
+                "コニャ".to_string(),
+                "シンブン".to_string()
+            )
+        )
+    }
+
+    #[test]
+    fn convert_kana_into_romaji() {
+        assert_eq!(to_romaji("ぎんがてつどうのよる"), "gingatetsudounoyoru")
+    }
+
+    #[test]
+    fn convert_geminate_consonants_into_romaji() {
+        assert_eq!(to_romaji("マッチャとキッテ"), "matchatokitte")
+    }
+
+    #[test]
+    fn keep_geminate_consonants_without_next_consonants() {
+        assert_eq!(
+            (to_romaji("アッ"), to_romaji("エッ!"), to_romaji("アッア")),
+            ("a'".to_string(), "e'!".to_string(), "a'a".to_string())
+        )
+    }
+
+    #[test]
+    fn separate_syllabic_n_in_romaji() {
+        assert_eq!(to_romaji("コンヤ、シンブン"), "kon'ya、shinbun")
+    }
+
+    #[test]
+    fn put_macron_on_long_vowel() {
+        assert_eq!(to_romaji("コーヒー"), "kōhī")
+    }
+
+    #[test]
+    fn keep_long_vowel_mark_after_syllabic_n() {
+        assert_eq!(
+            (to_romaji("ンー"), to_romaji("ラーー")),
+            ("n-".to_string(), "rā".to_string())
+        )
+    }
+}
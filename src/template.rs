@@ -0,0 +1,228 @@
+//! Generation of sentences by templates whose slots are filled by a model.
+
+use std::str::FromStr;
+
+use crate::model::LyrianModel;
+use crate::morphological_analysis::LyrianToken;
+
+/// A template of sentences, whose slots in braces are filled with the tokens
+/// of their parts of speech sampled from a model.
+///
+/// The slots are `{noun}`, `{verb}`, `{adjective}`, `{adverb}`,
+/// `{adnominal}`, `{particle}`, `{auxiliary}`, or the parts of speech of
+/// the dictionary themselves such as `{名詞}`. `{{` and `}}` are the braces
+/// themselves.
+///
+/// ```rust
+/// use lyrian::model::LyrianModel;
+/// use lyrian::template::Template;
+///
+/// let template = "{noun}が{adjective}{noun}を食べた".parse::<Template>().unwrap();
+/// assert_eq!(template.slots(), vec!["名詞", "形容詞", "名詞"]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+/// A part of [`Template`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    /// The text as it is.
+    Text(String),
+    /// The slot of the part of speech.
+    Slot(String),
+}
+
+impl FromStr for Template {
+    type Err = String;
+
+    /// Parses a template, which fails on the unmatched or empty braces.
+    fn from_str(template: &str) -> Result<Template, String> {
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let mut chars = template.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some('{') | None => {
+                                return Err("Unmatched `{` in the template.".to_string())
+                            }
+                            Some(c) => name.push(c),
+                        }
+                    }
+                    if name.trim().is_empty() {
+                        return Err("Empty slot in the template.".to_string());
+                    }
+                    if !text.is_empty() {
+                        parts.push(Part::Text(text.split_off(0)));
+                    }
+                    parts.push(Part::Slot(part_of_speech(name.trim()).to_string()));
+                }
+                '}' => return Err("Unmatched `}` in the template.".to_string()),
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            parts.push(Part::Text(text));
+        }
+        Ok(Template { parts })
+    }
+}
+
+impl Template {
+    /// Returns the parts of speech of the slots in order.
+    pub fn slots(&self) -> Vec<&str> {
+        self.parts
+            .iter()
+            .filter_map(|p| match p {
+                Part::Slot(pos) => Some(pos.as_str()),
+                Part::Text(_) => None,
+            })
+            .collect()
+    }
+
+    /// Fills the slots with the tokens sampled from the model.
+    ///
+    /// Each slot follows the token before it, which is the token of the
+    /// previous slot, or the longest word in the model which the text
+    /// before the slot ends with. The slots which can not follow it are
+    /// filled by the frequencies of the tokens. An error is returned if no
+    /// token of the part of speech of a slot is in the model.
+    ///
+    /// ```rust
+    /// use lyrian::model::LyrianModel;
+    /// use lyrian::template::Template;
+    ///
+    /// let mut model = LyrianModel::chars_from("あいう。");
+    /// let template = "{文字}「{{}}」".parse::<Template>().unwrap();
+    /// assert_eq!(template.fill(&mut model).unwrap().chars().count(), 5);
+    /// ```
+    pub fn fill(&self, model: &mut LyrianModel) -> Result<String, String> {
+        let mut sentence = String::new();
+        let mut last = None;
+        for part in &self.parts {
+            let pos = match part {
+                Part::Text(text) => {
+                    sentence.push_str(text);
+                    last = longest_suffix(model, &sentence);
+                    continue;
+                }
+                Part::Slot(pos) => pos,
+            };
+            let matches = |_: usize, t: &LyrianToken| match t.part_of_speech == *pos {
+                true => 1.0,
+                false => 0.0,
+            };
+            let token = match &last {
+                Some(token) if model.follow(token) => model.next_token_by(matches),
+                _ => None,
+            };
+            let token = match token.or_else(|| model.sample_token_by(matches)) {
+                Some(token) => token,
+                None => {
+                    model.initialize();
+                    return Err(format!("No token of `{}` is in the model.", pos));
+                }
+            };
+            sentence.push_str(&token.word);
+            last = Some(token);
+        }
+        model.initialize();
+        Ok(sentence)
+    }
+}
+
+/// Converts the name of a slot into the part of speech of IPADIC.
+fn part_of_speech(name: &str) -> &str {
+    match name {
+        "noun" => "名詞",
+        "verb" => "動詞",
+        "adjective" => "形容詞",
+        "adverb" => "副詞",
+        "adnominal" => "連体詞",
+        "particle" => "助詞",
+        "auxiliary" => "助動詞",
+        name => name,
+    }
+}
+
+/// Returns the most frequent token of the longest word in the model which
+/// the text ends with.
+fn longest_suffix(model: &LyrianModel, text: &str) -> Option<LyrianToken> {
+    model
+        .tokens()
+        .iter()
+        .filter(|t| !t.word.is_empty() && text.ends_with(t.word.as_str()))
+        .max_by_key(|t| (t.word.len(), model.frequency(t)))
+        .cloned()
+}
+
+#[cfg(test)]
+mod template_test {
+    use crate::model::LyrianModel;
+    use crate::morphological_analysis::token;
+    use crate::template::{Part, Template};
+
+    #[test]
+    fn parse_slots_and_braces() {
+        let template = "{noun}が{{{ 形容詞 }}}".parse::<Template>();
+        assert_eq!(
+            (
+                template.map(|t| t.parts),
+                "{noun".parse::<Template>().is_err(),
+                "}".parse::<Template>().is_err()
+            ),
+            (
+                Ok(vec![
+                    Part::Slot("名詞".to_string()),
+                    Part::Text("が{".to_string()),
+                    Part::Slot("形容詞".to_string()),
+                    Part::Text("}".to_string())
+                ]),
+                true,
+                true
+            )
+        )
+    }
+
+    #[test]
+    fn fill_slots_by_parts_of_speech() {
+        let mut model = LyrianModel::from_sequences(&[vec![
+            token("猫", "名詞"),
+            token("が", "助詞"),
+            token("白い", "形容詞"),
+            token("魚", "名詞"),
+            token("を", "助詞"),
+            token("食べ", "動詞"),
+        ]]);
+        let template = "{noun}が{adjective}{noun}を食べた"
+            .parse::<Template>()
+            .unwrap();
+        let sentences = (0..10)
+            .map(|_| template.fill(&mut model).unwrap())
+            .collect::<Vec<String>>();
+        assert!(
+            sentences
+                .iter()
+                .all(|s| s == "猫が白い魚を食べた" || s == "魚が白い魚を食べた")
+                && "{adverb}"
+                    .parse::<Template>()
+                    .unwrap()
+                    .fill(&mut model)
+                    .is_err()
+        )
+    }
+}
@@ -0,0 +1,780 @@
+//! Morphological analysis of Japanese text.
+//!
+//! The analyzers implement [`Tokenizer`], and can be used interchangeably.
+//! [`LinderaTokenizer`] is enabled by the `tokenizer` feature,
+//! [`VibratoTokenizer`] by the `vibrato` feature, [`HfTokenizer`] by the
+//! `huggingface` feature, and [`MecabTokenizer`] by the `mecab` feature.
+
+#[cfg(feature = "tokenizer")]
+use std::cell::RefCell;
+#[cfg(feature = "vibrato")]
+use std::fs::File;
+#[cfg(feature = "mecab")]
+use std::io::Write;
+#[cfg(feature = "vibrato")]
+use std::io::{BufReader, Read};
+#[cfg(any(feature = "vibrato", feature = "huggingface"))]
+use std::path::Path;
+#[cfg(feature = "mecab")]
+use std::path::PathBuf;
+#[cfg(feature = "mecab")]
+use std::process::{Command, Stdio};
+#[cfg(feature = "mecab")]
+use std::thread;
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+use crate::chars::{hiragana_to_katakana, is_japanese_punct, is_kana, LONG_VOWEL, SYMBOLS};
+use crate::corpus::{split_sentences, TERMINATORS};
+pub use crate::morphological_analysis::LyrianToken;
+#[cfg(any(feature = "tokenizer", feature = "vibrato", feature = "mecab"))]
+use crate::normalize::fold_halfwidth_kana;
+
+/// Morphological analyzer which splits text into tokens.
+///
+/// The tokens have the surface forms, the readings in katakana and the parts
+/// of speech in the format of IPADIC, and the words which are not in the
+/// dictionary have `unknown`. The tokenizers are used interchangeably to
+/// build models, count morae and find haiku.
+///
+/// The functions and closures of text are also tokenizers, which is the
+/// easiest way to use another analyzer.
+///
+/// ```rust
+/// use lyrian::pronunciation::count_morae_by;
+/// use lyrian::tokenize::LyrianToken;
+///
+/// let split = |text: &str| -> Result<Vec<LyrianToken>, String> {
+///     Ok(text
+///         .split_whitespace()
+///         .map(|w| LyrianToken::new(w.into(), w.into(), w.into(), "名詞".into()))
+///         .collect())
+/// };
+/// assert_eq!(count_morae_by("フルイケ ヤ", &split), Ok(5));
+/// ```
+pub trait Tokenizer {
+    /// Tokenizes the text.
+    fn tokenize(&self, text: &str) -> Result<Vec<LyrianToken>, String>;
+}
+
+impl<F: Fn(&str) -> Result<Vec<LyrianToken>, String>> Tokenizer for F {
+    fn tokenize(&self, text: &str) -> Result<Vec<LyrianToken>, String> {
+        self(text)
+    }
+}
+
+/// Tokenizer by [lindera](https://github.com/lindera-morphology/lindera)
+/// with IPADIC.
+#[cfg(feature = "tokenizer")]
+pub struct LinderaTokenizer {
+    tokenizer: RefCell<lindera::tokenizer::Tokenizer>,
+}
+
+#[cfg(feature = "tokenizer")]
+impl LinderaTokenizer {
+    /// Creates a new instance of [`LinderaTokenizer`].
+    ///
+    /// Loading the dictionary takes a while, so reuse the instance.
+    pub fn new() -> Result<LinderaTokenizer, String> {
+        match lindera::tokenizer::Tokenizer::new() {
+            Ok(tokenizer) => Ok(LinderaTokenizer {
+                tokenizer: RefCell::new(tokenizer),
+            }),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+}
+
+#[cfg(feature = "tokenizer")]
+impl Tokenizer for LinderaTokenizer {
+    fn tokenize(&self, text: &str) -> Result<Vec<LyrianToken>, String> {
+        tokenize_with(&mut self.tokenizer.borrow_mut(), text)
+    }
+}
+
+/// Tokenizer by [vibrato](https://github.com/daac-tools/vibrato), which is
+/// much faster than lindera for large corpora.
+///
+/// The dictionary is compiled by vibrato from IPADIC or its variants, e.g.
+/// `system.dic` of the released ones decompressed from zstd.
+///
+/// ```no_run
+/// use lyrian::model::LyrianModel;
+/// use lyrian::tokenize::VibratoTokenizer;
+///
+/// let tokenizer = VibratoTokenizer::load("ipadic-mecab-2_7_0/system.dic").unwrap();
+/// let model = LyrianModel::from_text_with("古池や蛙飛び込む水の音", &tokenizer).unwrap();
+/// ```
+#[cfg(feature = "vibrato")]
+pub struct VibratoTokenizer {
+    tokenizer: vibrato::Tokenizer,
+}
+
+#[cfg(feature = "vibrato")]
+impl VibratoTokenizer {
+    /// Reads the compiled dictionary of vibrato.
+    pub fn from_reader<R: Read>(reader: R) -> Result<VibratoTokenizer, String> {
+        match vibrato::Dictionary::read(reader) {
+            Ok(dict) => Ok(VibratoTokenizer {
+                tokenizer: vibrato::Tokenizer::new(dict),
+            }),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    /// Loads the compiled dictionary of vibrato from a file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<VibratoTokenizer, String> {
+        match File::open(path) {
+            Ok(file) => VibratoTokenizer::from_reader(BufReader::new(file)),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+}
+
+#[cfg(feature = "vibrato")]
+impl Tokenizer for VibratoTokenizer {
+    fn tokenize(&self, text: &str) -> Result<Vec<LyrianToken>, String> {
+        let mut worker = self.tokenizer.new_worker();
+        worker.reset_sentence(fold_halfwidth_kana(text));
+        worker.tokenize();
+        Ok(worker
+            .token_iter()
+            .map(|t| {
+                let features = t.feature().split(',').collect::<Vec<&str>>();
+                to_token(t.surface(), &features)
+            })
+            .collect())
+    }
+}
+
+/// Tokenizer by the [tokenizers](https://github.com/huggingface/tokenizers)
+/// of Hugging Face, such as the subword tokenizers of pretrained models.
+///
+/// The surface forms of the tokens are taken from the text by their offsets,
+/// so the special tokens and the markers of byte-level BPE like `Ġ` do not
+/// appear. The tokens in kana have their readings, and the others are
+/// counted as `0` morae.
+///
+/// ```no_run
+/// use lyrian::model::LyrianModel;
+/// use lyrian::tokenize::HfTokenizer;
+///
+/// let tokenizer = HfTokenizer::from_file("tokenizer.json").unwrap();
+/// let model = LyrianModel::from_text_with("古池や蛙飛び込む水の音", &tokenizer).unwrap();
+/// ```
+#[cfg(feature = "huggingface")]
+pub struct HfTokenizer {
+    tokenizer: tokenizers::Tokenizer,
+}
+
+#[cfg(feature = "huggingface")]
+impl HfTokenizer {
+    /// Creates a new instance of [`HfTokenizer`] from a tokenizer of
+    /// Hugging Face.
+    pub fn new(tokenizer: tokenizers::Tokenizer) -> HfTokenizer {
+        HfTokenizer { tokenizer }
+    }
+
+    /// Builds a new tokenizer from the json data of Hugging Face, such as
+    /// the contents of `tokenizer.json`.
+    pub fn from_json(json: &str) -> Result<HfTokenizer, String> {
+        match json.parse() {
+            Ok(tokenizer) => Ok(HfTokenizer::new(tokenizer)),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    /// Loads a tokenizer from the json file of Hugging Face, such as
+    /// `tokenizer.json`.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<HfTokenizer, String> {
+        match tokenizers::Tokenizer::from_file(path) {
+            Ok(tokenizer) => Ok(HfTokenizer::new(tokenizer)),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+}
+
+#[cfg(feature = "huggingface")]
+impl Tokenizer for HfTokenizer {
+    fn tokenize(&self, text: &str) -> Result<Vec<LyrianToken>, String> {
+        let encoding = self
+            .tokenizer
+            .encode(text, false)
+            .map_err(|e| e.to_string())?;
+        Ok(encoding
+            .get_offsets()
+            .iter()
+            .filter_map(|(start, end)| text.get(*start..*end))
+            .filter(|word| !word.is_empty())
+            .map(surface_token)
+            .collect())
+    }
+}
+
+/// Tokenizer which runs the external [MeCab](https://taku910.github.io/mecab/)
+/// command, for the tuned dictionaries such as NEologd.
+///
+/// The dictionary has to give the features in the format of IPADIC.
+///
+/// ```no_run
+/// use lyrian::model::LyrianModel;
+/// use lyrian::tokenize::MecabTokenizer;
+///
+/// let tokenizer = MecabTokenizer::new()
+///     .with_dictionary("/usr/lib/mecab/dic/mecab-ipadic-neologd");
+/// let model = LyrianModel::from_text_with("古池や蛙飛び込む水の音", &tokenizer).unwrap();
+/// ```
+#[cfg(feature = "mecab")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MecabTokenizer {
+    program: PathBuf,
+    dictionary: Option<PathBuf>,
+}
+
+#[cfg(feature = "mecab")]
+impl Default for MecabTokenizer {
+    fn default() -> MecabTokenizer {
+        MecabTokenizer::new()
+    }
+}
+
+#[cfg(feature = "mecab")]
+impl MecabTokenizer {
+    /// Creates a new instance of [`MecabTokenizer`], which runs `mecab` in
+    /// `PATH` with its default dictionary.
+    pub fn new() -> MecabTokenizer {
+        MecabTokenizer {
+            program: PathBuf::from("mecab"),
+            dictionary: None,
+        }
+    }
+
+    /// Runs the program instead of `mecab` in `PATH`.
+    pub fn with_program<P: Into<PathBuf>>(mut self, program: P) -> MecabTokenizer {
+        self.program = program.into();
+        self
+    }
+
+    /// Uses the dictionary in the directory, which is given by `-d`.
+    pub fn with_dictionary<P: Into<PathBuf>>(mut self, dictionary: P) -> MecabTokenizer {
+        self.dictionary = Some(dictionary.into());
+        self
+    }
+}
+
+#[cfg(feature = "mecab")]
+impl Tokenizer for MecabTokenizer {
+    fn tokenize(&self, text: &str) -> Result<Vec<LyrianToken>, String> {
+        let mut command = Command::new(&self.program);
+        if let Some(dictionary) = &self.dictionary {
+            command.arg("-d").arg(dictionary);
+        }
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| e.to_string())?;
+
+        // The input is written in another thread, so that MeCab does not
+        // block on the output of a large text.
+        let mut stdin = child.stdin.take().ok_or("Could not open stdin of MeCab.")?;
+        let input = fold_halfwidth_kana(text);
+        let writer = thread::spawn(move || stdin.write_all(input.as_bytes()));
+        let output = child.wait_with_output().map_err(|e| e.to_string())?;
+        match writer.join() {
+            Ok(result) => result.map_err(|e| e.to_string())?,
+            Err(_) => return Err("Could not write the text to MeCab.".to_string()),
+        }
+        if !output.status.success() {
+            return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+        }
+
+        Ok(parse_mecab(&String::from_utf8_lossy(&output.stdout)))
+    }
+}
+
+/// Parses the output of MeCab, whose lines have a word and its features
+/// separated by a tab, and the sentences end with `EOS`.
+#[cfg(any(test, feature = "mecab"))]
+fn parse_mecab(output: &str) -> Vec<LyrianToken> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (word, features) = line.split_once('\t')?;
+            Some(to_token(word, &features.split(',').collect::<Vec<&str>>()))
+        })
+        .collect()
+}
+
+/// Tokenizer by byte-pair encoding, whose subwords are learned from a
+/// corpus without dictionaries.
+///
+/// The training starts from the characters of the words separated by
+/// whitespace in each sentence, and merges the most frequent pair of
+/// adjacent subwords repeatedly. It can split the languages and the slang
+/// which the dictionaries of morphological analysis miss. The subwords in
+/// kana have their readings, and the others are counted as `0` morae.
+///
+/// The tokenizer can be saved in json by `serde_json`.
+///
+/// ```rust
+/// use lyrian::model::LyrianModel;
+/// use lyrian::tokenize::{BpeTokenizer, Tokenizer};
+///
+/// let corpus = "ぴえんこえてぱおん。ぴえんぴえん。ぱおんです。";
+/// let bpe = BpeTokenizer::train(corpus, 10);
+/// let words = bpe.tokenize("ぴえんぱおん").unwrap();
+/// assert_eq!(words.iter().map(|t| t.word.as_str()).collect::<Vec<&str>>(), vec!["ぴえん", "ぱおん"]);
+///
+/// let model = LyrianModel::from_text_with(corpus, &bpe).unwrap();
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(from = "Vec<(String, String)>", into = "Vec<(String, String)>")]
+pub struct BpeTokenizer {
+    merges: Vec<(String, String)>,
+    #[serde(skip)]
+    ranks: HashMap<(String, String), usize>,
+}
+
+impl BpeTokenizer {
+    /// Learns up to `merges` merges from the corpus, stopping when no pair
+    /// of subwords appears twice.
+    pub fn train(corpus: &str, merges: usize) -> BpeTokenizer {
+        let mut words = BTreeMap::new();
+        for word in split_sentences(corpus)
+            .into_iter()
+            .flat_map(|s| s.split_whitespace())
+        {
+            *words.entry(word).or_insert(0) += 1;
+        }
+        let mut words = words
+            .into_iter()
+            .map(|(w, n)| (w.chars().map(|c| c.to_string()).collect(), n))
+            .collect::<Vec<(Vec<String>, usize)>>();
+
+        let mut learned = Vec::new();
+        for _ in 0..merges {
+            let mut pairs = BTreeMap::new();
+            for (subwords, n) in &words {
+                for pair in subwords.windows(2) {
+                    *pairs.entry((&pair[0], &pair[1])).or_insert(0) += n;
+                }
+            }
+            // The first of the most frequent pairs in order is merged.
+            let best = pairs
+                .into_iter()
+                .rev()
+                .max_by_key(|(_, n)| *n)
+                .filter(|(_, n)| *n >= 2)
+                .map(|((a, b), _)| (a.clone(), b.clone()));
+            let pair = match best {
+                Some(pair) => pair,
+                None => break,
+            };
+            for (subwords, _) in &mut words {
+                merge_pair(subwords, &pair);
+            }
+            learned.push(pair);
+        }
+        BpeTokenizer::from(learned)
+    }
+
+    /// Returns the merges in the order of learning.
+    pub fn merges(&self) -> &[(String, String)] {
+        &self.merges
+    }
+
+    /// Splits a word into subwords by the merges.
+    fn split(&self, word: &str) -> Vec<String> {
+        let mut subwords = word.chars().map(|c| c.to_string()).collect::<Vec<String>>();
+        loop {
+            let best = subwords
+                .windows(2)
+                .filter_map(|p| self.ranks.get(&(p[0].clone(), p[1].clone())))
+                .min();
+            match best {
+                Some(rank) => merge_pair(&mut subwords, &self.merges[*rank]),
+                None => return subwords,
+            }
+        }
+    }
+}
+
+impl From<Vec<(String, String)>> for BpeTokenizer {
+    fn from(merges: Vec<(String, String)>) -> BpeTokenizer {
+        let ranks = merges
+            .iter()
+            .enumerate()
+            .map(|(i, pair)| (pair.clone(), i))
+            .collect();
+        BpeTokenizer { merges, ranks }
+    }
+}
+
+impl From<BpeTokenizer> for Vec<(String, String)> {
+    fn from(tokenizer: BpeTokenizer) -> Vec<(String, String)> {
+        tokenizer.merges
+    }
+}
+
+impl Tokenizer for BpeTokenizer {
+    fn tokenize(&self, text: &str) -> Result<Vec<LyrianToken>, String> {
+        Ok(text
+            .split_whitespace()
+            .flat_map(|w| self.split(w))
+            .map(|w| surface_token(&w))
+            .collect())
+    }
+}
+
+/// Merges the adjacent pairs of subwords from the start.
+fn merge_pair(subwords: &mut Vec<String>, pair: &(String, String)) {
+    let mut i = 0;
+    while i + 1 < subwords.len() {
+        if subwords[i] == pair.0 && subwords[i + 1] == pair.1 {
+            let next = subwords.remove(i + 1);
+            subwords[i].push_str(&next);
+        }
+        i += 1;
+    }
+}
+
+/// Converts a word without its features into a token, where the words in
+/// kana have their readings and the symbols are `記号`.
+pub(crate) fn surface_token(word: &str) -> LyrianToken {
+    let is_symbol =
+        |c: char| is_japanese_punct(c) || SYMBOLS.contains(&c) || TERMINATORS.contains(&c);
+    let (reading, part_of_speech) = if word.chars().all(|c| is_kana(c) || c == LONG_VOWEL) {
+        (word.chars().map(hiragana_to_katakana).collect(), "文字")
+    } else if word.chars().all(is_symbol) {
+        (word.to_string(), "記号")
+    } else {
+        ("unknown".to_string(), "文字")
+    };
+    LyrianToken::new(
+        word.to_string(),
+        reading.clone(),
+        reading,
+        part_of_speech.to_string(),
+    )
+}
+
+/// The parts of speech of the content words kept by
+/// [`TokenFilter::with_content_words_only()`].
+pub const CONTENT_WORDS: [&str; 5] = ["名詞", "動詞", "形容詞", "副詞", "連体詞"];
+
+/// Filter of the tokens by their parts of speech, to clean the output of a
+/// tokenizer before training.
+///
+/// All the tokens are kept by default.
+///
+/// ```rust
+/// use lyrian::model::LyrianModel;
+/// use lyrian::tokenize::TokenFilter;
+///
+/// let filter = TokenFilter::new()
+///     .with_symbols_dropped(true)
+///     .with_fillers_dropped(true);
+/// let model = LyrianModel::from_text_filtered("えーと、古池や。", &filter).unwrap();
+/// assert!(model.tokens().iter().all(|t| t.word != "。"));
+/// ```
+///
+/// The tokens of other tokenizers are filtered by
+/// [`filter()`](#method.filter), e.g. in a closure given to
+/// [`LyrianModel::from_text_with()`](crate::model::LyrianModel::from_text_with).
+#[derive(Default)]
+pub struct TokenFilter {
+    drop_symbols: bool,
+    drop_fillers: bool,
+    content_only: bool,
+    predicate: Option<PosPredicate>,
+}
+
+/// The predicate on the parts of speech of [`TokenFilter`].
+type PosPredicate = Box<dyn Fn(&str) -> bool>;
+
+impl TokenFilter {
+    /// Creates a new instance of [`TokenFilter`], which keeps all the
+    /// tokens.
+    pub fn new() -> TokenFilter {
+        TokenFilter::default()
+    }
+
+    /// Drops the symbols (`記号`), such as punctuation.
+    pub fn with_symbols_dropped(mut self, drop: bool) -> TokenFilter {
+        self.drop_symbols = drop;
+        self
+    }
+
+    /// Drops the fillers (`フィラー`), such as `えーと`.
+    pub fn with_fillers_dropped(mut self, drop: bool) -> TokenFilter {
+        self.drop_fillers = drop;
+        self
+    }
+
+    /// Keeps only the content words, whose parts of speech are in
+    /// [`CONTENT_WORDS`].
+    pub fn with_content_words_only(mut self, content_only: bool) -> TokenFilter {
+        self.content_only = content_only;
+        self
+    }
+
+    /// Keeps only the tokens whose parts of speech satisfy the predicate,
+    /// in addition to the other conditions.
+    pub fn with_predicate<F: Fn(&str) -> bool + 'static>(mut self, predicate: F) -> TokenFilter {
+        self.predicate = Some(Box::new(predicate));
+        self
+    }
+
+    /// Returns whether the token is kept.
+    pub fn keeps(&self, token: &LyrianToken) -> bool {
+        let pos = token.part_of_speech.as_str();
+        if self.drop_symbols && pos == "記号" || self.drop_fillers && pos == "フィラー" {
+            return false;
+        }
+        if self.content_only && !CONTENT_WORDS.contains(&pos) {
+            return false;
+        }
+        match &self.predicate {
+            Some(predicate) => predicate(pos),
+            None => true,
+        }
+    }
+
+    /// Removes the tokens which are not kept.
+    pub fn filter(&self, tokens: Vec<LyrianToken>) -> Vec<LyrianToken> {
+        tokens.into_iter().filter(|t| self.keeps(t)).collect()
+    }
+}
+
+/// Tokenizes contents in morphological analysis.
+///
+/// Lyrian uses [lindera](https://github.com/lindera-morphology/lindera) crate
+/// for morphological analysis.
+///
+/// Half-width katakana in `contents` are folded into full-width ones before
+/// the analysis.
+#[cfg(feature = "tokenizer")]
+pub fn tokenize(contents: &str) -> Result<Vec<LyrianToken>, String> {
+    match lindera::tokenizer::Tokenizer::new() {
+        Ok(mut tokenizer) => tokenize_with(&mut tokenizer, contents),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Tokenizes contents with an existing lindera tokenizer.
+#[cfg(feature = "tokenizer")]
+pub(crate) fn tokenize_with(
+    tokenizer: &mut lindera::tokenizer::Tokenizer,
+    contents: &str,
+) -> Result<Vec<LyrianToken>, String> {
+    let lin_tokens;
+
+    let contents = fold_halfwidth_kana(contents);
+    match tokenizer.tokenize(&*contents) {
+        Ok(v) => lin_tokens = v,
+        Err(e) => return Err(e.to_string()),
+    }
+
+    let lyr_tokens = lin_tokens
+        .iter()
+        .map(|t| to_token(t.text, &t.detail))
+        .collect();
+
+    Ok(lyr_tokens)
+}
+
+/// Converts a word and its features in the format of IPADIC into a token.
+///
+/// The features of the unknown words, which lack the readings, give the
+/// token of `unknown`.
+#[cfg(any(test, feature = "tokenizer", feature = "vibrato", feature = "mecab"))]
+fn to_token<S: AsRef<str>>(word: &str, features: &[S]) -> LyrianToken {
+    if features.len() > 8 {
+        LyrianToken::new(
+            word.to_string(),
+            features[7].as_ref().to_string(),
+            features[8].as_ref().to_string(),
+            features[0].as_ref().to_string(),
+        )
+    } else {
+        LyrianToken::new(
+            word.to_string(),
+            String::from("unknown"),
+            String::from("unknown"),
+            String::from("unknown"),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tokenize_test {
+    use crate::tokenize::{parse_mecab, to_token, BpeTokenizer, Tokenizer};
+
+    #[cfg(feature = "tokenizer")]
+    #[test]
+    fn get_lyrian_tokens_from_text() {
+        use crate::tokenize::{tokenize, LyrianToken};
+
+        let text = "すもももももももものうち";
+        match tokenize(text) {
+            Ok(tokens) => {
+                let expected = vec![
+                    LyrianToken::new(
+                        "すもも".to_string(),
+                        "スモモ".to_string(),
+                        "スモモ".to_string(),
+                        "名詞".to_string(),
+                    ),
+                    LyrianToken::new(
+                        "も".to_string(),
+                        "モ".to_string(),
+                        "モ".to_string(),
+                        "助詞".to_string(),
+                    ),
+                    LyrianToken::new(
+                        "もも".to_string(),
+                        "モモ".to_string(),
+                        "モモ".to_string(),
+                        "名詞".to_string(),
+                    ),
+                    LyrianToken::new(
+                        "も".to_string(),
+                        "モ".to_string(),
+                        "モ".to_string(),
+                        "助詞".to_string(),
+                    ),
+                    LyrianToken::new(
+                        "もも".to_string(),
+                        "モモ".to_string(),
+                        "モモ".to_string(),
+                        "名詞".to_string(),
+                    ),
+                    LyrianToken::new(
+                        "の".to_string(),
+                        "ノ".to_string(),
+                        "ノ".to_string(),
+                        "助詞".to_string(),
+                    ),
+                    LyrianToken::new(
+                        "うち".to_string(),
+                        "ウチ".to_string(),
+                        "ウチ".to_string(),
+                        "名詞".to_string(),
+                    ),
+                ];
+                assert_eq!(tokens, expected)
+            }
+            Err(msg) => panic!("{}", msg),
+        }
+    }
+
+    #[test]
+    fn convert_features_into_token() {
+        let features = "名詞,一般,*,*,*,*,古池,フルイケ,フルイケ"
+            .split(',')
+            .collect::<Vec<&str>>();
+        assert_eq!(
+            (
+                to_token("古池", &features).mora,
+                to_token("ほげ", &["名詞"]).mora
+            ),
+            ("フルイケ".to_string(), "unknown".to_string())
+        )
+    }
+
+    #[test]
+    fn parse_output_of_mecab() {
+        let output = "古池\t名詞,一般,*,*,*,*,古池,フルイケ,フルイケ\nや\t助詞,係助詞,*,*,*,*,や,ヤ,ヤ\nEOS\n";
+        let tokens = parse_mecab(output);
+        assert_eq!(
+            tokens
+                .iter()
+                .map(|t| t.mora.as_str())
+                .collect::<Vec<&str>>(),
+            vec!["フルイケ", "ヤ"]
+        )
+    }
+
+    #[test]
+    fn filter_tokens_by_part_of_speech() {
+        use crate::tokenize::{LyrianToken, TokenFilter};
+
+        let tokens = [
+            ("古池", "名詞"),
+            ("や", "助詞"),
+            ("えーと", "フィラー"),
+            ("。", "記号"),
+        ]
+        .iter()
+        .map(|(w, p)| LyrianToken::new(w.to_string(), w.to_string(), w.to_string(), p.to_string()))
+        .collect::<Vec<LyrianToken>>();
+        let words = |filter: TokenFilter| {
+            filter
+                .filter(tokens.clone())
+                .iter()
+                .map(|t| t.word.clone())
+                .collect::<Vec<String>>()
+        };
+        assert_eq!(
+            (
+                words(
+                    TokenFilter::new()
+                        .with_symbols_dropped(true)
+                        .with_fillers_dropped(true)
+                ),
+                words(TokenFilter::new().with_content_words_only(true)),
+                words(TokenFilter::new().with_predicate(|p| p != "名詞")).len()
+            ),
+            (
+                vec!["古池".to_string(), "や".to_string()],
+                vec!["古池".to_string()],
+                3
+            )
+        )
+    }
+
+    #[test]
+    fn learn_merges_of_byte_pair_encoding() {
+        let bpe = BpeTokenizer::train("あいう あいう あい", 10);
+        let json = serde_json::to_string(&bpe).unwrap();
+        let words = bpe
+            .tokenize("あいうえ")
+            .unwrap()
+            .into_iter()
+            .map(|t| t.word)
+            .collect::<Vec<String>>();
+        assert_eq!(
+            (words, serde_json::from_str::<BpeTokenizer>(&json).unwrap()),
+            (vec!["あいう".to_string(), "え".to_string()], bpe)
+        )
+    }
+
+    #[cfg(feature = "huggingface")]
+    #[test]
+    fn take_surfaces_of_huggingface_tokens() {
+        use crate::tokenize::HfTokenizer;
+
+        let json = r#"{"version": "1.0", "truncation": null, "padding": null, "added_tokens": [],
+            "normalizer": null, "pre_tokenizer": {"type": "WhitespaceSplit"},
+            "post_processor": null, "decoder": null,
+            "model": {"type": "WordLevel", "vocab": {"ふるいけ": 0, "や": 1, "[UNK]": 2}, "unk_token": "[UNK]"}}"#;
+        let tokenizer = HfTokenizer::from_json(json).unwrap();
+        let words = tokenizer
+            .tokenize("ふるいけ や")
+            .unwrap()
+            .into_iter()
+            .map(|t| (t.word, t.mora))
+            .collect::<Vec<(String, String)>>();
+        assert_eq!(
+            words,
+            vec![
+                ("ふるいけ".to_string(), "フルイケ".to_string()),
+                ("や".to_string(), "ヤ".to_string())
+            ]
+        )
+    }
+}